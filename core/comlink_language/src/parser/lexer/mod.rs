@@ -16,6 +16,8 @@ pub enum LexerTokenData {
     Comma,
     Dot,
     Equals,
+    Colon,
+    Semicolon,
     Whitespace,
     Identifier,
     IntNumber,
@@ -13,7 +13,7 @@ impl<'a> Tokenizer<'a> {
         match self.chars.next() {
             None => None,
             Some(c) => {
-                self.offset += 1;
+                self.offset += c.len_utf8();
                 Some(c)
             }
         }
@@ -98,6 +98,8 @@ impl<'a> Tokenizer<'a> {
             ',' => self.token(LexerTokenData::Comma),
             '.' => self.token(LexerTokenData::Dot),
             '=' => self.token(LexerTokenData::Equals),
+            ':' => self.token(LexerTokenData::Colon),
+            ';' => self.token(LexerTokenData::Semicolon),
             // whitespace (but not newline)
             a if a.is_whitespace() => {
                 self.advance_if(|c| c.is_whitespace() && c != '\n');
@@ -1,12 +1,37 @@
 mod lexer;
 mod syntax;
 
-pub use syntax::{AstNode, CstNode, CstToken, ParserError, ProfileDocumentNode};
+pub use syntax::{
+    AstNode, CstNode, CstToken, LiteralNode, MapHeaderNode, MapManifest, MapManifestError,
+    ParserError, ProfileDocumentDefinitionNode, ProfileDocumentNode, ProfileId, ProfileVersion,
+    ProfileVersionRange, TextEdit, UseCaseDefinitionExampleNode, UseCaseDefinitionNode,
+};
 
 pub fn parse_profile(source: &str) -> (ProfileDocumentNode, Vec<ParserError>) {
     ProfileDocumentNode::parse_root(source)
 }
 
+/// Parses the header of a map, which contains the map manifest.
+///
+/// Maps are JavaScript, so only the `const manifest = { ... };` declaration at the start of the map is parsed.
+/// Returns `None` if the map doesn't start with a manifest.
+pub fn parse_map_header(source: &str) -> Option<(MapHeaderNode, Vec<ParserError>)> {
+    let len = MapHeaderNode::source_len(source)?;
+
+    Some(MapHeaderNode::parse_root(&source[..len]))
+}
+
+/// Reparses `profile` after `edit` is applied to its source, reusing the unchanged parts of the tree.
+///
+/// `errors` must be the errors returned when `profile` was parsed.
+pub fn reparse_profile(
+    profile: &ProfileDocumentNode,
+    errors: &[ParserError],
+    edit: &TextEdit,
+) -> (ProfileDocumentNode, Vec<ParserError>) {
+    profile.reparse(errors, edit)
+}
+
 #[cfg(test)]
 pub mod testing {
     use std::fmt::Write;
@@ -1,6 +1,6 @@
 mod tree;
 
-pub use tree::{nodes::*, tokens::*, AstNode, CstNode, CstToken, Parser, ParserError};
+pub use tree::{nodes::*, tokens::*, AstNode, CstNode, CstToken, Parser, ParserError, TextEdit};
 
 /// All syntax token kinds that this parser can produce.
 ///
@@ -35,6 +35,10 @@ pub enum SyntaxKind {
     Dot,
     /// `=`
     Equals,
+    /// `:`
+    Colon,
+    /// `;`
+    Semicolon,
     /// ` `
     Whitespace,
     /// `abcd123`
@@ -69,6 +73,8 @@ pub enum SyntaxKind {
     KeywordModel,
     KeywordField,
     KeywordEnum,
+    KeywordConst,
+    KeywordManifest,
     // --- specialization tokens --- //
     UseCaseSafety,
     StringDoc,
@@ -107,6 +113,8 @@ pub enum SyntaxKind {
     UseCaseDefinitionExampleError,
     NamedModelDefinition,
     NamedFieldDefinition,
+    MapHeader,
+    MapHeaderField,
     /// The entire source file
     ProfileDocument,
     /// End of file marker.
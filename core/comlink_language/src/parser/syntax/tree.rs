@@ -9,12 +9,14 @@ use super::SyntaxElement;
 mod location;
 pub mod nodes;
 mod parser;
+mod reparse;
 pub mod serde;
 pub mod tokens;
 
 pub use self::{
     location::{Location, LocationSpan},
     parser::{Parser, ParserError},
+    reparse::TextEdit,
 };
 use location::RawLocation;
 
@@ -40,6 +42,8 @@ pub trait TreeParser {
         String: From<M>;
     /// Returns the kind of the next token.
     fn peek(&self) -> SyntaxKind;
+    /// Returns the text of the next token.
+    fn peek_text(&self) -> &str;
     /// Like [`Self::peek`] but transforms valid identifiers into keywords.
     fn peek_keyword(&self) -> SyntaxKind;
     /// Skip any number of tokens `T` and return how many were skipped.
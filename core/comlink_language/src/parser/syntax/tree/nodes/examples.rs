@@ -23,6 +23,20 @@ impl LiteralValue<'_> {
         }
     }
 }
+impl From<LiteralValue<'_>> for serde_json::Value {
+    fn from(value: LiteralValue<'_>) -> Self {
+        match value {
+            LiteralValue::None => Self::Null,
+            LiteralValue::String(v) => Self::String(v.into_owned()),
+            LiteralValue::Bool(v) => Self::Bool(v),
+            // not finite floats cannot be represented in JSON
+            LiteralValue::FloatNumber(v) => serde_json::Number::from_f64(v)
+                .map(Self::Number)
+                .unwrap_or(Self::Null),
+            LiteralValue::IntNumber(v) => Self::Number((v as i64).into()),
+        }
+    }
+}
 impl<'a> From<&'a str> for LiteralValue<'a> {
     fn from(value: &'a str) -> Self {
         Self::String(value.into())
@@ -160,6 +174,18 @@ impl UseCaseDefinitionExampleErrorNode {
     }
 }
 
+impl UseCaseDefinitionExampleNode {
+    /// Input of the example as a JSON object, empty if the example has no input.
+    ///
+    /// Returns `None` if the input is not a complete literal.
+    pub fn input_value(&self) -> Option<serde_json::Map<StdString, serde_json::Value>> {
+        match self.input() {
+            None => Some(serde_json::Map::new()),
+            Some(input) => input.literal()?.json_value(),
+        }
+    }
+}
+
 node! {
     #[derive(Serialize, Deserialize)]
     #[serde(untagged)]
@@ -184,6 +210,16 @@ node! {
         }
     }
 }
+impl LiteralNode {
+    /// Converts the literal into a JSON value, `None` if the literal is not complete.
+    pub fn json_value(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::PrimitiveLiteral(node) => node.value().map(serde_json::Value::from),
+            Self::ListLiteral(node) => node.json_value().map(serde_json::Value::Array),
+            Self::ObjectLiteral(node) => node.json_value().map(serde_json::Value::Object),
+        }
+    }
+}
 node! {
     pub struct PrimitiveLiteralNode = PrimitiveLiteral;
     parse(p) {
@@ -258,6 +294,13 @@ impl ListLiteralNode {
     pub fn elements(&self) -> impl Iterator<Item = LiteralNode> {
         self.filter_nodes()
     }
+
+    /// Converts the literal into JSON values, `None` if any of the elements is not complete.
+    pub fn json_value(&self) -> Option<Vec<serde_json::Value>> {
+        self.elements()
+            .map(|element| element.json_value())
+            .collect()
+    }
 }
 node! {
     pub struct ObjectLiteralNode = ObjectLiteral;
@@ -293,6 +336,36 @@ impl ObjectLiteralNode {
     pub fn fields(&self) -> impl Iterator<Item = ObjectLiteralFieldNode> {
         self.filter_nodes()
     }
+
+    /// Converts the literal into a JSON object, `None` if any of the fields is not complete.
+    ///
+    /// Dotted keys such as `a.b = 1` create nested objects.
+    pub fn json_value(&self) -> Option<serde_json::Map<StdString, serde_json::Value>> {
+        let mut result = serde_json::Map::new();
+
+        for field in self.fields() {
+            let key = field
+                .key()
+                .map(|k| k.value().map(|v| v.into_owned()))
+                .collect::<Option<Vec<_>>>()?;
+            let value = field.literal()?.json_value()?;
+
+            let (last, path) = key.split_last()?;
+            let mut object = &mut result;
+            for part in path {
+                let entry = object
+                    .entry(part.clone())
+                    .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+                if !entry.is_object() {
+                    *entry = serde_json::Value::Object(serde_json::Map::new());
+                }
+                object = entry.as_object_mut().unwrap();
+            }
+            object.insert(last.clone(), value);
+        }
+
+        Some(result)
+    }
 }
 node! {
     pub struct ObjectLiteralFieldNode = ObjectLiteralField;
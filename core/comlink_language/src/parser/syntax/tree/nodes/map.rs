@@ -0,0 +1,208 @@
+//! Map header nodes.
+//!
+//! Maps are plain JavaScript, the only part of a map which is parsed is the manifest declared at its very beginning:
+//!
+//! ```js
+//! const manifest = {
+//!   profile: 'scope/name@1.2',
+//!   provider: 'provider-name',
+//!   variant: 'variant-name', // optional
+//!   version: '1.0.0' // optional
+//! };
+//! ```
+
+use std::str::FromStr;
+
+use crate::parser::lexer::{tokenize, LexerTokenData};
+
+use super::*;
+
+/// Like [`TreeParser::expect`] but also requires the identifier to be `keyword`.
+fn expect_contextual_keyword<T: CstToken>(
+    p: &mut impl TreeParser,
+    keyword: &str,
+    recovery_set: SyntaxKindSet,
+) {
+    if p.peek() == Identifier && p.peek_text() == keyword {
+        p.token::<T>();
+    } else {
+        p.error(T::EXPECT_MESSAGE, recovery_set);
+    }
+}
+
+const MAP_HEADER_RECOVERY: SyntaxKindSet =
+    SyntaxKindSet::from_static_slice(&[Equals, BraceLeft, BraceRight]);
+node! {
+    pub struct MapHeaderNode = MapHeader;
+    parse(p) {
+        p.skip::<NewlineToken>();
+        expect_contextual_keyword::<KeywordConstToken>(p, "const", MAP_HEADER_RECOVERY);
+        expect_contextual_keyword::<KeywordManifestToken>(p, "manifest", MAP_HEADER_RECOVERY);
+        p.expect::<EqualsToken>(MAP_HEADER_RECOVERY);
+        p.expect::<BraceLeftToken>(MAP_HEADER_RECOVERY);
+        loop {
+            p.skip::<NewlineToken>();
+            match p.peek() {
+                BraceRight | EndOfFile => break,
+                _ => MapHeaderFieldNode::parse(p)
+            }
+            // require a newline or a comma
+            match p.peek() {
+                Newline => p.token::<NewlineToken>(),
+                Comma => p.token::<CommaToken>(),
+                BraceRight => break,
+                _ => {
+                    p.error(
+                        "manifest fields must be newline or comma terminated",
+                        MAP_HEADER_RECOVERY,
+                    );
+                    break;
+                }
+            }
+        }
+        p.skip::<NewlineToken>();
+        p.expect::<BraceRightToken>(SyntaxKindSet::empty());
+        p.opt::<SemicolonToken>();
+    }
+}
+impl MapHeaderNode {
+    /// Returns the length of the header at the start of `source`.
+    ///
+    /// The header spans from the start of the map until the end of the manifest declaration.
+    /// Returns `None` if the map doesn't start with a manifest.
+    pub fn source_len(source: &str) -> Option<usize> {
+        let mut tokens = tokenize(source)
+            .filter(|t| {
+                !matches!(
+                    t.data,
+                    LexerTokenData::Whitespace
+                        | LexerTokenData::Newline
+                        | LexerTokenData::LineComment
+                )
+            })
+            .peekable();
+
+        for keyword in ["const", "manifest"] {
+            match tokens.next() {
+                Some(t)
+                    if matches!(t.data, LexerTokenData::Identifier)
+                        && &source[t.offset..][..t.len] == keyword => {}
+                _ => return None,
+            }
+        }
+
+        let mut depth = 0usize;
+        for token in tokens.by_ref() {
+            match token.data {
+                LexerTokenData::BraceLeft => depth += 1,
+                LexerTokenData::BraceRight if depth <= 1 => {
+                    let end = match tokens.peek() {
+                        Some(t) if matches!(t.data, LexerTokenData::Semicolon) => t.offset + t.len,
+                        _ => token.offset + token.len,
+                    };
+                    return Some(end);
+                }
+                LexerTokenData::BraceRight => depth -= 1,
+                _ => (),
+            }
+        }
+
+        Some(source.len())
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = MapHeaderFieldNode> {
+        self.filter_nodes()
+    }
+
+    /// Returns the field with given `name`.
+    pub fn field(&self, name: &str) -> Option<MapHeaderFieldNode> {
+        self.fields().find(|field| {
+            field
+                .name()
+                .and_then(|n| n.value().map(|v| v == name))
+                .unwrap_or(false)
+        })
+    }
+
+    /// Interprets the fields of this header as a map manifest.
+    pub fn manifest(&self) -> Result<MapManifest, MapManifestError> {
+        let field_value = |name: &'static str| -> Result<Option<StdString>, MapManifestError> {
+            match self.field(name) {
+                None => Ok(None),
+                Some(field) => match field.value().and_then(|v| v.value().map(|v| v.to_string())) {
+                    None => Err(MapManifestError::InvalidField(
+                        name,
+                        field.as_ref().text().to_string(),
+                    )),
+                    Some(value) => Ok(Some(value)),
+                },
+            }
+        };
+
+        let profile = field_value("profile")?.ok_or(MapManifestError::MissingField("profile"))?;
+        let (profile_id, profile_version) = profile
+            .split_once('@')
+            .ok_or_else(|| MapManifestError::InvalidField("profile", profile.clone()))?;
+        let profile_version = ProfileVersionRepr::from_str(profile_version)
+            .map_err(|_| MapManifestError::InvalidField("profile", profile.clone()))?;
+        if profile_id.is_empty() {
+            return Err(MapManifestError::InvalidField("profile", profile.clone()));
+        }
+
+        let provider =
+            field_value("provider")?.ok_or(MapManifestError::MissingField("provider"))?;
+        let variant = field_value("variant")?;
+        let version = field_value("version")?;
+
+        Ok(MapManifest {
+            profile: ProfileId::from(profile_id),
+            profile_version,
+            provider,
+            variant,
+            version,
+        })
+    }
+}
+
+node! {
+    pub struct MapHeaderFieldNode = MapHeaderField;
+    parse(p) {
+        const RECOVERY: SyntaxKindSet = SyntaxKindSet::from_static_slice(&[Newline, Comma, BraceRight]);
+
+        p.expect::<FieldNameToken>(RECOVERY);
+        p.expect::<ColonToken>(RECOVERY);
+        p.expect::<StringLiteralToken>(RECOVERY);
+    }
+}
+impl MapHeaderFieldNode {
+    pub fn name(&self) -> Option<FieldNameToken> {
+        self.find_token()
+    }
+    pub fn value(&self) -> Option<StringLiteralToken> {
+        self.find_token()
+    }
+}
+
+/// Information declared by the map manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapManifest {
+    /// Profile this map implements.
+    pub profile: ProfileId,
+    /// Version of the profile this map was written for.
+    pub profile_version: ProfileVersionRepr,
+    /// Provider this map is written for.
+    pub provider: StdString,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<StdString>,
+    /// Version or revision of the map itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<StdString>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MapManifestError {
+    #[error("Map manifest is missing field \"{0}\"")]
+    MissingField(&'static str),
+    #[error("Map manifest field \"{0}\" has invalid value: {1}")]
+    InvalidField(&'static str, StdString),
+}
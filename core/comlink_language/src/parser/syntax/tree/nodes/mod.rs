@@ -89,8 +89,9 @@ macro_rules! node {
 }
 
 mod examples;
+mod map;
 mod types;
-pub use self::{examples::*, types::*};
+pub use self::{examples::*, map::*, types::*};
 
 fn opt_string_doc(p: &mut impl TreeParser) {
     if p.opt::<StringDocToken>() {
@@ -264,7 +265,7 @@ node! {
 
         loop {
             p.skip::<NewlineToken>();
-            if p.peek() == BraceRight {
+            if matches!(p.peek(), BraceRight | EndOfFile) {
                 break;
             }
             UseCaseDefinitionExampleNode::parse(p);
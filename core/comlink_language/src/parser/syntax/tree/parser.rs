@@ -11,7 +11,7 @@ pub struct ParserError {
     pub offset: usize,
 }
 
-struct ParserToken {
+pub(super) struct ParserToken {
     pub kind: SyntaxKind,
     pub offset: usize,
     pub len: usize,
@@ -77,7 +77,7 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn map_token(lexer_token: LexerToken) -> ParserToken {
+    pub(super) fn map_token(lexer_token: LexerToken) -> ParserToken {
         let kind = match lexer_token.data {
             LexerTokenData::Error => SyntaxKind::Error,
             LexerTokenData::Newline => SyntaxKind::Newline,
@@ -92,6 +92,8 @@ impl<'a> Parser<'a> {
             LexerTokenData::Comma => SyntaxKind::Comma,
             LexerTokenData::Dot => SyntaxKind::Dot,
             LexerTokenData::Equals => SyntaxKind::Equals,
+            LexerTokenData::Colon => SyntaxKind::Colon,
+            LexerTokenData::Semicolon => SyntaxKind::Semicolon,
             LexerTokenData::Whitespace => SyntaxKind::Whitespace,
             LexerTokenData::Identifier => SyntaxKind::Identifier,
             LexerTokenData::IntNumber => SyntaxKind::IntNumber,
@@ -108,25 +110,72 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// Returns the text of the next token.
-    fn peek_text(&self) -> &str {
-        let token = &self.tokens[self.position];
-
-        &self.source[token.offset..][..token.len]
-    }
-
     /// Advance current token position by up to `count` tokens.
     #[inline]
     fn advance(&mut self, count: usize) {
         self.position = (self.position + count).min(self.tokens.len() - 1);
     }
 
+    /// Returns the keyword kind of `text`, if `text` is a keyword.
+    pub(super) fn keyword_kind(text: &str) -> Option<SyntaxKind> {
+        Some(match text {
+            "true" => SyntaxKind::KeywordTrue,
+            "false" => SyntaxKind::KeywordFalse,
+            "None" => SyntaxKind::KeywordNone,
+            "string" => SyntaxKind::KeywordString,
+            "number" => SyntaxKind::KeywordNumber,
+            "boolean" => SyntaxKind::KeywordBoolean,
+            "name" => SyntaxKind::KeywordName,
+            "version" => SyntaxKind::KeywordVersion,
+            "usecase" => SyntaxKind::KeywordUsecase,
+            "safe" => SyntaxKind::KeywordSafe,
+            "idempotent" => SyntaxKind::KeywordIdempotent,
+            "unsafe" => SyntaxKind::KeywordUnsafe,
+            "input" => SyntaxKind::KeywordInput,
+            "result" => SyntaxKind::KeywordResult,
+            "async" => SyntaxKind::KeywordAsync,
+            "error" => SyntaxKind::KeywordError,
+            "example" => SyntaxKind::KeywordExample,
+            "model" => SyntaxKind::KeywordModel,
+            "field" => SyntaxKind::KeywordField,
+            "enum" => SyntaxKind::KeywordEnum,
+            _ => return None,
+        })
+    }
+
+    /// Builds the tree of a node parsed out of a fragment of a larger source.
+    ///
+    /// Returns `None` if the node did not consume the whole fragment or if the fragment starts or ends with trivia,
+    /// because such a node could not be spliced back into the original tree.
+    pub fn build_fragment(self) -> Option<(rowan::GreenNode, Vec<ParserError>)> {
+        if self.peek() != SyntaxKind::EndOfFile {
+            return None;
+        }
+
+        let mut tokens = tokenize(self.source).map(Self::map_token);
+        let starts_with_trivia = tokens
+            .next()
+            .map(|t| Self::TRIVIA_TOKENS.contains(t.kind))
+            .unwrap_or(false);
+        let ends_with_trivia = tokens
+            .filter(|t| t.kind != SyntaxKind::EndOfFile)
+            .last()
+            .map(|t| Self::TRIVIA_TOKENS.contains(t.kind))
+            .unwrap_or(false);
+        if starts_with_trivia || ends_with_trivia {
+            return None;
+        }
+
+        Some(self.build_tree())
+    }
+
     pub fn build_tree(self) -> (rowan::GreenNode, Vec<ParserError>) {
         assert_eq!(self.active_stack.len(), 0);
 
         let mut builder = rowan::GreenNodeBuilder::new();
         let mut errors = Vec::new();
         let mut error_offset = 0;
+        let mut depth = 0;
 
         // the parser internally stores tokens without trivia, so here we replay all the events
         // and output trivia tokens in-between as appropriate - this ensures that nodes never start nor end with trivia - it's always pushed to the outermost node
@@ -152,12 +201,19 @@ impl<'a> Parser<'a> {
                 // this means there is an unclosed frame - this is a bug
                 ParserEvent::FrameStart { .. } => unreachable!(),
                 // skip trivia before node start so that nodes never start with trivia
+                // except for the root node, which must contain all the leading trivia
                 ParserEvent::NodeStart { kind } => {
-                    skip_trivia!();
+                    if depth > 0 {
+                        skip_trivia!();
+                    }
 
                     builder.start_node(kind.into());
+                    depth += 1;
+                }
+                ParserEvent::NodeEnd => {
+                    builder.finish_node();
+                    depth -= 1;
                 }
-                ParserEvent::NodeEnd => builder.finish_node(),
                 // skip trivia before each token
                 ParserEvent::Token { kind } => {
                     skip_trivia!();
@@ -176,7 +232,8 @@ impl<'a> Parser<'a> {
         }
         // skip trivia so that final trivia is included
         skip_trivia!();
-        debug_assert!(tokens.next().is_none());
+        // fragments don't consume the end of file marker
+        debug_assert!(tokens.all(|t| t.kind == SyntaxKind::EndOfFile));
 
         (builder.finish(), errors)
     }
@@ -224,7 +281,8 @@ impl TreeParser for Parser<'_> {
             message: String::from(message),
         });
         let next = self.peek();
-        if !recovery_set.contains(next) {
+        // end of file cannot be skipped, it would be recorded into the tree more than once
+        if !recovery_set.contains(next) && next != SyntaxKind::EndOfFile {
             self.events.push(ParserEvent::Token { kind: next });
             self.advance(1);
         }
@@ -235,35 +293,19 @@ impl TreeParser for Parser<'_> {
         self.tokens[self.position].kind
     }
 
+    fn peek_text(&self) -> &str {
+        let token = &self.tokens[self.position];
+
+        &self.source[token.offset..][..token.len]
+    }
+
     fn peek_keyword(&self) -> SyntaxKind {
         let kind = self.peek();
         if kind != SyntaxKind::Identifier {
             return kind;
         }
 
-        match self.peek_text() {
-            "true" => SyntaxKind::KeywordTrue,
-            "false" => SyntaxKind::KeywordFalse,
-            "None" => SyntaxKind::KeywordNone,
-            "string" => SyntaxKind::KeywordString,
-            "number" => SyntaxKind::KeywordNumber,
-            "boolean" => SyntaxKind::KeywordBoolean,
-            "name" => SyntaxKind::KeywordName,
-            "version" => SyntaxKind::KeywordVersion,
-            "usecase" => SyntaxKind::KeywordUsecase,
-            "safe" => SyntaxKind::KeywordSafe,
-            "idempotent" => SyntaxKind::KeywordIdempotent,
-            "unsafe" => SyntaxKind::KeywordUnsafe,
-            "input" => SyntaxKind::KeywordInput,
-            "result" => SyntaxKind::KeywordResult,
-            "async" => SyntaxKind::KeywordAsync,
-            "error" => SyntaxKind::KeywordError,
-            "example" => SyntaxKind::KeywordExample,
-            "model" => SyntaxKind::KeywordModel,
-            "field" => SyntaxKind::KeywordField,
-            "enum" => SyntaxKind::KeywordEnum,
-            _ => kind,
-        }
+        Self::keyword_kind(self.peek_text()).unwrap_or(kind)
     }
 
     fn skip<T: CstToken>(&mut self) -> usize {
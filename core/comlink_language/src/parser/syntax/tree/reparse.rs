@@ -0,0 +1,367 @@
+//! Incremental reparsing of profile documents.
+//!
+//! Reparsing first attempts to relex only the token touched by the edit, then to reparse only the smallest
+//! enclosing definition. If neither is possible the whole document is parsed again.
+
+use std::{ops::Range, string::String as StdString};
+
+use rowan::{GreenNode, GreenToken, NodeOrToken, TextRange, TextSize};
+
+use crate::parser::{
+    lexer::tokenize,
+    syntax::{SyntaxKind, SyntaxNode},
+};
+
+use super::{
+    nodes::{
+        NamedFieldDefinitionNode, NamedModelDefinitionNode, ProfileDocumentNode,
+        UseCaseDefinitionNode,
+    },
+    AstNode, CstNode, Parser, ParserError,
+};
+
+/// A single edit of the source text.
+#[derive(Debug, Clone)]
+pub struct TextEdit {
+    /// Byte range of the original source which is removed.
+    pub delete: Range<usize>,
+    /// Text inserted in place of the deleted range.
+    pub insert: StdString,
+}
+impl TextEdit {
+    /// Applies this edit to `text`.
+    pub fn apply(&self, text: &str) -> StdString {
+        let mut result = text.to_string();
+        result.replace_range(self.delete.clone(), &self.insert);
+
+        result
+    }
+
+    /// Applies this edit to `text` which starts at `offset` in the original source.
+    fn apply_at(&self, text: &str, offset: TextSize) -> StdString {
+        let offset = usize::from(offset);
+
+        TextEdit {
+            delete: (self.delete.start - offset)..(self.delete.end - offset),
+            insert: self.insert.clone(),
+        }
+        .apply(text)
+    }
+
+    /// Difference between the length of the new and the old source.
+    fn len_delta(&self) -> isize {
+        self.insert.len() as isize - self.delete.len() as isize
+    }
+
+    fn delete_range(&self) -> Option<TextRange> {
+        Some(TextRange::new(
+            TextSize::try_from(self.delete.start).ok()?,
+            TextSize::try_from(self.delete.end).ok()?,
+        ))
+    }
+}
+
+impl ProfileDocumentNode {
+    /// Reparses this document after `edit` is applied to its source.
+    ///
+    /// `errors` must be the errors reported when this document was parsed. Green nodes untouched by the edit are reused.
+    pub fn reparse(&self, errors: &[ParserError], edit: &TextEdit) -> (Self, Vec<ParserError>) {
+        let root = self.as_ref();
+
+        match reparse_token(root, edit).or_else(|| reparse_definition(root, errors, edit)) {
+            None => Self::parse_root(&edit.apply(&root.text().to_string())),
+            Some((green, old_range)) => {
+                let errors = errors
+                    .iter()
+                    .map(|error| ParserError {
+                        message: error.message.clone(),
+                        offset: if error.offset >= usize::from(old_range.end()) {
+                            error.offset.wrapping_add_signed(edit.len_delta())
+                        } else {
+                            error.offset
+                        },
+                    })
+                    .collect();
+
+                (Self::cast(SyntaxNode::new_root(green)).unwrap(), errors)
+            }
+        }
+    }
+}
+
+/// Returns the raw kind a token of `kind` is lexed as if its text can be changed without affecting the parse.
+fn relexable_raw_kind(kind: SyntaxKind) -> Option<SyntaxKind> {
+    match kind {
+        SyntaxKind::Whitespace => Some(SyntaxKind::Whitespace),
+        SyntaxKind::LineComment => Some(SyntaxKind::LineComment),
+        SyntaxKind::Identifier => Some(SyntaxKind::Identifier),
+        SyntaxKind::IntNumber => Some(SyntaxKind::IntNumber),
+        SyntaxKind::FloatNumber => Some(SyntaxKind::FloatNumber),
+        SyntaxKind::StringDoc
+        | SyntaxKind::StringLiteral
+        | SyntaxKind::ProfileName
+        | SyntaxKind::ProfileVersion => Some(SyntaxKind::String),
+        _ => None,
+    }
+}
+
+/// Checks that `text` placed between `prev` and `next` tokens doesn't merge with them when lexed.
+fn lexes_in_isolation(prev: Option<&str>, text: &str, next: Option<&str>) -> bool {
+    let prev = prev.unwrap_or_default();
+    let next = next.unwrap_or_default();
+    let window = [prev, text, next].concat();
+
+    let mut boundaries = tokenize(&window).map(|t| (t.offset, t.len));
+    let text_start = boundaries.find(|&(offset, _)| offset >= prev.len());
+    let next_start = boundaries.find(|&(offset, _)| offset >= prev.len() + text.len());
+
+    text_start.map(|(offset, _)| offset) == Some(prev.len())
+        && next_start == Some((prev.len() + text.len(), next.len()))
+}
+
+/// Relexes the single token covering the edit.
+///
+/// Returns the new green root and the range of the replaced token in the old tree.
+fn reparse_token(root: &SyntaxNode, edit: &TextEdit) -> Option<(GreenNode, TextRange)> {
+    let delete = edit.delete_range()?;
+    if !root.text_range().contains_range(delete) {
+        return None;
+    }
+
+    let token = match root.covering_element(delete) {
+        NodeOrToken::Token(token) => token,
+        NodeOrToken::Node(_) => return None,
+    };
+    let raw_kind = relexable_raw_kind(token.kind())?;
+    let range = token.text_range();
+    let new_text = edit.apply_at(token.text(), range.start());
+
+    // identifiers which are or become keywords could change how the document is parsed
+    if raw_kind == SyntaxKind::Identifier
+        && (Parser::keyword_kind(token.text()).is_some()
+            || Parser::keyword_kind(&new_text).is_some())
+    {
+        return None;
+    }
+
+    let mut new_tokens = tokenize(&new_text).map(Parser::map_token);
+    match (new_tokens.next(), new_tokens.next()) {
+        (Some(first), Some(eof))
+            if first.kind == raw_kind
+                && first.len == new_text.len()
+                && eof.kind == SyntaxKind::EndOfFile => {}
+        _ => return None,
+    }
+
+    let prev = token.prev_token();
+    let next = token.next_token();
+    if !lexes_in_isolation(
+        prev.as_ref().map(|t| t.text()),
+        &new_text,
+        next.as_ref().map(|t| t.text()),
+    ) {
+        return None;
+    }
+
+    let green = token.replace_with(GreenToken::new(token.kind().into(), &new_text));
+    Some((green, range))
+}
+
+/// Reparses the smallest definition node enclosing the edit.
+///
+/// Returns the new green root and the range of the replaced node in the old tree.
+fn reparse_definition(
+    root: &SyntaxNode,
+    errors: &[ParserError],
+    edit: &TextEdit,
+) -> Option<(GreenNode, TextRange)> {
+    let delete = edit.delete_range()?;
+    if !root.text_range().contains_range(delete) {
+        return None;
+    }
+
+    let node = root.covering_element(delete).ancestors().find(|n| {
+        matches!(
+            n.kind(),
+            SyntaxKind::UseCaseDefinition
+                | SyntaxKind::NamedModelDefinition
+                | SyntaxKind::NamedFieldDefinition
+        )
+    })?;
+    let range = node.text_range();
+
+    // errors don't belong to any node, so we bail out if there may be errors reported while parsing this one
+    let has_errors = errors
+        .iter()
+        .any(|e| e.offset >= usize::from(range.start()) && e.offset <= usize::from(range.end()));
+    if has_errors {
+        return None;
+    }
+
+    // the node must not be extended by what follows it: that is either a newline or end of file,
+    // but not a newline followed by `|` which would continue a union type
+    let mut following = std::iter::successors(node.last_token()?.next_token(), |t| t.next_token())
+        .map(|t| t.kind())
+        .filter(|&k| k != SyntaxKind::Whitespace && k != SyntaxKind::LineComment);
+    match following.next() {
+        None | Some(SyntaxKind::EndOfFile) => (),
+        Some(SyntaxKind::Newline) => {
+            if following.find(|&k| k != SyntaxKind::Newline) == Some(SyntaxKind::Pipe) {
+                return None;
+            }
+        }
+        Some(_) => return None,
+    }
+
+    let new_text = edit.apply_at(&node.text().to_string(), range.start());
+    let prev = node.first_token()?.prev_token();
+    let next = node.last_token()?.next_token();
+    if !lexes_in_isolation(
+        prev.as_ref().map(|t| t.text()),
+        &new_text,
+        next.as_ref().map(|t| t.text()),
+    ) {
+        return None;
+    }
+
+    let mut parser = Parser::new(&new_text);
+    match node.kind() {
+        SyntaxKind::UseCaseDefinition => UseCaseDefinitionNode::parse(&mut parser),
+        SyntaxKind::NamedModelDefinition => NamedModelDefinitionNode::parse(&mut parser),
+        SyntaxKind::NamedFieldDefinition => NamedFieldDefinitionNode::parse(&mut parser),
+        _ => unreachable!(),
+    }
+    let (green, new_errors) = parser.build_fragment()?;
+    if !new_errors.is_empty() {
+        return None;
+    }
+
+    Some((node.replace_with(green), range))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = r#"name = "scope/example"
+version = "1.2.3"
+
+"Title"
+usecase Foo safe {
+  input {
+    a string
+    b Bar
+  }
+  result Bar
+}
+
+// comment
+model Bar {
+  c number!
+} | { d boolean }
+
+field baz string
+"#;
+
+    /// Reparses `source` with the edit and asserts it is the same as a full parse.
+    fn assert_reparse(source: &str, edit: TextEdit) {
+        let (document, errors) = ProfileDocumentNode::parse_root(source);
+        let (reparsed, reparsed_errors) = document.reparse(&errors, &edit);
+        let (expected, expected_errors) = ProfileDocumentNode::parse_root(&edit.apply(source));
+
+        assert_eq!(
+            reparsed.as_ref().green().into_owned(),
+            expected.as_ref().green().into_owned()
+        );
+        assert_eq!(
+            reparsed_errors
+                .iter()
+                .map(|e| (e.message.as_str(), e.offset))
+                .collect::<Vec<_>>(),
+            expected_errors
+                .iter()
+                .map(|e| (e.message.as_str(), e.offset))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    fn edit(source: &str, find: &str, insert: &str) -> TextEdit {
+        let start = source.find(find).unwrap();
+
+        TextEdit {
+            delete: start..start + find.len(),
+            insert: insert.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_reparse_token() {
+        let cases = [
+            edit(SOURCE, "Foo", "FooBar"),
+            edit(SOURCE, "Title", "Other title"),
+            edit(SOURCE, "// comment", "// another comment"),
+            edit(SOURCE, "1.2.3", "1.3.0"),
+        ];
+
+        for case in cases {
+            let (document, _) = ProfileDocumentNode::parse_root(SOURCE);
+            assert!(reparse_token(document.as_ref(), &case).is_some());
+            assert_reparse(SOURCE, case);
+        }
+    }
+
+    #[test]
+    fn test_reparse_definition() {
+        let cases = [
+            edit(SOURCE, "    b Bar\n", "    b Bar\n    c [string]\n"),
+            edit(SOURCE, "c number!", "c number, e string"),
+            edit(SOURCE, "field baz string", "field baz enum { A, B }"),
+        ];
+
+        for case in cases {
+            let (document, errors) = ProfileDocumentNode::parse_root(SOURCE);
+            assert!(reparse_definition(document.as_ref(), &errors, &case).is_some());
+            assert_reparse(SOURCE, case);
+        }
+    }
+
+    #[test]
+    fn test_reparse_falls_back() {
+        let cases = [
+            // identifier turns into a keyword
+            edit(SOURCE, "Foo", "model"),
+            // relexed token is split in two
+            edit(SOURCE, "Title", "Title\" \"Other"),
+            // definition is split in two
+            edit(SOURCE, "  result Bar\n}", "}\nmodel Baz {"),
+            // definition becomes a part of a union
+            edit(SOURCE, "field baz string", "model X {}\n| { e string }"),
+            // definition is broken
+            edit(SOURCE, "c number!", "c number!!"),
+            // edit crossing definitions
+            edit(SOURCE, "}\n\n// comment\nmodel", "}\n\nmodel"),
+        ];
+
+        for case in cases {
+            assert_reparse(SOURCE, case);
+        }
+    }
+
+    #[test]
+    fn test_reparse_unterminated() {
+        let source = format!("{}model Broken {{\n", SOURCE);
+        assert_reparse(&source, edit(&source, "Broken {", "Broken {\n  a"));
+
+        let source = format!("{}usecase Broken {{\n", SOURCE);
+        assert_reparse(&source, edit(&source, "Broken", "Unfinished"));
+    }
+
+    #[test]
+    fn test_reparse_keeps_errors() {
+        let source = format!("{}model 1\n", SOURCE);
+
+        assert_reparse(&source, edit(&source, "Foo", "Foos"));
+        assert_reparse(&source, edit(&source, "c number!", "c string"));
+        assert_reparse(&source, edit(&source, "model 1", "model One"));
+    }
+}
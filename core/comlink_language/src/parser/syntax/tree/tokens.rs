@@ -44,6 +44,8 @@ token! { "|" pub struct PipeToken = Pipe; }
 token! { "," pub struct CommaToken = Comma; }
 token! { "." pub struct DotToken = Dot; }
 token! { "=" pub struct EqualsToken = Equals; }
+token! { ":" pub struct ColonToken = Colon; }
+token! { ";" pub struct SemicolonToken = Semicolon; }
 token! { "whitespace" pub struct WhitespaceToken = Whitespace; }
 token! { "identifier" pub struct IdentifierToken = Identifier; }
 impl IdentifierToken {
@@ -137,6 +139,9 @@ token! { "example keyword" pub struct KeywordExampleToken = KeywordExample; }
 token! { "model keyword" pub struct KeywordModelToken = KeywordModel; }
 token! { "field keyword" pub struct KeywordFieldToken = KeywordField; }
 token! { "enum keyword" pub struct KeywordEnumToken = KeywordEnum; }
+// map header keywords are contextual, they are lexed as identifiers and checked by text
+token! { "const keyword" pub struct KeywordConstToken = Identifier => KeywordConst; }
+token! { "manifest keyword" pub struct KeywordManifestToken = Identifier => KeywordManifest; }
 
 /// Returns the number of paired quote characters around the `text`.
 fn quote_pair_count(text: &str) -> usize {
@@ -213,30 +218,44 @@ impl FieldNameToken {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProfileId {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub scope: Option<StdString>,
     pub name: StdString,
 }
-token! { "profile name" pub struct ProfileNameToken = String => ProfileName; }
-impl ProfileNameToken {
-    pub fn id(&self) -> Option<ProfileId> {
-        let text = string_literal_value(self.as_ref().text())?;
-        Some(match text.split_once('/') {
+impl From<&str> for ProfileId {
+    /// Splits `scope/name` into scope and name.
+    fn from(value: &str) -> Self {
+        match value.split_once('/') {
             None => ProfileId {
                 scope: None,
-                name: text.into_owned(),
+                name: value.to_string(),
             },
             Some((scope, name)) => ProfileId {
                 scope: Some(scope.to_string()),
                 name: name.to_string(),
             },
-        })
+        }
+    }
+}
+impl std::fmt::Display for ProfileId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.scope {
+            Some(ref scope) => write!(f, "{}/{}", scope, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+token! { "profile name" pub struct ProfileNameToken = String => ProfileName; }
+impl ProfileNameToken {
+    pub fn id(&self) -> Option<ProfileId> {
+        let text = string_literal_value(self.as_ref().text())?;
+        Some(ProfileId::from(text.as_ref()))
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProfileVersion {
     pub major: usize,
     pub minor: usize,
@@ -244,6 +263,125 @@ pub struct ProfileVersion {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub label: Option<std::string::String>,
 }
+impl FromStr for ProfileVersion {
+    type Err = ();
+
+    /// Parses `major.minor[.patch][-label]`, patch defaults to zero.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (numbers, label) = match s.split_once('-') {
+            None => (s, None),
+            Some((numbers, label)) => (numbers, Some(label.to_string())),
+        };
+
+        let mut it = numbers
+            .split('.')
+            .map(|n| n.parse::<usize>().map_err(|_| ()));
+        let version = ProfileVersion {
+            major: it.next().ok_or(())??,
+            minor: it.next().ok_or(())??,
+            patch: it.next().transpose()?.unwrap_or(0),
+            label,
+        };
+        if it.next().is_some() {
+            return Err(());
+        }
+
+        Ok(version)
+    }
+}
+impl std::fmt::Display for ProfileVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(ref label) = self.label {
+            write!(f, "-{}", label)?;
+        }
+
+        Ok(())
+    }
+}
+impl ProfileVersion {
+    /// Checks whether a map written for this version of a profile can be used with `profile` version.
+    ///
+    /// Following semver, the major versions must be the same and the map must not require a newer minor version
+    /// than the profile provides. For versions 0.x the minor versions must be the same, like in [ProfileVersionRange::Caret].
+    /// Patch versions and labels don't affect compatibility.
+    pub fn is_compatible_with(&self, profile: &ProfileVersion) -> bool {
+        if self.major == 0 {
+            return profile.major == 0 && self.minor == profile.minor;
+        }
+
+        self.major == profile.major && self.minor <= profile.minor
+    }
+}
+
+/// Range of profile versions, as used when requesting a profile from the registry.
+///
+/// Supported forms are an exact version `1.2.3`, a caret range `^1.2` (compatible with `1.2.0`) and a tilde range `~1.2.3`
+/// (same major and minor version). Versions with labels only match exact ranges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileVersionRange {
+    Exact(ProfileVersion),
+    Caret(ProfileVersion),
+    Tilde(ProfileVersion),
+}
+impl ProfileVersionRange {
+    pub fn matches(&self, version: &ProfileVersion) -> bool {
+        match self {
+            Self::Exact(v) => v == version,
+            _ if version.label.is_some() => false,
+            // for versions 0.x, minor versions are considered breaking
+            Self::Caret(v) if v.major == 0 => {
+                version.major == 0 && version.minor == v.minor && version.patch >= v.patch
+            }
+            Self::Caret(v) => {
+                version.major == v.major && (version.minor, version.patch) >= (v.minor, v.patch)
+            }
+            Self::Tilde(v) => {
+                version.major == v.major && version.minor == v.minor && version.patch >= v.patch
+            }
+        }
+    }
+
+    /// Returns the highest version from `versions` matching this range.
+    pub fn resolve<'a>(
+        &self,
+        versions: impl IntoIterator<Item = &'a ProfileVersion>,
+    ) -> Option<&'a ProfileVersion> {
+        versions
+            .into_iter()
+            .filter(|v| self.matches(v))
+            .max_by_key(|v| (v.major, v.minor, v.patch))
+    }
+}
+impl FromStr for ProfileVersionRange {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(version) = s.strip_prefix('^') {
+            return Ok(Self::Caret(version.parse()?));
+        }
+        if let Some(version) = s.strip_prefix('~') {
+            return Ok(Self::Tilde(version.parse()?));
+        }
+
+        // a full version is exact, a partial version such as `1.2` is the same as `^1.2`
+        let version: ProfileVersion = s.parse()?;
+        if s.split('-').next().unwrap_or_default().split('.').count() == 3 {
+            Ok(Self::Exact(version))
+        } else {
+            Ok(Self::Caret(version))
+        }
+    }
+}
+impl std::fmt::Display for ProfileVersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(v) => write!(f, "{}", v),
+            Self::Caret(v) => write!(f, "^{}", v),
+            Self::Tilde(v) => write!(f, "~{}", v),
+        }
+    }
+}
 token! { "profile version" pub struct ProfileVersionToken = String => ProfileVersion; }
 impl ProfileVersionToken {
     pub fn value(&self) -> Option<ProfileVersion> {
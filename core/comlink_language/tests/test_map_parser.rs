@@ -0,0 +1,72 @@
+use comlink_language::parser::{parse_map_header, MapManifest, ProfileId, ProfileVersion};
+
+#[test]
+fn test_map_manifest() {
+    let source = r#"/// <reference types="@superface/map-std" />
+// @ts-check
+
+const manifest = {
+  profile: 'communication/send-sms@1.2',
+  provider: "twilio",
+  variant: 'bulk',
+  version: '2.1.0-rev3',
+};
+
+var SendMessage = ({ input, parameters, services }) => {
+  const url = `${services.default}/Messages.json`;
+  return { messageId: 'don\'t parse me' };
+};
+"#;
+
+    let (header, errors) = parse_map_header(source).unwrap();
+    assert!(errors.is_empty(), "errors: {:?}", errors);
+
+    assert_eq!(
+        header.manifest().unwrap(),
+        MapManifest {
+            profile: ProfileId {
+                scope: Some("communication".to_string()),
+                name: "send-sms".to_string()
+            },
+            profile_version: ProfileVersion {
+                major: 1,
+                minor: 2,
+                patch: 0,
+                label: None
+            },
+            provider: "twilio".to_string(),
+            variant: Some("bulk".to_string()),
+            version: Some("2.1.0-rev3".to_string())
+        }
+    );
+}
+
+#[test]
+fn test_map_manifest_single_line() {
+    let source = "const manifest = { profile: 'example@1.0.3-rev2', provider: 'localhost' }\nvar Example = () => {};";
+
+    let (header, errors) = parse_map_header(source).unwrap();
+    assert!(errors.is_empty(), "errors: {:?}", errors);
+
+    let manifest = header.manifest().unwrap();
+    assert_eq!(manifest.profile.to_string(), "example");
+    assert_eq!(manifest.profile_version.to_string(), "1.0.3-rev2");
+    assert_eq!(manifest.provider, "localhost");
+    assert_eq!(manifest.variant, None);
+    assert_eq!(manifest.version, None);
+}
+
+#[test]
+fn test_map_without_manifest() {
+    let source = "// @ts-check\n\nvar Example = () => {};";
+
+    assert!(parse_map_header(source).is_none());
+}
+
+#[test]
+fn test_map_manifest_invalid() {
+    let (header, errors) =
+        parse_map_header("const manifest = { profile: 'scope/name', provider 'x' };").unwrap();
+    assert_eq!(errors[0].message, "expected :");
+    assert!(header.manifest().is_err());
+}
@@ -0,0 +1,80 @@
+use comlink_language::parser::ProfileDocumentDefinitionNode;
+use serde_json::json;
+
+#[test]
+fn test_example_values() {
+    let (profile, errors) =
+        comlink_language::parser::parse_profile(include_str!("fixtures/p3.profile"));
+    assert!(errors.is_empty());
+
+    let usecase = profile
+        .definitions()
+        .find_map(|definition| match definition {
+            ProfileDocumentDefinitionNode::UseCaseDefinition(usecase) => Some(usecase),
+            _ => None,
+        })
+        .unwrap();
+    let examples: Vec<_> = usecase.examples().collect();
+    assert_eq!(examples.len(), 3);
+
+    assert_eq!(
+        serde_json::Value::Object(examples[0].input_value().unwrap()),
+        json!({ "f": "hello", "fn": null })
+    );
+    assert_eq!(
+        examples[0]
+            .result()
+            .unwrap()
+            .literal()
+            .unwrap()
+            .json_value(),
+        Some(json!(5))
+    );
+    assert_eq!(
+        examples[1].error().unwrap().literal().unwrap().json_value(),
+        Some(json!("FORBIDDEN_WORD"))
+    );
+    assert!(examples[2].input_value().unwrap().is_empty());
+    assert_eq!(
+        examples[2]
+            .result()
+            .unwrap()
+            .literal()
+            .unwrap()
+            .json_value(),
+        Some(json!([0, 1, 2]))
+    );
+}
+
+#[test]
+fn test_object_literal_dotted_keys() {
+    let (profile, errors) = comlink_language::parser::parse_profile(
+        r#"name = "scope/example"
+version = "1.0.0"
+
+usecase Foo {
+  example {
+    input {
+      a.b = 1
+      a.c = "two"
+      d = [true, { e = 3.5 }]
+    }
+  }
+}
+"#,
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let example = profile
+        .definitions()
+        .find_map(|definition| match definition {
+            ProfileDocumentDefinitionNode::UseCaseDefinition(usecase) => usecase.examples().next(),
+            _ => None,
+        })
+        .unwrap();
+
+    assert_eq!(
+        serde_json::Value::Object(example.input_value().unwrap()),
+        json!({ "a": { "b": 1, "c": "two" }, "d": [true, { "e": 3.5 }] })
+    );
+}
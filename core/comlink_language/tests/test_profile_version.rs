@@ -0,0 +1,66 @@
+use comlink_language::parser::{ProfileVersion, ProfileVersionRange};
+
+fn version(s: &str) -> ProfileVersion {
+    s.parse().unwrap()
+}
+
+fn range(s: &str) -> ProfileVersionRange {
+    s.parse().unwrap()
+}
+
+#[test]
+fn test_version_compatibility() {
+    assert!(version("1.2").is_compatible_with(&version("1.2.0")));
+    assert!(version("1.2").is_compatible_with(&version("1.3.1")));
+    assert!(version("1.2.5").is_compatible_with(&version("1.2.0")));
+
+    assert!(!version("1.3").is_compatible_with(&version("1.2.9")));
+    assert!(!version("1.2").is_compatible_with(&version("2.2.0")));
+    assert!(!version("2.0").is_compatible_with(&version("1.9.0")));
+
+    // for versions 0.x minor versions are breaking, same as for caret ranges
+    assert!(version("0.1").is_compatible_with(&version("0.1.3")));
+    assert!(!version("0.1").is_compatible_with(&version("0.2.0")));
+    assert!(!version("0.2").is_compatible_with(&version("0.1.0")));
+    assert!(!range("^0.1").matches(&version("0.2.0")));
+}
+
+#[test]
+fn test_version_range_parse() {
+    assert_eq!(range("1.2.3"), ProfileVersionRange::Exact(version("1.2.3")));
+    assert_eq!(range("1.2"), ProfileVersionRange::Caret(version("1.2.0")));
+    assert_eq!(range("^1.2"), ProfileVersionRange::Caret(version("1.2.0")));
+    assert_eq!(
+        range("~1.2.3"),
+        ProfileVersionRange::Tilde(version("1.2.3"))
+    );
+    assert_eq!(range("^1.2").to_string(), "^1.2.0");
+
+    assert!("^1".parse::<ProfileVersionRange>().is_err());
+    assert!(">=1.2".parse::<ProfileVersionRange>().is_err());
+}
+
+#[test]
+fn test_version_range_resolve() {
+    let versions: Vec<_> = [
+        "0.1.0",
+        "0.2.1",
+        "1.0.0",
+        "1.2.0",
+        "1.2.4",
+        "1.3.0-rc1",
+        "1.4.2",
+        "2.0.0",
+    ]
+    .into_iter()
+    .map(version)
+    .collect();
+    let resolve = |r: &str| range(r).resolve(&versions).map(|v| v.to_string());
+
+    assert_eq!(resolve("^1.2").as_deref(), Some("1.4.2"));
+    assert_eq!(resolve("~1.2").as_deref(), Some("1.2.4"));
+    assert_eq!(resolve("^0.1").as_deref(), Some("0.1.0"));
+    assert_eq!(resolve("1.3.0-rc1").as_deref(), Some("1.3.0-rc1"));
+    assert_eq!(resolve("^1.5"), None);
+    assert_eq!(resolve("^3.0"), None);
+}
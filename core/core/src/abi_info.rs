@@ -0,0 +1,106 @@
+//! ABI version and capability handshake between core and the host.
+
+use std::sync::OnceLock;
+
+use sf_std::{
+    abi::{MessageEncoding, Ptr},
+    unstable::host_info::{AbiInfo, HostInfo, HostInfoError, ABI_VERSION, MESSAGE_KINDS},
+};
+
+use crate::{bindings::MessageExchangeFfi, observability::FatPointer};
+
+/// Optional features supported by this core.
+///
+/// - `trace_context` - trace context passed with perform input is propagated
+/// - `perform_id` - perform id can be passed with perform input and is returned with exceptions
+/// - `examples` - `oneclient_core_run_examples` is exported
+/// - `traces` - `oneclient_core_get_traces` is exported
+/// - `developer_dump_since` - `oneclient_core_get_developer_dump_since` is exported
+/// - `message_pack` - messages after `host-info` are encoded as MessagePack if the host supports it as well
+/// - `concurrent_performs` - performs may be suspended and interleaved, `asyncify_alloc_task` is exported
+/// - `cancel` - `oneclient_core_cancel` is exported
+/// - `configure` - `oneclient_core_configure` is exported
+/// - `recover` - `oneclient_core_recover` is exported, performs which trapped because of a panic can be recovered from
+/// - `secrets` - security values may reference secrets as `{ "$secret": "<name>" }`, retrieved through `secret-get` when a request needs them
+/// - `document_cache` - `oneclient_core_preload`, `oneclient_core_invalidate_cache` and `oneclient_core_list_cache` are exported
+const CORE_FEATURES: &[&str] = &[
+    "trace_context",
+    "perform_id",
+    "examples",
+    "traces",
+    "developer_dump_since",
+    "message_pack",
+    "concurrent_performs",
+    "cancel",
+    "recover",
+    "configure",
+    "secrets",
+    "document_cache",
+];
+
+fn core_abi_info() -> AbiInfo<'static> {
+    AbiInfo {
+        abi_version: ABI_VERSION,
+        core_version: env!("CARGO_PKG_VERSION"),
+        message_kinds: MESSAGE_KINDS,
+        features: CORE_FEATURES,
+    }
+}
+
+/// Exchanges information with the host during setup.
+///
+/// Hosts which predate the handshake are assumed to implement the current ABI version without any optional features.
+///
+/// Panics if the host implements a different ABI version, so that the mismatch surfaces during setup and not in the middle of a perform.
+///
+/// Switches message encoding to MessagePack if the host supports it, `host-info` itself is always sent as JSON.
+pub fn exchange_host_info() -> HostInfo {
+    MessageExchangeFfi::set_message_encoding(MessageEncoding::Json);
+    let host_info = match HostInfo::exchange_in(&core_abi_info(), MessageExchangeFfi) {
+        Ok(host_info) => host_info,
+        Err(err @ HostInfoError::IncompatibleAbi { .. }) => panic!("{}", err),
+        Err(err) => {
+            tracing::warn!(
+                target: "@user",
+                "{}, assuming host without optional features",
+                err
+            );
+            HostInfo::default()
+        }
+    };
+    tracing::debug!(
+        target: "@user",
+        host_abi_version = ?host_info.abi_version,
+        host_features = ?host_info.features,
+        "Host info received"
+    );
+
+    if host_info.supports("message_pack") {
+        MessageExchangeFfi::set_message_encoding(MessageEncoding::MessagePack);
+    }
+
+    host_info
+}
+
+static ABI_INFO: OnceLock<String> = OnceLock::new();
+static mut ABI_INFO_RETURN: FatPointer = FatPointer::null();
+
+#[no_mangle]
+#[export_name = "oneclient_core_abi_info"]
+/// Returns a fat pointer to memory where information about the ABI implemented by core is stored.
+///
+/// The information is a UTF-8 encoded JSON object with `abi_version`, `core_version`, `message_kinds` and `features`.
+/// This can be called before [__export_oneclient_core_setup](crate::__export_oneclient_core_setup) so that the host
+/// can check whether it is compatible with core.
+pub extern "C" fn __export_oneclient_core_abi_info() -> Ptr<FatPointer> {
+    let abi_info =
+        ABI_INFO.get_or_init(|| serde_json::to_string(&core_abi_info()).expect("Serializable"));
+
+    unsafe {
+        let ret = std::ptr::addr_of_mut!(ABI_INFO_RETURN);
+        (*ret).ptr = abi_info.as_ptr().into();
+        (*ret).size = abi_info.len();
+
+        Ptr::from(ret as *const FatPointer)
+    }
+}
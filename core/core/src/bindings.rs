@@ -1,12 +1,68 @@
+use std::sync::Mutex;
+
 use sf_std::abi::{
-    AbiResultRepr, Handle, MessageExchange, MessageExchangeFfiFn, Ptr, Size, StaticMessageExchange,
-    StaticStreamExchange, StreamExchange, StreamExchangeFfiFn,
+    AbiResultRepr, Handle, MessageEncoding, MessageExchange, MessageExchangeFfiFn, Ptr, Size,
+    StaticMessageExchange, StaticStreamExchange, StreamExchange, StreamExchangeFfiFn,
 };
 
+/// Spans entered by the current perform while it is suspended in a call to the host.
+///
+/// A call to the host may suspend the current perform and resume another one, which must not see spans
+/// of this perform as its parents. So we exit all entered spans before the call and re-enter them after it.
+struct ParkedSpans(Vec<tracing::Id>);
+impl ParkedSpans {
+    fn park() -> Self {
+        tracing::dispatcher::get_default(|dispatch| {
+            let mut ids = Vec::new();
+            while let Some(id) = dispatch.current_span().id().cloned() {
+                // guard against subscribers which don't track entered spans
+                if ids.contains(&id) {
+                    break;
+                }
+                dispatch.exit(&id);
+                ids.push(id);
+            }
+
+            Self(ids)
+        })
+    }
+
+    fn unpark(self) {
+        tracing::dispatcher::get_default(|dispatch| {
+            for id in self.0.iter().rev() {
+                dispatch.enter(id);
+            }
+        })
+    }
+
+    fn around<R>(f: impl FnOnce() -> R) -> R {
+        let parked = Self::park();
+        let result = f();
+        parked.unpark();
+
+        result
+    }
+}
+
+/// Exits and closes spans left entered by a perform which trapped.
+///
+/// The trapped perform never drops its span guards, so we release the reference held by each of them.
+pub fn close_abandoned_spans() {
+    let abandoned = ParkedSpans::park();
+    tracing::dispatcher::get_default(|dispatch| {
+        for id in &abandoned.0 {
+            dispatch.try_close(id.clone());
+        }
+    })
+}
+
 //////////////
 // MESSAGES //
 //////////////
 
+/// Encoding negotiated with the host during setup.
+static MESSAGE_ENCODING: Mutex<MessageEncoding> = Mutex::new(MessageEncoding::Json);
+
 pub struct MessageExchangeFfi;
 impl MessageExchangeFfi {
     // SAFETY: We choose to trust this FFI.
@@ -16,10 +72,18 @@ impl MessageExchangeFfi {
             __import_message_exchange_retrieve,
         )
     };
+
+    pub fn set_message_encoding(encoding: MessageEncoding) {
+        *MESSAGE_ENCODING.lock().unwrap() = encoding;
+    }
 }
 impl MessageExchange for MessageExchangeFfi {
     fn invoke(&self, message: &[u8]) -> Vec<u8> {
-        Self::FFI.invoke(message)
+        ParkedSpans::around(|| Self::FFI.invoke(message))
+    }
+
+    fn message_encoding(&self) -> MessageEncoding {
+        *MESSAGE_ENCODING.lock().unwrap()
     }
 }
 impl StaticMessageExchange for MessageExchangeFfi {
@@ -83,15 +147,15 @@ impl StreamExchangeFfi {
 }
 impl StreamExchange for StreamExchangeFfi {
     fn read(&self, handle: Handle, buf: &mut [u8]) -> std::io::Result<Size> {
-        Self::FFI.read(handle, buf)
+        ParkedSpans::around(|| Self::FFI.read(handle, buf))
     }
 
     fn write(&self, handle: Handle, buf: &[u8]) -> std::io::Result<Size> {
-        Self::FFI.write(handle, buf)
+        ParkedSpans::around(|| Self::FFI.write(handle, buf))
     }
 
     fn close(&self, handle: Handle) -> std::io::Result<()> {
-        Self::FFI.close(handle)
+        ParkedSpans::around(|| Self::FFI.close(handle))
     }
 }
 impl StaticStreamExchange for StreamExchangeFfi {
@@ -132,3 +196,52 @@ extern "C" fn __import_stream_write(
 extern "C" fn __import_stream_close(_handle: Handle) -> AbiResultRepr {
     unreachable!()
 }
+
+#[cfg(test)]
+mod test {
+    use tracing_subscriber::prelude::*;
+
+    use tracing_subscriber::registry::{LookupSpan, Registry};
+
+    use super::{close_abandoned_spans, ParkedSpans};
+
+    #[test]
+    fn test_parked_spans() {
+        let _guard = tracing_subscriber::registry().set_default();
+
+        let outer = tracing::info_span!("outer");
+        let inner = tracing::info_span!(parent: &outer, "inner");
+        let _outer = outer.enter();
+        let _inner = inner.enter();
+
+        let during = ParkedSpans::around(|| {
+            // another perform started during the call does not see our spans
+            let other = tracing::info_span!("other");
+            assert_eq!(other.metadata().map(|m| m.name()), Some("other"));
+            tracing::Span::current().id()
+        });
+        assert_eq!(during, None);
+        assert_eq!(tracing::Span::current().id(), inner.id());
+    }
+
+    #[test]
+    fn test_close_abandoned_spans() {
+        // registry only removes closed spans when it is layered
+        let _guard = tracing_subscriber::registry()
+            .with(tracing_subscriber::layer::Identity::new())
+            .set_default();
+
+        // a trapped perform never drops its spans nor their guards
+        let span = tracing::info_span!("trapped");
+        let id = span.id().unwrap();
+        std::mem::forget(span.clone().entered());
+        std::mem::drop(span);
+
+        close_abandoned_spans();
+        assert_eq!(tracing::Span::current().id(), None);
+        tracing::dispatcher::get_default(|dispatch| {
+            let registry = dispatch.downcast_ref::<Registry>().unwrap();
+            assert!(registry.span(&id).is_none());
+        });
+    }
+}
@@ -1,14 +1,21 @@
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use bindings::MessageExchangeFfi;
 
 use sf_std::{
     abi::{Ptr, Size},
-    unstable::perform::{
-        set_perform_output_error_in, set_perform_output_exception_in, set_perform_output_result_in,
+    unstable::{
+        configure::set_configure_output_in,
+        document_cache::{set_list_cache_output_in, set_preload_output_in},
+        examples::set_examples_output_in,
+        perform::{
+            set_perform_output_error_in, set_perform_output_exception_in,
+            set_perform_output_result_in,
+        },
     },
 };
 
+mod abi_info;
 mod bindings;
 mod observability;
 mod sf_core;
@@ -17,7 +24,11 @@ use sf_core::{CoreConfiguration, OneClientCore};
 #[cfg(feature = "core_mock")]
 mod mock;
 
-static GLOBAL_STATE: Mutex<Option<OneClientCore>> = Mutex::new(None);
+/// Core state shared by all exports.
+///
+/// The lock is only held while the state is being accessed and never across calls to the host, since multiple
+/// performs may be suspended inside one core instance at the same time. Each perform holds its own reference.
+static GLOBAL_STATE: Mutex<Option<Arc<OneClientCore>>> = Mutex::new(None);
 
 // WASI functions which would be automatically called from `_start`, but we need to explicitly call them since we are a lib.
 extern "C" {
@@ -63,8 +74,10 @@ pub extern "C" fn __export_oneclient_core_setup() {
         );
     }
 
+    abi_info::exchange_host_info();
+
     // here we panic on error because there is nothing to teardown
-    lock.replace(OneClientCore::new(&config).unwrap());
+    lock.replace(Arc::new(OneClientCore::new(&config).unwrap()));
 }
 
 #[no_mangle]
@@ -83,6 +96,7 @@ pub extern "C" fn __export_oneclient_core_teardown() {
         Err(_) => panic!("Global state lock already locked: perform most likely panicked"),
         Ok(lock) if lock.is_none() => panic!("Not setup or already torn down"),
         Ok(mut lock) => {
+            // performs which are still suspended keep their own reference to the state
             let state = lock.take();
             std::mem::drop(state); // just to be explicit, would be dropped implicitly anyway
 
@@ -92,6 +106,16 @@ pub extern "C" fn __export_oneclient_core_teardown() {
     }
 }
 
+/// Returns a reference to the core state without keeping the global state locked.
+fn global_state() -> Arc<OneClientCore> {
+    GLOBAL_STATE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .expect("Global state missing: has oneclient_core_setup been called?")
+        .clone()
+}
+
 #[no_mangle]
 #[export_name = "oneclient_core_perform"]
 #[cfg_attr(feature = "core_mock", allow(unreachable_code))]
@@ -104,22 +128,156 @@ pub extern "C" fn __export_oneclient_core_perform() {
     #[cfg(feature = "core_mock")]
     return mock::__export_oneclient_core_perform();
 
-    let mut lock = GLOBAL_STATE.lock().unwrap();
-    let state: &mut OneClientCore = lock
-        .as_mut()
-        .expect("Global state missing: has oneclient_core_setup been called?");
+    let state = global_state();
 
-    match state.perform() {
+    let (perform_id, result) = state.perform();
+    match result {
         Ok(Ok(result)) => set_perform_output_result_in(result, MessageExchangeFfi),
         Ok(Err(error)) => set_perform_output_error_in(error, MessageExchangeFfi),
         Err(exception) => {
-            tracing::error!(target: "@user", "Perform failed unexpectedly: {}", exception);
+            set_perform_output_exception_in(exception, perform_id, MessageExchangeFfi)
+        }
+    }
+}
 
-            set_perform_output_exception_in(exception, MessageExchangeFfi)
+#[no_mangle]
+#[export_name = "oneclient_core_run_examples"]
+#[cfg_attr(feature = "core_mock", allow(unreachable_code))]
+/// Runs use-case examples declared in a profile and reports which of them pass.
+///
+/// Must be called after [__export_oneclient_core_setup] and before [__export_oneclient_core_teardown].
+///
+/// Profile, provider and map are retrieved through the `examples-input` message and the report is sent back with the `examples-output` message.
+pub extern "C" fn __export_oneclient_core_run_examples() {
+    #[cfg(feature = "core_mock")]
+    return mock::__export_oneclient_core_run_examples();
+
+    let state = global_state();
+
+    let report = state.run_examples();
+    set_examples_output_in(report, MessageExchangeFfi);
+}
+
+#[no_mangle]
+#[export_name = "oneclient_core_cancel"]
+#[cfg_attr(feature = "core_mock", allow(unreachable_code))]
+/// Cancels a running perform.
+///
+/// Must be called after [__export_oneclient_core_setup] and before [__export_oneclient_core_teardown].
+///
+/// The id of the perform is retrieved through the `cancel-input` message. The perform fails with a `Cancelled` exception.
+pub extern "C" fn __export_oneclient_core_cancel() {
+    #[cfg(feature = "core_mock")]
+    return mock::__export_oneclient_core_cancel();
+
+    let state = global_state();
+
+    if let Err(err) = state.cancel() {
+        tracing::error!(target: "@user", "Failed to cancel perform: {}", err);
+    }
+}
+
+#[no_mangle]
+#[export_name = "oneclient_core_configure"]
+#[cfg_attr(feature = "core_mock", allow(unreachable_code))]
+/// Changes configuration of core without tearing it down.
+///
+/// Must be called after [__export_oneclient_core_setup] and before [__export_oneclient_core_teardown].
+///
+/// Settings are retrieved through the `configure-input` message and the outcome is sent back with the `configure-output` message.
+/// Invalid configuration is rejected as a whole.
+pub extern "C" fn __export_oneclient_core_configure() {
+    #[cfg(feature = "core_mock")]
+    return mock::__export_oneclient_core_configure();
+
+    let state = global_state();
+
+    match state.configure() {
+        Ok(()) => set_configure_output_in(None, MessageExchangeFfi),
+        Err(err) => {
+            tracing::error!(target: "@user", "Failed to configure core: {}", err);
+            set_configure_output_in(Some(&err.to_string()), MessageExchangeFfi);
         }
     }
 }
 
+#[no_mangle]
+#[export_name = "oneclient_core_preload"]
+#[cfg_attr(feature = "core_mock", allow(unreachable_code))]
+/// Loads documents into the document caches ahead of performs.
+///
+/// Must be called after [__export_oneclient_core_setup] and before [__export_oneclient_core_teardown].
+///
+/// Profile, provider and map urls are retrieved through the `preload-input` message and the outcome of each document
+/// is sent back with the `preload-output` message.
+pub extern "C" fn __export_oneclient_core_preload() {
+    #[cfg(feature = "core_mock")]
+    return mock::__export_oneclient_core_preload();
+
+    let state = global_state();
+
+    match state.preload() {
+        Ok(documents) => set_preload_output_in(documents, MessageExchangeFfi),
+        Err(err) => tracing::error!(target: "@user", "Failed to preload documents: {}", err),
+    }
+}
+
+#[no_mangle]
+#[export_name = "oneclient_core_invalidate_cache"]
+#[cfg_attr(feature = "core_mock", allow(unreachable_code))]
+/// Drops documents from the document caches, so that they are loaded again by the next perform which uses them.
+///
+/// Must be called after [__export_oneclient_core_setup] and before [__export_oneclient_core_teardown].
+///
+/// The url of the document is retrieved through the `invalidate-cache-input` message, all documents are dropped if it is not present.
+pub extern "C" fn __export_oneclient_core_invalidate_cache() {
+    #[cfg(feature = "core_mock")]
+    return mock::__export_oneclient_core_invalidate_cache();
+
+    let state = global_state();
+
+    match state.invalidate_cache() {
+        Ok(count) => tracing::debug!(count, "Invalidated cached documents"),
+        Err(err) => tracing::error!(target: "@user", "Failed to invalidate cache: {}", err),
+    }
+}
+
+#[no_mangle]
+#[export_name = "oneclient_core_list_cache"]
+#[cfg_attr(feature = "core_mock", allow(unreachable_code))]
+/// Lists documents in the document caches with their content hash and age.
+///
+/// Must be called after [__export_oneclient_core_setup] and before [__export_oneclient_core_teardown].
+///
+/// The documents are sent with the `list-cache-output` message.
+pub extern "C" fn __export_oneclient_core_list_cache() {
+    #[cfg(feature = "core_mock")]
+    return mock::__export_oneclient_core_list_cache();
+
+    let state = global_state();
+
+    set_list_cache_output_in(state.list_cache(), MessageExchangeFfi);
+}
+
+#[no_mangle]
+#[export_name = "oneclient_core_recover"]
+#[cfg_attr(feature = "core_mock", allow(unreachable_code))]
+/// Recovers core after a perform trapped because of a panic, so that it doesn't have to be instantiated again.
+///
+/// Must be called after [__export_oneclient_core_setup] and before [__export_oneclient_core_teardown], right after the trap.
+///
+/// The id of the trapped perform is retrieved through the `recover-input` message and its `Panic` exception is sent back
+/// with the `perform-output-exception` message. Traps again if core cannot be recovered.
+pub extern "C" fn __export_oneclient_core_recover() {
+    #[cfg(feature = "core_mock")]
+    return mock::__export_oneclient_core_recover();
+
+    let state = global_state();
+
+    let (perform_id, exception) = state.recover();
+    set_perform_output_exception_in(exception, perform_id, MessageExchangeFfi);
+}
+
 #[cfg(feature = "asyncify")]
 #[no_mangle]
 #[export_name = "asyncify_alloc_stack"]
@@ -139,6 +297,49 @@ pub extern "C" fn __export_oneclient_core_async_init(mut data_ptr: Ptr<Size>, st
         data_ptr.mut_ptr().write(stack.start as Size);
         data_ptr.mut_ptr().offset(1).write(stack.end as Size)
     }
+}
+
+#[cfg(feature = "asyncify")]
+#[no_mangle]
+#[export_name = "asyncify_alloc_task"]
+/// Allocates stacks of one asyncify task and returns a pointer to its asyncify data struct.
+///
+/// The data struct is placed at the beginning of the allocation, followed by the initial shadow stack pointer of the task,
+/// so the host can run multiple suspended exports at the same time, each with its own unwind and shadow stack.
+pub extern "C" fn __export_oneclient_core_async_alloc_task(
+    unwind_stack_size: Size,
+    shadow_stack_size: Size,
+) -> Ptr<Size> {
+    // the shadow stack must be 16 byte aligned, so we align all parts of the allocation
+    const ALIGN: Size = 16;
+    let align_up = |size: Size| (size + ALIGN - 1) & !(ALIGN - 1);
+
+    let header_size = align_up(3 * std::mem::size_of::<Size>());
+    let unwind_stack_size = align_up(unwind_stack_size);
+    let shadow_stack_size = align_up(shadow_stack_size);
+    let layout = std::alloc::Layout::from_size_align(
+        header_size + unwind_stack_size + shadow_stack_size,
+        ALIGN,
+    )
+    .unwrap();
+
+    // the allocation is never freed, hosts are expected to reuse tasks
+    let block = unsafe { std::alloc::alloc_zeroed(layout) };
+    if block.is_null() {
+        std::alloc::handle_alloc_error(layout);
+    }
+
+    unsafe {
+        let data = block as *mut Size;
+        let unwind_stack = block.add(header_size);
+        let shadow_stack = unwind_stack.add(unwind_stack_size);
 
-    // TODO: if we allocate the stack so that the data structure is at its beginning we get the possibility of having multiple stacks without relying on undocumented compiler behavior
+        data.write(unwind_stack as Size);
+        data.add(1).write(shadow_stack as Size);
+        // the shadow stack grows down
+        data.add(2)
+            .write(shadow_stack.add(shadow_stack_size) as Size);
+
+        Ptr::from(data as *const Size)
+    }
 }
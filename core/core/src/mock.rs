@@ -6,13 +6,24 @@
 //! - CORE_PERFORM_INPUT_VALIDATION_ERROR
 
 use sf_std::unstable::{
+    configure::{set_configure_output_in, ConfigureInput},
+    document_cache::{
+        set_list_cache_output_in, set_preload_output_in, InvalidateCacheInput, PreloadInput,
+    },
+    examples::{set_examples_output_in, ExamplesInput, ExamplesReport},
     exception::{PerformException, PerformExceptionErrorCode},
-    perform::{set_perform_output_exception_in, set_perform_output_result_in, PerformInput},
+    perform::{
+        set_perform_output_exception_in, set_perform_output_result_in, CancelInput, PerformInput,
+        RecoverInput,
+    },
     HostValue,
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::bindings::MessageExchangeFfi;
+use crate::{
+    bindings::MessageExchangeFfi,
+    observability::{store_panic_report, take_panic_report, PanicReport},
+};
 
 extern "C" {
     fn __wasm_call_ctors();
@@ -27,6 +38,22 @@ pub fn __export_oneclient_core_setup() {
         .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .init();
 
+    // keep panic reports for recovery, metrics are not initialized in mock
+    std::panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => info
+                .payload()
+                .downcast_ref::<String>()
+                .cloned()
+                .unwrap_or_default(),
+        };
+        tracing::error!(target: "panic", message);
+        store_panic_report(PanicReport::new(&message, info.location()));
+    }));
+
+    crate::abi_info::exchange_host_info();
+
     tracing::debug!("mocked oneclient core setup");
 }
 
@@ -50,8 +77,86 @@ pub fn __export_oneclient_core_perform() {
                 error_code: PerformExceptionErrorCode::InputValidationError,
                 message: "Test validation error".to_string(),
             },
+            perform_input.perform_id,
             MessageExchangeFfi,
         ),
         _ => panic!("Unknown usecase: {}", perform_input.usecase),
     };
 }
+
+pub fn __export_oneclient_core_run_examples() {
+    let examples_input = ExamplesInput::take_in(MessageExchangeFfi).unwrap();
+
+    tracing::debug!(
+        "mocked oneclient core run examples {}",
+        examples_input.profile_url
+    );
+
+    set_examples_output_in(ExamplesReport::default(), MessageExchangeFfi);
+}
+
+pub fn __export_oneclient_core_cancel() {
+    let cancel_input = CancelInput::take_in(MessageExchangeFfi).unwrap();
+
+    tracing::debug!("mocked oneclient core cancel {}", cancel_input.perform_id);
+}
+
+pub fn __export_oneclient_core_recover() {
+    let recover_input = RecoverInput::take_in(MessageExchangeFfi).unwrap();
+
+    tracing::debug!(
+        "mocked oneclient core recover {:?}",
+        recover_input.perform_id
+    );
+
+    set_perform_output_exception_in(
+        PerformException {
+            error_code: PerformExceptionErrorCode::Panic,
+            message: match take_panic_report() {
+                Some(report) => format!("Perform panicked: {}", report),
+                None => "Perform panicked".to_string(),
+            },
+        },
+        recover_input.perform_id,
+        MessageExchangeFfi,
+    );
+}
+
+pub fn __export_oneclient_core_configure() {
+    let configure_input = ConfigureInput::take_in(MessageExchangeFfi).unwrap();
+
+    tracing::debug!(
+        "mocked oneclient core configure {:?}",
+        configure_input.configuration
+    );
+
+    set_configure_output_in(None, MessageExchangeFfi);
+}
+
+pub fn __export_oneclient_core_preload() {
+    let preload_input = PreloadInput::take_in(MessageExchangeFfi).unwrap();
+
+    tracing::debug!(
+        "mocked oneclient core preload {:?} {:?} {:?}",
+        preload_input.profile_urls,
+        preload_input.provider_urls,
+        preload_input.map_urls
+    );
+
+    set_preload_output_in(Vec::new(), MessageExchangeFfi);
+}
+
+pub fn __export_oneclient_core_invalidate_cache() {
+    let invalidate_input = InvalidateCacheInput::take_in(MessageExchangeFfi).unwrap();
+
+    tracing::debug!(
+        "mocked oneclient core invalidate cache {:?}",
+        invalidate_input.url
+    );
+}
+
+pub fn __export_oneclient_core_list_cache() {
+    tracing::debug!("mocked oneclient core list cache");
+
+    set_list_cache_output_in(Vec::new(), MessageExchangeFfi);
+}
@@ -11,7 +11,7 @@ mod vec;
 
 pub use self::{ring::RingEventBuffer, vec::VecEventBuffer};
 
-const EVENT_SEPARATOR: u8 = b'\0';
+pub(super) const EVENT_SEPARATOR: u8 = b'\0';
 
 /// Event buffer implementation.
 ///
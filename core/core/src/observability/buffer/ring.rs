@@ -4,6 +4,10 @@ use super::{TracingEventBuffer, EVENT_SEPARATOR};
 
 pub struct RingEventBuffer {
     data: VecDeque<u8>,
+    /// Number of complete events currently in the buffer.
+    event_count: usize,
+    /// Sequence number of the next event, which is also the number of events ever written.
+    next_seq: usize,
 }
 impl RingEventBuffer {
     pub fn new(size: usize) -> Self {
@@ -11,7 +15,49 @@ impl RingEventBuffer {
         let mut data = VecDeque::new();
         data.reserve_exact(size);
 
-        Self { data }
+        Self {
+            data,
+            event_count: 0,
+            next_seq: 0,
+        }
+    }
+
+    /// Sequence number of the next event written into the buffer.
+    pub fn next_seq(&self) -> usize {
+        self.next_seq
+    }
+
+    /// Sequence number of the oldest event still in the buffer.
+    pub fn first_seq(&self) -> usize {
+        self.next_seq - self.event_count
+    }
+
+    /// Like [as_raw_parts](TracingEventBuffer::as_raw_parts) but skips events with sequence number lower than `seq`.
+    pub fn as_raw_parts_since(&self, seq: usize) -> [(*const u8, usize); 2] {
+        let skip = seq.saturating_sub(self.first_seq());
+        let offset = if skip == 0 {
+            0
+        } else {
+            self.data
+                .iter()
+                .enumerate()
+                .filter(|(_, &b)| b == EVENT_SEPARATOR)
+                .nth(skip - 1)
+                .map(|(i, _)| i + 1)
+                .unwrap_or(self.data.len())
+        };
+
+        let (first, second) = self.data.as_slices();
+        let (first, second) = if offset < first.len() {
+            (&first[offset..], second)
+        } else {
+            (&second[offset - first.len()..], &second[second.len()..])
+        };
+
+        [
+            (first.as_ptr(), first.len()),
+            (second.as_ptr(), second.len()),
+        ]
     }
 
     fn free_len(&self) -> usize {
@@ -29,6 +75,7 @@ impl RingEventBuffer {
             },
         };
 
+        self.event_count -= 1;
         self.data.drain(0..event_len)
     }
 }
@@ -45,6 +92,10 @@ impl TracingEventBuffer for RingEventBuffer {
         }
 
         self.data.extend(data.iter().copied());
+
+        let finished_events = data.iter().filter(|&&b| b == EVENT_SEPARATOR).count();
+        self.event_count += finished_events;
+        self.next_seq += finished_events;
     }
 
     fn as_raw_parts(&self) -> [(*const u8, usize); 2] {
@@ -58,6 +109,7 @@ impl TracingEventBuffer for RingEventBuffer {
 
     fn clear(&mut self) {
         self.data.clear();
+        self.event_count = 0;
     }
 }
 impl std::fmt::Debug for RingEventBuffer {
@@ -114,4 +166,37 @@ mod test {
         assert_eq!(buffer.as_raw_parts()[0].1, 0);
         assert_eq!(buffer.as_raw_parts()[1].1, 0);
     }
+
+    #[test]
+    fn test_ring_buffer_since() {
+        let mut buffer = RingEventBuffer::new(10);
+        buffer.write(&[10, 11, 12, 0]);
+        buffer.write(&[13, 14, 15, 0]);
+        buffer.write(&[16, 0]);
+        assert_eq!(buffer.first_seq(), 0);
+        assert_eq!(buffer.next_seq(), 3);
+
+        let parts = buffer.as_raw_parts_since(1);
+        assert_eq!(parts[0].1 + parts[1].1, 6);
+
+        // the first event is popped to make room
+        buffer.write(&[17, 18, 0]);
+        assert_eq!(buffer.first_seq(), 1);
+        assert_eq!(buffer.next_seq(), 4);
+
+        let read = |parts: [(*const u8, usize); 2]| {
+            parts
+                .iter()
+                .flat_map(|&(ptr, len)| unsafe { std::slice::from_raw_parts(ptr, len) })
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            read(buffer.as_raw_parts_since(0)),
+            vec![13, 14, 15, 0, 16, 0, 17, 18, 0]
+        );
+        assert_eq!(read(buffer.as_raw_parts_since(3)), vec![17, 18, 0]);
+        assert_eq!(read(buffer.as_raw_parts_since(4)), Vec::<u8>::new());
+        assert_eq!(read(buffer.as_raw_parts_since(100)), Vec::<u8>::new());
+    }
 }
@@ -2,19 +2,72 @@ use crate::observability::buffer::EVENT_SEPARATOR;
 
 use super::TracingEventBuffer;
 
-/// Inbounded buffer backed by `Vec<u8>`.
+/// Buffer backed by `Vec<u8>` which holds at most `max_size` bytes.
+///
+/// Events which don't fit are dropped whole and counted, so that the buffer doesn't grow without bound
+/// when the host doesn't clear it.
 pub struct VecEventBuffer {
     /// Each event is terminated by a null byte. Otherwise there can be no null bytes in the strings because they are utf-8.
     data: Vec<u8>,
+    max_size: usize,
+    /// Start of the event currently being written.
+    event_start: usize,
+    /// Whether the event currently being written did not fit and is being dropped.
+    overflowing: bool,
+    /// Number of events dropped because the buffer was full.
+    dropped_events: usize,
 }
 impl VecEventBuffer {
-    pub fn new() -> Self {
-        Self { data: Vec::new() }
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            data: Vec::new(),
+            max_size,
+            event_start: 0,
+            overflowing: false,
+            dropped_events: 0,
+        }
+    }
+
+    /// Total number of events dropped because the buffer was full.
+    pub fn dropped_events(&self) -> usize {
+        self.dropped_events
+    }
+
+    fn write_partial(&mut self, data: &[u8]) {
+        if self.overflowing {
+            return;
+        }
+
+        // reserve one byte for the separator
+        if self.data.len() + data.len() + 1 > self.max_size {
+            self.data.truncate(self.event_start);
+            self.overflowing = true;
+        } else {
+            self.data.extend_from_slice(data);
+        }
+    }
+
+    fn finish_event(&mut self) {
+        if self.overflowing {
+            self.overflowing = false;
+            self.dropped_events += 1;
+        } else {
+            self.data.push(EVENT_SEPARATOR);
+        }
+        self.event_start = self.data.len();
     }
 }
 impl TracingEventBuffer for VecEventBuffer {
     fn write(&mut self, data: &[u8]) {
-        self.data.extend(data.iter().copied())
+        // the first chunk continues the current event, each following chunk starts a new event
+        let mut chunks = data.split(|&b| b == EVENT_SEPARATOR);
+        if let Some(chunk) = chunks.next() {
+            self.write_partial(chunk);
+        }
+        for chunk in chunks {
+            self.finish_event();
+            self.write_partial(chunk);
+        }
     }
 
     fn as_raw_parts(&self) -> [(*const u8, usize); 2] {
@@ -23,15 +76,56 @@ impl TracingEventBuffer for VecEventBuffer {
 
     fn clear(&mut self) {
         self.data.clear();
+        self.event_start = 0;
+        self.overflowing = false;
     }
 }
 impl std::fmt::Debug for VecEventBuffer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "VecBuffer(<{} events, {} bytes>)",
+            "VecBuffer(<{} events, {} bytes, {} dropped>)",
             self.data.iter().filter(|&&b| b == EVENT_SEPARATOR).count(),
-            self.data.len()
+            self.data.len(),
+            self.dropped_events
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::observability::buffer::TracingEventBuffer;
+
+    use super::VecEventBuffer;
+
+    #[test]
+    fn test_vec_buffer_write() {
+        let mut buffer = VecEventBuffer::new(10);
+        buffer.write(&[10, 11]);
+        buffer.write(&[12, 0]);
+        buffer.write(&[13, 0, 14, 0]);
+
+        assert_eq!(buffer.data, &[10, 11, 12, 0, 13, 0, 14, 0]);
+        assert_eq!(buffer.dropped_events(), 0);
+    }
+
+    #[test]
+    fn test_vec_buffer_overflow() {
+        let mut buffer = VecEventBuffer::new(8);
+        buffer.write(&[10, 11, 12, 0]);
+        // does not fit, is dropped whole even though the first part fits
+        buffer.write(&[13, 14]);
+        buffer.write(&[15, 16]);
+        buffer.write(&[0]);
+        // fits again
+        buffer.write(&[17, 0]);
+
+        assert_eq!(buffer.data, &[10, 11, 12, 0, 17, 0]);
+        assert_eq!(buffer.dropped_events(), 1);
+
+        buffer.clear();
+        buffer.write(&[13, 14, 15, 16, 0]);
+        assert_eq!(buffer.data, &[13, 14, 15, 16, 0]);
+        assert_eq!(buffer.dropped_events(), 1);
+    }
+}
@@ -0,0 +1,124 @@
+//! Structured format of the developer dump.
+//!
+//! Each event is written into the dump buffer as a JSON object with its sequence number, so that the host can request
+//! only events it hasn't seen yet.
+
+use serde_json::{Map, Value};
+use tracing::{
+    span::{Attributes, Id, Record},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use super::{
+    buffer::{RingEventBuffer, SharedEventBuffer, TracingEventBuffer},
+    user_log::JsonVisitor,
+};
+
+/// Fields of a span recorded so far, stored in the span extensions.
+struct DumpSpanFields(Map<String, Value>);
+
+/// Tracing layer which writes events as JSON objects into the developer dump buffer.
+pub struct DeveloperDumpJsonLayer {
+    buffer: SharedEventBuffer<RingEventBuffer>,
+}
+impl DeveloperDumpJsonLayer {
+    pub fn new(buffer: SharedEventBuffer<RingEventBuffer>) -> Self {
+        Self { buffer }
+    }
+}
+impl<S> Layer<S> for DeveloperDumpJsonLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("Span must exist");
+
+        let mut fields = Map::new();
+        attrs.record(&mut JsonVisitor(&mut fields));
+        span.extensions_mut().insert(DumpSpanFields(fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("Span must exist");
+        let mut extensions = span.extensions_mut();
+
+        if let Some(DumpSpanFields(fields)) = extensions.get_mut::<DumpSpanFields>() {
+            values.record(&mut JsonVisitor(fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let spans: Vec<Value> = ctx
+            .event_scope(event)
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| {
+                        let extensions = span.extensions();
+                        let fields = extensions
+                            .get::<DumpSpanFields>()
+                            .map(|DumpSpanFields(fields)| fields.clone())
+                            .unwrap_or_default();
+
+                        serde_json::json!({ "name": span.name(), "fields": fields })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+
+        // sequence number must be taken under the same lock as the write
+        let mut buffer = self.buffer.lock();
+        let line = serde_json::json!({
+            "seq": buffer.next_seq(),
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "level": event.metadata().level().as_str(),
+            "target": event.metadata().target(),
+            "spans": spans,
+            "fields": fields,
+        });
+
+        buffer.write(line.to_string().as_bytes());
+        buffer.write(&[super::buffer::EVENT_SEPARATOR]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn test_developer_dump_json() {
+        let buffer = SharedEventBuffer::new(RingEventBuffer::new(1024 * 1024));
+        let subscriber =
+            tracing_subscriber::registry().with(DeveloperDumpJsonLayer::new(buffer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!("before");
+            let _span = tracing::info_span!("perform", perform_id = "abc").entered();
+            tracing::warn!(status = 500, "Request failed");
+        });
+
+        let buffer = buffer.lock();
+        let [(ptr, len), _] = buffer.as_raw_parts_since(1);
+        let data = unsafe { std::slice::from_raw_parts(ptr, len) };
+        let events: Vec<Value> = data
+            .split(|&b| b == 0)
+            .filter(|event| !event.is_empty())
+            .map(|event| serde_json::from_slice(event).unwrap())
+            .collect();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["seq"], 1);
+        assert_eq!(events[0]["level"], "WARN");
+        assert_eq!(events[0]["spans"][0]["name"], "perform");
+        assert_eq!(events[0]["spans"][0]["fields"]["perform_id"], "abc");
+        assert_eq!(events[0]["fields"]["message"], "Request failed");
+        assert_eq!(events[0]["fields"]["status"], 500);
+    }
+}
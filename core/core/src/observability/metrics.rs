@@ -1,6 +1,127 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use serde::{Serialize, Serializer};
+
+mod aggregate;
+
+pub use self::aggregate::{MetricsAggregator, PerformRecord};
+
+/// Wall times of the phases of a perform.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct PerformTimings {
+    /// The whole perform, from receiving the input to sending the result
+    #[serde(rename = "total_ms", serialize_with = "serialize_duration_ms")]
+    pub total: Option<Duration>,
+    /// Loading profile, provider and map documents, either from the cache or from their sources
+    #[serde(rename = "document_load_ms", serialize_with = "serialize_duration_ms")]
+    pub document_load: Option<Duration>,
+    /// Validating inputs and documents and preparing the map context
+    #[serde(rename = "validation_ms", serialize_with = "serialize_duration_ms")]
+    pub validation: Option<Duration>,
+    /// Initializing the interpreter and running the map
+    #[serde(rename = "interpretation_ms", serialize_with = "serialize_duration_ms")]
+    pub interpretation: Option<Duration>,
+}
+
+fn serialize_duration_ms<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    duration
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .serialize(serializer)
+}
+
+/// Statistics of outbound HTTP calls made by a map.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct HttpCallMetrics {
+    /// Number of HTTP calls started
+    pub calls: usize,
+    /// Number of calls which didn't receive a response
+    pub failed_calls: usize,
+    /// Number of received responses by status code class
+    pub status_buckets: HttpStatusBuckets,
+    /// Number of request body bytes sent
+    pub bytes_sent: usize,
+    /// Number of response body bytes read by the map
+    pub bytes_received: usize,
+}
+impl HttpCallMetrics {
+    /// Adds the counts of `other` to this.
+    pub fn merge(&mut self, other: &HttpCallMetrics) {
+        self.calls += other.calls;
+        self.failed_calls += other.failed_calls;
+        self.status_buckets.informational += other.status_buckets.informational;
+        self.status_buckets.success += other.status_buckets.success;
+        self.status_buckets.redirection += other.status_buckets.redirection;
+        self.status_buckets.client_error += other.status_buckets.client_error;
+        self.status_buckets.server_error += other.status_buckets.server_error;
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_received += other.bytes_received;
+    }
+
+    pub fn record_status(&mut self, status: u16) {
+        let bucket = match status {
+            100..=199 => &mut self.status_buckets.informational,
+            200..=299 => &mut self.status_buckets.success,
+            300..=399 => &mut self.status_buckets.redirection,
+            400..=499 => &mut self.status_buckets.client_error,
+            _ => &mut self.status_buckets.server_error,
+        };
+
+        *bucket += 1;
+    }
+}
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct HttpStatusBuckets {
+    #[serde(rename = "1xx")]
+    pub informational: usize,
+    #[serde(rename = "2xx")]
+    pub success: usize,
+    #[serde(rename = "3xx")]
+    pub redirection: usize,
+    #[serde(rename = "4xx")]
+    pub client_error: usize,
+    /// Also counts invalid status codes
+    #[serde(rename = "5xx")]
+    pub server_error: usize,
+}
+
+/// Configuration of this core instance reported in metrics events.
+#[derive(Debug)]
+pub struct SdkConfiguration {
+    /// Hash of the core configuration, see [CoreConfiguration::configuration_hash](crate::sf_core::CoreConfiguration::configuration_hash)
+    pub configuration_hash: String,
+    /// Providers by profile which this instance has performed or preloaded
+    pub profiles: BTreeMap<String, BTreeSet<String>>,
+}
+impl SdkConfiguration {
+    pub fn new(configuration_hash: String) -> Self {
+        Self {
+            configuration_hash,
+            profiles: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the profile and provider pair, returns whether it wasn't present before.
+    pub fn register(&mut self, profile: &str, provider: &str) -> bool {
+        match self.profiles.get_mut(profile) {
+            Some(providers) => providers.insert(provider.to_string()),
+            None => {
+                self.profiles
+                    .insert(profile.to_string(), BTreeSet::from([provider.to_string()]));
+                true
+            }
+        }
+    }
+}
+
 /// Public module so that the macro can access it, but should not be used outside of the parent module.
 pub mod __private {
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, BTreeSet};
 
     use serde::Serialize;
 
@@ -32,14 +153,20 @@ pub mod __private {
     }
     #[derive(Debug, Serialize)]
     pub struct SdkInitDataConfiguration<'a> {
-        pub profiles: HashMap<&'a str, ()>,
+        pub profiles: BTreeMap<&'a str, SdkInitDataProfile<'a>>,
+    }
+    #[derive(Debug, Serialize)]
+    pub struct SdkInitDataProfile<'a> {
+        pub providers: &'a BTreeSet<String>,
     }
 
     #[derive(Debug, Serialize)]
     pub struct MetricsData<'a> {
         pub from: &'a str,
         pub to: &'a str,
-        pub metrics: [MetricsDataEntry<'a>; 1],
+        pub metrics: Vec<MetricsDataEntry<'a>>,
+        /// Total number of metric events dropped because the metrics buffer was full
+        pub dropped_events: usize,
     }
     #[derive(Debug, Serialize)]
     #[serde(tag = "type")]
@@ -47,8 +174,8 @@ pub mod __private {
         PerformMetrics {
             profile: &'a str,
             provider: &'a str,
-            successful_performs: usize,
-            failed_performs: usize,
+            #[serde(flatten)]
+            aggregate: &'a super::aggregate::PerformAggregate,
         },
     }
 
@@ -74,31 +201,177 @@ pub mod __private {
 
         serde_json::to_writer(writer, &event).unwrap();
     }
+
+    pub fn log_sdk_init() {
+        let configuration = unsafe {
+            crate::observability::SDK_CONFIGURATION
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+        };
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+        let event = Event::SdkInit {
+            occurred_at: &now,
+            configuration_hash: Some(&configuration.configuration_hash),
+            data: SdkInitData {
+                configuration: SdkInitDataConfiguration {
+                    profiles: configuration
+                        .profiles
+                        .iter()
+                        .map(|(profile, providers)| {
+                            (profile.as_str(), SdkInitDataProfile { providers })
+                        })
+                        .collect(),
+                },
+            },
+        };
+        tracing::info!(target: "@metrics", event = ?event);
+        log_metric_event(event);
+    }
+
+    pub fn configuration_hash() -> String {
+        unsafe {
+            crate::observability::SDK_CONFIGURATION
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .configuration_hash
+                .clone()
+        }
+    }
+
+    pub fn record_perform(record: super::PerformRecord) {
+        let now = chrono::Utc::now();
+
+        if record.profile != "unknown" && record.provider != "unknown" {
+            super::register_profile(record.profile, record.provider);
+        }
+
+        tracing::debug!(target: "@metrics", perform = ?record);
+        let window = {
+            let mut aggregator = unsafe {
+                crate::observability::METRICS_AGGREGATOR
+                    .as_ref()
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+            };
+            aggregator.record(now, record);
+
+            aggregator.take_elapsed(now)
+        };
+
+        if let Some(window) = window {
+            log_metrics_window(window);
+        }
+    }
+
+    pub fn log_metrics_window(window: super::aggregate::MetricsWindow) {
+        let from = window
+            .from
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let to = window
+            .to
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let dropped_events = unsafe {
+            crate::observability::METRICS_BUFFER
+                .as_ref()
+                .unwrap()
+                .lock()
+                .dropped_events()
+        };
+
+        let configuration_hash = configuration_hash();
+
+        let event = Event::Metrics {
+            occurred_at: &to,
+            configuration_hash: Some(&configuration_hash),
+            data: MetricsData {
+                from: &from,
+                to: &to,
+                metrics: window
+                    .performs
+                    .iter()
+                    .map(
+                        |((profile, provider), aggregate)| MetricsDataEntry::PerformMetrics {
+                            profile,
+                            provider,
+                            aggregate,
+                        },
+                    )
+                    .collect(),
+                dropped_events,
+            },
+        };
+        tracing::info!(target: "@metrics", event = ?event);
+        log_metric_event(event);
+    }
+}
+
+/// Reports that this instance uses `profile` with `provider`.
+///
+/// The SDK init event is emitted again with the updated profile list when the pair is new.
+pub fn register_profile(profile: &str, provider: &str) {
+    let is_new = unsafe {
+        match crate::observability::SDK_CONFIGURATION {
+            Some(ref configuration) => configuration.lock().unwrap().register(profile, provider),
+            None => false,
+        }
+    };
+
+    if is_new {
+        __private::log_sdk_init();
+    }
+}
+
+/// Updates the configuration hash after core was reconfigured, the SDK init event is emitted again if it changed.
+pub fn set_configuration_hash(configuration_hash: String) {
+    let is_changed = unsafe {
+        match crate::observability::SDK_CONFIGURATION {
+            Some(ref configuration) => {
+                let mut configuration = configuration.lock().unwrap();
+                let is_changed = configuration.configuration_hash != configuration_hash;
+                configuration.configuration_hash = configuration_hash;
+                is_changed
+            }
+            None => false,
+        }
+    };
+
+    if is_changed {
+        __private::log_sdk_init();
+    }
+}
+
+/// Emits aggregated metrics of the current window even if the window hasn't elapsed yet.
+///
+/// Called before the host reads the metrics buffer so that it always receives all the recorded performs.
+pub fn flush_metrics() {
+    let window = unsafe {
+        match crate::observability::METRICS_AGGREGATOR {
+            Some(ref aggregator) => aggregator.lock().unwrap().take(chrono::Utc::now()),
+            None => None,
+        }
+    };
+
+    if let Some(window) = window {
+        __private::log_metrics_window(window);
+    }
 }
 
 macro_rules! log_metric {
     (
         Init
-        // TODO
     ) => {
-        {
-            use $crate::observability::metrics::{log_metric, __private::*};
-            let now = log_metric!(__internal now());
-            log_metric!(
-                __internal common()
-                Event::SdkInit {
-                    occurred_at: &now,
-                    configuration_hash: None,
-                    data: SdkInitData {
-                        configuration: SdkInitDataConfiguration { profiles: Default::default() }
-                    }
-                }
-            );
-        }
+        $crate::observability::metrics::__private::log_sdk_init()
     };
 
     (
         Perform
+        perform_id = $perform_id: expr,
         success = $is_success: expr,
         profile = $profile: expr,
         profile_url = $profile_url: expr,
@@ -107,31 +380,25 @@ macro_rules! log_metric {
         provider_url = $provider_url: expr,
         provider_content_hash = $provider_content_hash: expr,
         map_url = $map_url: expr,
-        map_content_hash = $map_content_hash: expr
+        map_content_hash = $map_content_hash: expr,
+        timings = $timings: expr,
+        http = $http: expr
         $(,)?
     ) => {
         {
-            use $crate::observability::metrics::{log_metric, __private::*};
+            use $crate::observability::metrics::{PerformRecord, __private::*};
 
-            let now = log_metric!(__internal now());
-            let (successful_performs, failed_performs) = if $is_success { (1, 0) } else { (0, 1) };
-            log_metric!(
-                __internal common()
-                Event::Metrics {
-                    occurred_at: &now,
-                    configuration_hash: None,
-                    data: MetricsData {
-                        from: &now,
-                        to: &now,
-                        metrics: [MetricsDataEntry::PerformMetrics {
-                            profile: $profile,
-                            provider: $provider,
-                            successful_performs,
-                            failed_performs
-                        }]
-                    }
-                }
-            );
+            record_perform(PerformRecord {
+                perform_id: $perform_id,
+                success: $is_success,
+                profile: $profile,
+                provider: $provider,
+                profile_content_hash: $profile_content_hash,
+                provider_content_hash: $provider_content_hash,
+                map_content_hash: $map_content_hash,
+                timings: $timings,
+                http: $http,
+            });
         }
     };
 
@@ -186,3 +453,77 @@ macro_rules! log_metric {
 
 // TIL trick to make scoped crate-wide macros
 pub(crate) use log_metric;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_http_status_buckets() {
+        let mut metrics = HttpCallMetrics::default();
+        for status in [101, 200, 204, 301, 404, 429, 500, 999] {
+            metrics.record_status(status);
+        }
+
+        assert_eq!(
+            serde_json::to_value(&metrics.status_buckets).unwrap(),
+            serde_json::json!({ "1xx": 1, "2xx": 2, "3xx": 1, "4xx": 2, "5xx": 2 })
+        );
+    }
+
+    #[test]
+    fn test_sdk_configuration_register() {
+        let mut configuration = SdkConfiguration::new("hash".to_string());
+
+        assert!(configuration.register("scope/name", "provider"));
+        assert!(configuration.register("scope/name", "other"));
+        assert!(configuration.register("other", "provider"));
+        assert!(!configuration.register("scope/name", "provider"));
+
+        let providers = &configuration.profiles["scope/name"];
+        assert_eq!(
+            providers.iter().map(String::as_str).collect::<Vec<_>>(),
+            ["other", "provider"]
+        );
+    }
+
+    #[test]
+    fn test_perform_metrics_entry_serialize() {
+        let aggregate = aggregate::PerformAggregate {
+            successful_performs: 2,
+            ..Default::default()
+        };
+        let entry = __private::MetricsDataEntry::PerformMetrics {
+            profile: "scope/name",
+            provider: "provider",
+            aggregate: &aggregate,
+        };
+
+        let value = serde_json::to_value(&entry).unwrap();
+        assert_eq!(value["type"], "PerformMetrics");
+        assert_eq!(value["profile"], "scope/name");
+        assert_eq!(value["successful_performs"], 2);
+        assert_eq!(value["failed_performs"], 0);
+        assert_eq!(value["http"]["calls"], 0);
+    }
+
+    #[test]
+    fn test_perform_timings_serialize() {
+        let timings = PerformTimings {
+            total: Some(Duration::from_millis(1500)),
+            document_load: Some(Duration::from_millis(250)),
+            validation: None,
+            interpretation: None,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&timings).unwrap(),
+            serde_json::json!({
+                "total_ms": 1500.0,
+                "document_load_ms": 250.0,
+                "validation_ms": null,
+                "interpretation_ms": null
+            })
+        );
+    }
+}
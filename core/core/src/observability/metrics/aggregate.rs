@@ -0,0 +1,275 @@
+//! Aggregation of perform metrics over a time window.
+//!
+//! Instead of sending one event per perform, performs are aggregated per profile and provider and one event
+//! is emitted for each window.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::{HttpCallMetrics, PerformTimings};
+
+/// Number of most recent failed perform ids kept per aggregate.
+const MAX_FAILED_PERFORM_IDS: usize = 10;
+
+/// Upper bounds of latency histogram buckets in milliseconds, the last bucket is unbounded.
+const LATENCY_BUCKET_BOUNDS_MS: [f64; 11] = [
+    5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyHistogram {
+    bounds_ms: &'static [f64],
+    /// Number of values in each bucket, has one more bucket than there are bounds
+    counts: [usize; LATENCY_BUCKET_BOUNDS_MS.len() + 1],
+    count: usize,
+    sum_ms: f64,
+}
+impl LatencyHistogram {
+    pub fn record(&mut self, value_ms: f64) {
+        let bucket = LATENCY_BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| value_ms <= bound)
+            .unwrap_or(LATENCY_BUCKET_BOUNDS_MS.len());
+
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_ms += value_ms;
+    }
+}
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            bounds_ms: &LATENCY_BUCKET_BOUNDS_MS,
+            counts: Default::default(),
+            count: 0,
+            sum_ms: 0.0,
+        }
+    }
+}
+
+/// Sums of perform phase wall times, divide by the number of performs to get averages.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PhaseTimeSums {
+    pub document_load_ms: f64,
+    pub validation_ms: f64,
+    pub interpretation_ms: f64,
+}
+
+/// Metrics aggregated for one profile and provider pair.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PerformAggregate {
+    pub successful_performs: usize,
+    pub failed_performs: usize,
+    pub success_latency: LatencyHistogram,
+    pub failure_latency: LatencyHistogram,
+    pub phase_time_sums: PhaseTimeSums,
+    pub http: HttpCallMetrics,
+    /// Ids of the most recent failed performs, to look them up in logs
+    pub failed_perform_ids: VecDeque<String>,
+    /// Content hashes seen in the last perform
+    pub profile_content_hash: Option<String>,
+    pub provider_content_hash: Option<String>,
+    pub map_content_hash: Option<String>,
+}
+
+/// Data of a single finished perform.
+#[derive(Debug)]
+pub struct PerformRecord<'a> {
+    pub perform_id: &'a str,
+    pub success: bool,
+    pub profile: &'a str,
+    pub provider: &'a str,
+    pub profile_content_hash: Option<&'a str>,
+    pub provider_content_hash: Option<&'a str>,
+    pub map_content_hash: Option<&'a str>,
+    pub timings: &'a PerformTimings,
+    pub http: &'a HttpCallMetrics,
+}
+
+/// Aggregated metrics of one window.
+#[derive(Debug)]
+pub struct MetricsWindow {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Aggregates keyed by (profile, provider)
+    pub performs: BTreeMap<(String, String), PerformAggregate>,
+}
+
+#[derive(Debug)]
+pub struct MetricsAggregator {
+    window: std::time::Duration,
+    window_start: Option<DateTime<Utc>>,
+    performs: BTreeMap<(String, String), PerformAggregate>,
+}
+impl MetricsAggregator {
+    pub fn new(window: std::time::Duration) -> Self {
+        Self {
+            window,
+            window_start: None,
+            performs: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, now: DateTime<Utc>, record: PerformRecord) {
+        self.window_start.get_or_insert(now);
+
+        let aggregate = self
+            .performs
+            .entry((record.profile.to_string(), record.provider.to_string()))
+            .or_default();
+
+        let to_ms = |d: Option<std::time::Duration>| d.map(|d| d.as_secs_f64() * 1000.0);
+        let total_ms = to_ms(record.timings.total).unwrap_or(0.0);
+        if record.success {
+            aggregate.successful_performs += 1;
+            aggregate.success_latency.record(total_ms);
+        } else {
+            aggregate.failed_performs += 1;
+            aggregate.failure_latency.record(total_ms);
+
+            if aggregate.failed_perform_ids.len() == MAX_FAILED_PERFORM_IDS {
+                aggregate.failed_perform_ids.pop_front();
+            }
+            aggregate
+                .failed_perform_ids
+                .push_back(record.perform_id.to_string());
+        }
+
+        let sums = &mut aggregate.phase_time_sums;
+        sums.document_load_ms += to_ms(record.timings.document_load).unwrap_or(0.0);
+        sums.validation_ms += to_ms(record.timings.validation).unwrap_or(0.0);
+        sums.interpretation_ms += to_ms(record.timings.interpretation).unwrap_or(0.0);
+
+        aggregate.http.merge(record.http);
+
+        if let Some(hash) = record.profile_content_hash {
+            aggregate.profile_content_hash = Some(hash.to_string());
+        }
+        if let Some(hash) = record.provider_content_hash {
+            aggregate.provider_content_hash = Some(hash.to_string());
+        }
+        if let Some(hash) = record.map_content_hash {
+            aggregate.map_content_hash = Some(hash.to_string());
+        }
+    }
+
+    /// Takes the current window if it is older than the configured window duration.
+    pub fn take_elapsed(&mut self, now: DateTime<Utc>) -> Option<MetricsWindow> {
+        match self.window_start {
+            // negative durations mean the clock went backwards
+            Some(start)
+                if (now - start)
+                    .to_std()
+                    .map_or(false, |age| age >= self.window) =>
+            {
+                self.take(now)
+            }
+            _ => None,
+        }
+    }
+
+    /// Takes the current window regardless of its age, returns `None` if nothing was recorded.
+    pub fn take(&mut self, now: DateTime<Utc>) -> Option<MetricsWindow> {
+        let from = self.window_start.take()?;
+
+        Some(MetricsWindow {
+            from,
+            to: now,
+            performs: std::mem::take(&mut self.performs),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn record<'a>(
+        perform_id: &'a str,
+        success: bool,
+        provider: &'a str,
+        timings: &'a PerformTimings,
+        http: &'a HttpCallMetrics,
+    ) -> PerformRecord<'a> {
+        PerformRecord {
+            perform_id,
+            success,
+            profile: "scope/name",
+            provider,
+            profile_content_hash: Some("profile-hash"),
+            provider_content_hash: None,
+            map_content_hash: None,
+            timings,
+            http,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_window() {
+        let start = Utc::now();
+        let mut aggregator = MetricsAggregator::new(Duration::from_secs(60));
+        let timings = PerformTimings {
+            total: Some(Duration::from_millis(20)),
+            interpretation: Some(Duration::from_millis(250)),
+            ..Default::default()
+        };
+        let http = HttpCallMetrics {
+            calls: 2,
+            bytes_received: 100,
+            ..Default::default()
+        };
+
+        aggregator.record(start, record("1", true, "first", &timings, &http));
+        aggregator.record(start, record("2", true, "first", &timings, &http));
+        aggregator.record(start, record("3", false, "first", &timings, &http));
+        aggregator.record(start, record("4", true, "second", &timings, &http));
+
+        assert!(aggregator
+            .take_elapsed(start + chrono::Duration::seconds(30))
+            .is_none());
+
+        let end = start + chrono::Duration::seconds(60);
+        let window = aggregator.take_elapsed(end).unwrap();
+        assert_eq!(window.from, start);
+        assert_eq!(window.to, end);
+        assert_eq!(window.performs.len(), 2);
+
+        let first = &window.performs[&("scope/name".to_string(), "first".to_string())];
+        assert_eq!(first.successful_performs, 2);
+        assert_eq!(first.failed_performs, 1);
+        assert_eq!(first.success_latency.count, 2);
+        assert_eq!(first.success_latency.counts[2], 2);
+        assert_eq!(first.failure_latency.count, 1);
+        assert_eq!(first.phase_time_sums.interpretation_ms, 750.0);
+        assert_eq!(first.http.calls, 6);
+        assert_eq!(first.http.bytes_received, 300);
+        assert_eq!(first.profile_content_hash.as_deref(), Some("profile-hash"));
+        assert_eq!(first.failed_perform_ids, ["3"]);
+
+        // nothing left after the window is taken
+        assert!(aggregator.take(end).is_none());
+    }
+
+    #[test]
+    fn test_aggregate_failed_perform_ids_bounded() {
+        let mut aggregator = MetricsAggregator::new(Duration::from_secs(60));
+        let timings = PerformTimings::default();
+        let http = HttpCallMetrics::default();
+
+        let ids: Vec<String> = (0..MAX_FAILED_PERFORM_IDS + 2)
+            .map(|i| i.to_string())
+            .collect();
+        for id in ids.iter() {
+            aggregator.record(Utc::now(), record(id, false, "first", &timings, &http));
+        }
+
+        let window = aggregator.take(Utc::now()).unwrap();
+        let first = &window.performs[&("scope/name".to_string(), "first".to_string())];
+        assert_eq!(first.failed_performs, MAX_FAILED_PERFORM_IDS + 2);
+        assert_eq!(first.failed_perform_ids, &ids[2..]);
+    }
+}
@@ -1,38 +1,118 @@
-use std::{borrow::Cow, ops::Deref};
+use std::{
+    borrow::Cow,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
 
 use sf_std::abi::{Ptr, Size};
-use tracing::metadata::LevelFilter;
+use tracing::{metadata::LevelFilter, Level};
 use tracing_subscriber::{
-    filter::FilterFn, fmt::format, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+    filter::FilterFn, fmt::format, layer::SubscriberExt, reload, util::SubscriberInitExt,
+    EnvFilter, Layer,
 };
 
-use self::buffer::{RingEventBuffer, SharedEventBuffer, TracingEventBuffer, VecEventBuffer};
-use crate::sf_core::CoreConfiguration;
+use self::{
+    buffer::{RingEventBuffer, SharedEventBuffer, TracingEventBuffer, VecEventBuffer},
+    developer_dump::DeveloperDumpJsonLayer,
+    metrics::{MetricsAggregator, SdkConfiguration},
+    traces::OtlpTraceLayer,
+    user_log::UserJsonFormat,
+};
+use crate::sf_core::{CoreConfiguration, DeveloperDumpFormat, UserLogFormat};
 
 mod buffer;
+mod developer_dump;
 pub mod metrics;
+pub mod traces;
+mod user_log;
 
 static mut METRICS_BUFFER: Option<SharedEventBuffer<VecEventBuffer>> = None;
+static mut METRICS_AGGREGATOR: Option<Mutex<MetricsAggregator>> = None;
+static mut SDK_CONFIGURATION: Option<Mutex<SdkConfiguration>> = None;
 static mut DEVELOPER_DUMP_BUFFER: Option<SharedEventBuffer<RingEventBuffer>> = None;
+static mut TRACES_BUFFER: Option<SharedEventBuffer<VecEventBuffer>> = None;
+/// Whether the user log is enabled, can be changed at runtime.
+static USER_LOG: AtomicBool = AtomicBool::new(false);
+type ReloadFilterFn = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send>;
+/// Replaces the filter of the developer log, set once tracing is initialized.
+static DEVELOPER_LOG_RELOAD: Mutex<Option<ReloadFilterFn>> = Mutex::new(None);
+/// Report of the last panic.
+///
+/// Panics abort on wasm, so this is how core learns about the panic once the host recovers it.
+static LAST_PANIC: Mutex<Option<PanicReport>> = Mutex::new(None);
+
+/// Message and location of a panic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicReport {
+    pub message: String,
+    pub location: Option<String>,
+}
+impl PanicReport {
+    pub fn new(message: &str, location: Option<&std::panic::Location>) -> Self {
+        Self {
+            message: message.to_string(),
+            location: location.map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+        }
+    }
+}
+impl std::fmt::Display for PanicReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.location {
+            Some(ref location) => write!(f, "{} at {}", self.message, location),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Keeps `report` until it is taken with [take_panic_report].
+pub fn store_panic_report(report: PanicReport) {
+    // the panic might have happened while the lock was held, the report is lost then
+    if let Ok(mut last) = LAST_PANIC.try_lock() {
+        last.replace(report);
+    }
+}
+
+/// Takes the report of the last panic, if any.
+pub fn take_panic_report() -> Option<PanicReport> {
+    LAST_PANIC.lock().unwrap().take()
+}
 
 /// SAFETY: must only be called once during initialization of the program
 pub unsafe fn init(config: &CoreConfiguration) {
     // SAFETY: this is only called once and there is no asynchronous mutation
     unsafe {
-        METRICS_BUFFER.replace(SharedEventBuffer::new(VecEventBuffer::new()));
+        METRICS_BUFFER.replace(SharedEventBuffer::new(VecEventBuffer::new(
+            config.metrics_buffer_size,
+        )));
+        METRICS_AGGREGATOR.replace(Mutex::new(MetricsAggregator::new(config.metrics_window)));
+        SDK_CONFIGURATION.replace(Mutex::new(SdkConfiguration::new(
+            config.configuration_hash(),
+        )));
         DEVELOPER_DUMP_BUFFER.replace(SharedEventBuffer::new(RingEventBuffer::new(
             config.developer_dump_buffer_size,
         )));
+        if config.trace_export {
+            TRACES_BUFFER.replace(SharedEventBuffer::new(VecEventBuffer::new(
+                config.traces_buffer_size,
+            )));
+        }
 
         init_tracing(
             // METRICS_BUFFER.as_ref().cloned().unwrap(),
             DEVELOPER_DUMP_BUFFER.as_ref().cloned().unwrap(),
+            config.developer_dump_format,
+            config.developer_dump_level,
+            TRACES_BUFFER.as_ref().cloned(),
             config.user_log,
+            config.user_log_format,
             &config.developer_log,
         );
     }
 
-    // add panic hook so we can log panics as metrics
+    // add panic hook so we can log panics as metrics and report them to the host
     std::panic::set_hook(Box::new(|info| {
         let message: Cow<'_, str> = if let Some(message) = info.payload().downcast_ref::<&str>() {
             (*message).into()
@@ -52,6 +132,7 @@ pub unsafe fn init(config: &CoreConfiguration) {
             message = message.as_ref(),
             location = ?info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
         );
+        store_panic_report(PanicReport::new(message.as_ref(), info.location()));
     }));
 }
 
@@ -59,7 +140,11 @@ fn init_tracing(
     // TODO: we don't use tracing to store metrics in the metrics buffer because we need more complex fields than tracing currently supports
     // _metrics_buffer: SharedEventBuffer<VecEventBuffer>,
     developer_dump_buffer: SharedEventBuffer<RingEventBuffer>,
+    developer_dump_format: DeveloperDumpFormat,
+    developer_dump_level: LevelFilter,
+    traces_buffer: Option<SharedEventBuffer<VecEventBuffer>>,
     user_log: bool,
+    user_log_format: UserLogFormat,
     developer_log: &str,
 ) {
     // we set up these layers:
@@ -67,34 +152,106 @@ fn init_tracing(
     // * metrics layer (@metrics) - metrics sent to the dashboard
     // * developer layer (everything) - output not relevant for normal users, but relevant when debugging and during development
     // * dump layer (not @metrics) - output dumped after a panic, excluding metrics which are dumped separately
+    // * traces layer (not @metrics) - spans exported in OpenTelemetry format, only when enabled
 
-    let user_layer = tracing_subscriber::fmt::layer()
-        .event_format(format::format().with_target(false).with_level(false))
-        .with_writer(std::io::stdout)
-        .with_filter(FilterFn::new(move |metadata| {
-            user_log && metadata.target().starts_with("@user")
-        }));
+    USER_LOG.store(user_log, Ordering::Relaxed);
+    let user_filter = move || {
+        FilterFn::new(move |metadata| {
+            USER_LOG.load(Ordering::Relaxed) && metadata.target().starts_with("@user")
+        })
+    };
+    let (user_text_layer, user_json_layer) = match user_log_format {
+        UserLogFormat::Text => (
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .event_format(format::format().with_target(false).with_level(false))
+                    .with_writer(std::io::stdout)
+                    .with_filter(user_filter()),
+            ),
+            None,
+        ),
+        UserLogFormat::Json => (
+            None,
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .fmt_fields(format::JsonFields::new())
+                    .event_format(UserJsonFormat)
+                    .with_writer(std::io::stdout)
+                    .with_filter(user_filter()),
+            ),
+        ),
+    };
 
+    let (developer_filter, developer_filter_handle) =
+        reload::Layer::new(developer_log_filter(developer_log));
     let developer_layer = tracing_subscriber::fmt::layer()
         .with_writer(std::io::stderr)
-        .with_filter(
-            EnvFilter::builder()
-                .with_default_directive(LevelFilter::OFF.into())
-                .parse_lossy(developer_log),
-        );
+        .with_filter(developer_filter);
 
-    let developer_dump_layer = tracing_subscriber::fmt::layer()
-        .with_writer(developer_dump_buffer)
-        .event_format(
-            format::format().with_ansi(false), // disable ansi colors because this will usually go into a file
-        )
-        .with_filter(LevelFilter::DEBUG);
+    let (developer_dump_text_layer, developer_dump_json_layer) = match developer_dump_format {
+        DeveloperDumpFormat::Text => (
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(developer_dump_buffer)
+                    .event_format(
+                        format::format().with_ansi(false), // disable ansi colors because this will usually go into a file
+                    )
+                    .with_filter(developer_dump_level),
+            ),
+            None,
+        ),
+        DeveloperDumpFormat::Json => (
+            None,
+            Some(
+                DeveloperDumpJsonLayer::new(developer_dump_buffer)
+                    .with_filter(developer_dump_level),
+            ),
+        ),
+    };
+
+    let traces_layer = traces_buffer.map(|buffer| {
+        OtlpTraceLayer::new(buffer).with_filter(FilterFn::new(|metadata| {
+            *metadata.level() <= Level::DEBUG && !metadata.target().starts_with("@metrics")
+        }))
+    });
 
     tracing_subscriber::registry()
-        .with(user_layer)
+        .with(user_text_layer)
+        .with(user_json_layer)
         .with(developer_layer)
-        .with(developer_dump_layer)
+        .with(developer_dump_text_layer)
+        .with(developer_dump_json_layer)
+        .with(traces_layer)
         .init();
+
+    DEVELOPER_LOG_RELOAD
+        .lock()
+        .unwrap()
+        .replace(Box::new(move |filter| {
+            developer_filter_handle.reload(filter)
+        }));
+}
+
+fn developer_log_filter(developer_log: &str) -> EnvFilter {
+    EnvFilter::builder()
+        .with_default_directive(LevelFilter::OFF.into())
+        .parse_lossy(developer_log)
+}
+
+/// Applies logging settings which can be changed at runtime.
+///
+/// Other settings, such as formats and buffer sizes, are only applied in [init].
+pub fn reconfigure(config: &CoreConfiguration) {
+    USER_LOG.store(config.user_log, Ordering::Relaxed);
+    if let Some(ref reload) = *DEVELOPER_LOG_RELOAD.lock().unwrap() {
+        if let Err(err) = reload(developer_log_filter(&config.developer_log)) {
+            tracing::error!("Failed to replace developer log filter: {}", err);
+        }
+    }
+    // interest in callsites filtered by the user log toggle is cached as well
+    tracing::callsite::rebuild_interest_cache();
+
+    metrics::set_configuration_hash(config.configuration_hash());
 }
 
 #[repr(C)]
@@ -122,8 +279,11 @@ unsafe fn clear_return_arena() -> Ptr<[FatPointer; 2]> {
     }
 }
 unsafe fn set_return_arena_from(buffer: &impl TracingEventBuffer) -> Ptr<[FatPointer; 2]> {
-    let [(ptr1, size1), (ptr2, size2)] = buffer.as_raw_parts();
-
+    unsafe { set_return_arena_from_raw_parts(buffer.as_raw_parts()) }
+}
+unsafe fn set_return_arena_from_raw_parts(
+    [(ptr1, size1), (ptr2, size2)]: [(*const u8, usize); 2],
+) -> Ptr<[FatPointer; 2]> {
     unsafe {
         BUFFER_RETURN_ARENA[0].ptr = ptr1.into();
         BUFFER_RETURN_ARENA[0].size = size1;
@@ -143,6 +303,7 @@ unsafe fn set_return_arena_from(buffer: &impl TracingEventBuffer) -> Ptr<[FatPoi
 /// Each metric is a UTF-8 encoded JSON string and is terminated by a null byte.
 pub extern "C" fn __export_oneclient_core_get_metrics() -> Ptr<[FatPointer; 2]> {
     tracing::debug!("Getting metrics buffer");
+    metrics::flush_metrics();
 
     unsafe {
         match METRICS_BUFFER {
@@ -167,6 +328,36 @@ pub extern "C" fn __export_oneclient_core_clear_metrics() {
     }
 }
 
+#[no_mangle]
+#[export_name = "oneclient_core_get_traces"]
+/// Returns two fat pointers to memory where exported spans are stored.
+///
+/// The first one will point to the head of the buffer up to its end.
+/// The second one will point from the beginning buffer up to its tail. The second pointer may be null or have zero length.
+/// Each span is a UTF-8 encoded OTLP JSON `Span` object and is terminated by a null byte.
+/// Both pointers are null when trace export is not enabled.
+pub extern "C" fn __export_oneclient_core_get_traces() -> Ptr<[FatPointer; 2]> {
+    unsafe {
+        match TRACES_BUFFER {
+            Some(ref b) => set_return_arena_from(b.lock().deref()),
+            None => clear_return_arena(),
+        }
+    }
+}
+
+#[no_mangle]
+#[export_name = "oneclient_core_clear_traces"]
+/// Clears the traces buffer.
+///
+/// This should be called after [__export_oneclient_core_get_traces] is called and the spans are processed.
+pub extern "C" fn __export_oneclient_core_clear_traces() {
+    unsafe {
+        if let Some(ref buffer) = TRACES_BUFFER {
+            buffer.lock().clear();
+        }
+    }
+}
+
 #[no_mangle]
 #[export_name = "oneclient_core_get_developer_dump"]
 /// Returns two fat pointer to memory where the developer dump is stored.
@@ -182,3 +373,22 @@ pub extern "C" fn __export_oneclient_core_get_developer_dump() -> Ptr<[FatPointe
         }
     }
 }
+
+#[no_mangle]
+#[export_name = "oneclient_core_get_developer_dump_since"]
+/// Returns two fat pointers to memory where developer dump events with sequence number `since` and later are stored.
+///
+/// The pointers have the same layout as in [__export_oneclient_core_get_developer_dump].
+/// Sequence numbers start at zero and increase by one with each event written into the dump, including events which were
+/// already overwritten. The host can remember the sequence number of the last event it read (present on each event in
+/// the `json` dump format) and pass the next one here to only receive new events.
+pub extern "C" fn __export_oneclient_core_get_developer_dump_since(
+    since: Size,
+) -> Ptr<[FatPointer; 2]> {
+    unsafe {
+        match DEVELOPER_DUMP_BUFFER {
+            Some(ref b) => set_return_arena_from_raw_parts(b.lock().as_raw_parts_since(since)),
+            None => clear_return_arena(),
+        }
+    }
+}
@@ -0,0 +1,558 @@
+//! Export of tracing spans in OpenTelemetry format.
+//!
+//! Each closed span is serialized as an OTLP JSON `Span` object and written into the traces buffer as one event.
+//! The host drains the buffer and wraps the spans into `resourceSpans` and `scopeSpans` before sending them to its collector.
+//!
+//! A root span with a `traceparent` field continues the trace of the remote parent described by the W3C `traceparent` value.
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{fmt::MakeWriter, layer::Context, registry::LookupSpan, Layer, Registry};
+
+use super::buffer::{SharedEventBuffer, VecEventBuffer};
+
+/// OTLP `SpanKind` of spans created by core.
+const SPAN_KIND_INTERNAL: u8 = 1;
+/// OTLP `StatusCode` of spans which didn't record an error.
+const STATUS_CODE_UNSET: u8 = 0;
+/// OTLP `StatusCode` of spans during which an error event was recorded.
+const STATUS_CODE_ERROR: u8 = 2;
+
+/// Trace flags with the `sampled` flag set.
+const TRACE_FLAGS_SAMPLED: u8 = 0x01;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid traceparent \"{0}\"")]
+pub struct TraceParentParseError(String);
+
+/// Parsed W3C `traceparent` value, see <https://www.w3.org/TR/trace-context/#traceparent-header>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceParent {
+    pub trace_id: u128,
+    /// Id of the parent span
+    pub parent_id: u64,
+    pub flags: u8,
+}
+impl std::str::FromStr for TraceParent {
+    type Err = TraceParentParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || TraceParentParseError(s.to_string());
+
+        fn parse_hex<T>(
+            value: &str,
+            len: usize,
+            parse: fn(&str, u32) -> Result<T, std::num::ParseIntError>,
+        ) -> Option<T> {
+            if value.len() != len
+                || !value
+                    .bytes()
+                    .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+            {
+                return None;
+            }
+
+            parse(value, 16).ok()
+        }
+
+        let mut parts = s.trim().split('-');
+        let version = parts
+            .next()
+            .and_then(|v| parse_hex(v, 2, u8::from_str_radix))
+            .ok_or_else(err)?;
+        let trace_id = parts
+            .next()
+            .and_then(|v| parse_hex(v, 32, u128::from_str_radix))
+            .ok_or_else(err)?;
+        let parent_id = parts
+            .next()
+            .and_then(|v| parse_hex(v, 16, u64::from_str_radix))
+            .ok_or_else(err)?;
+        let flags = parts
+            .next()
+            .and_then(|v| parse_hex(v, 2, u8::from_str_radix))
+            .ok_or_else(err)?;
+
+        // version 00 has exactly four parts, future versions may append more
+        let has_more_parts = parts.next().is_some();
+        if version == 0xff || (version == 0 && has_more_parts) || trace_id == 0 || parent_id == 0 {
+            return Err(err());
+        }
+
+        Ok(Self {
+            trace_id,
+            parent_id,
+            flags,
+        })
+    }
+}
+impl fmt::Display for TraceParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.parent_id, self.flags
+        )
+    }
+}
+
+/// Returns the `traceparent` identifying the innermost exported span which is currently entered.
+///
+/// Returns `None` when trace export is not enabled or no exported span is entered.
+pub fn current_trace_parent() -> Option<TraceParent> {
+    tracing::Span::current().with_subscriber(|(id, dispatch)| {
+        let registry = dispatch.downcast_ref::<Registry>()?;
+        let span = registry.span(id)?;
+
+        span.scope().find_map(|span| {
+            span.extensions().get::<SpanData>().map(|data| TraceParent {
+                trace_id: data.trace_id,
+                parent_id: data.span_id,
+                flags: data.flags,
+            })
+        })
+    })?
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum AnyValue {
+    StringValue(String),
+    BoolValue(bool),
+    /// 64-bit integers are encoded as decimal strings in OTLP JSON
+    IntValue(String),
+    DoubleValue(f64),
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct KeyValue {
+    key: &'static str,
+    value: AnyValue,
+}
+
+/// Collects fields of spans and events as OTLP attributes.
+struct AttributeVisitor<'a> {
+    attributes: &'a mut Vec<KeyValue>,
+    /// The `message` field of events, which is used as the event name instead of an attribute
+    message: Option<String>,
+}
+impl<'a> AttributeVisitor<'a> {
+    fn new(attributes: &'a mut Vec<KeyValue>) -> Self {
+        Self {
+            attributes,
+            message: None,
+        }
+    }
+
+    fn push(&mut self, field: &Field, value: AnyValue) {
+        match self.attributes.iter_mut().find(|a| a.key == field.name()) {
+            Some(attribute) => attribute.value = value,
+            None => self.attributes.push(KeyValue {
+                key: field.name(),
+                value,
+            }),
+        }
+    }
+}
+impl Visit for AttributeVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            self.push(field, AnyValue::StringValue(value.to_string()));
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, AnyValue::BoolValue(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, AnyValue::IntValue(value.to_string()));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, AnyValue::IntValue(value.to_string()));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, AnyValue::DoubleValue(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            self.push(field, AnyValue::StringValue(format!("{:?}", value)));
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SpanEvent {
+    time: SystemTime,
+    name: String,
+    attributes: Vec<KeyValue>,
+}
+
+/// Data of an open span, stored in the span extensions.
+#[derive(Debug)]
+struct SpanData {
+    trace_id: u128,
+    span_id: u64,
+    parent_span_id: Option<u64>,
+    flags: u8,
+    start: SystemTime,
+    attributes: Vec<KeyValue>,
+    events: Vec<SpanEvent>,
+    is_error: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpSpan<'a> {
+    trace_id: String,
+    span_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_span_id: Option<String>,
+    name: &'a str,
+    kind: u8,
+    start_time_unix_nano: String,
+    end_time_unix_nano: String,
+    attributes: &'a [KeyValue],
+    events: Vec<OtlpSpanEvent<'a>>,
+    status: OtlpStatus,
+}
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpSpanEvent<'a> {
+    time_unix_nano: String,
+    name: &'a str,
+    attributes: &'a [KeyValue],
+}
+#[derive(Serialize)]
+struct OtlpStatus {
+    code: u8,
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+        .to_string()
+}
+
+/// Generates random non-zero ids.
+///
+/// Ids don't need to be cryptographically secure, so we avoid an extra dependency and use the randomly keyed std hasher.
+#[derive(Debug)]
+pub struct IdGenerator {
+    state: RandomState,
+    counter: AtomicU64,
+}
+impl IdGenerator {
+    pub fn new() -> Self {
+        Self {
+            state: RandomState::new(),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        loop {
+            let mut hasher = self.state.build_hasher();
+            hasher.write_u64(self.counter.fetch_add(1, Ordering::Relaxed));
+
+            let id = hasher.finish();
+            if id != 0 {
+                return id;
+            }
+        }
+    }
+
+    pub fn next_u128(&self) -> u128 {
+        (self.next_u64() as u128) << 64 | self.next_u64() as u128
+    }
+}
+
+/// Tracing layer which writes closed spans as OTLP JSON into a buffer.
+pub struct OtlpTraceLayer {
+    buffer: SharedEventBuffer<VecEventBuffer>,
+    ids: IdGenerator,
+}
+impl OtlpTraceLayer {
+    pub fn new(buffer: SharedEventBuffer<VecEventBuffer>) -> Self {
+        Self {
+            buffer,
+            ids: IdGenerator::new(),
+        }
+    }
+
+    fn write_span(&self, name: &str, data: SpanData, end: SystemTime) {
+        let span = OtlpSpan {
+            trace_id: format!("{:032x}", data.trace_id),
+            span_id: format!("{:016x}", data.span_id),
+            parent_span_id: data.parent_span_id.map(|id| format!("{:016x}", id)),
+            name,
+            kind: SPAN_KIND_INTERNAL,
+            start_time_unix_nano: unix_nanos(data.start),
+            end_time_unix_nano: unix_nanos(end),
+            attributes: &data.attributes,
+            events: data
+                .events
+                .iter()
+                .map(|event| OtlpSpanEvent {
+                    time_unix_nano: unix_nanos(event.time),
+                    name: &event.name,
+                    attributes: &event.attributes,
+                })
+                .collect(),
+            status: OtlpStatus {
+                code: if data.is_error {
+                    STATUS_CODE_ERROR
+                } else {
+                    STATUS_CODE_UNSET
+                },
+            },
+        };
+
+        // serialization of these types cannot fail and the buffer writer never fails
+        let _ = serde_json::to_writer(self.buffer.make_writer(), &span);
+    }
+}
+impl<S> Layer<S> for OtlpTraceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("Span must exist");
+
+        let mut attributes = vec![KeyValue {
+            key: "code.namespace",
+            value: AnyValue::StringValue(attrs.metadata().target().to_string()),
+        }];
+        attrs.record(&mut AttributeVisitor::new(&mut attributes));
+
+        let remote_parent = attributes
+            .iter()
+            .position(|a| a.key == "traceparent")
+            .map(|index| attributes.remove(index))
+            .and_then(|attribute| match attribute.value {
+                AnyValue::StringValue(value) => match value.parse::<TraceParent>() {
+                    Ok(parent) => Some(parent),
+                    Err(err) => {
+                        tracing::warn!("Ignoring remote trace context: {}", err);
+                        None
+                    }
+                },
+                _ => None,
+            });
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<SpanData>()
+                .map(|data| TraceParent {
+                    trace_id: data.trace_id,
+                    parent_id: data.span_id,
+                    flags: data.flags,
+                })
+        });
+        let (trace_id, parent_span_id, flags) = match parent.or(remote_parent) {
+            Some(parent) => (parent.trace_id, Some(parent.parent_id), parent.flags),
+            None => (self.ids.next_u128(), None, TRACE_FLAGS_SAMPLED),
+        };
+
+        span.extensions_mut().insert(SpanData {
+            trace_id,
+            span_id: self.ids.next_u64(),
+            parent_span_id,
+            flags,
+            start: SystemTime::now(),
+            attributes,
+            events: Vec::new(),
+            is_error: false,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("Span must exist");
+        let mut extensions = span.extensions_mut();
+
+        if let Some(data) = extensions.get_mut::<SpanData>() {
+            values.record(&mut AttributeVisitor::new(&mut data.attributes));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let span = match ctx.event_span(event) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut extensions = span.extensions_mut();
+
+        if let Some(data) = extensions.get_mut::<SpanData>() {
+            let mut attributes = vec![KeyValue {
+                key: "level",
+                value: AnyValue::StringValue(event.metadata().level().to_string()),
+            }];
+            let mut visitor = AttributeVisitor::new(&mut attributes);
+            event.record(&mut visitor);
+            let name = visitor
+                .message
+                .unwrap_or_else(|| event.metadata().name().to_string());
+
+            data.is_error |= *event.metadata().level() == Level::ERROR;
+            data.events.push(SpanEvent {
+                time: SystemTime::now(),
+                name,
+                attributes,
+            });
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).expect("Span must exist");
+        let data = span.extensions_mut().remove::<SpanData>();
+
+        if let Some(data) = data {
+            self.write_span(span.name(), data, SystemTime::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::observability::buffer::TracingEventBuffer;
+
+    use super::*;
+
+    fn read_spans(buffer: &SharedEventBuffer<VecEventBuffer>) -> Vec<serde_json::Value> {
+        let buffer = buffer.lock();
+        let [(ptr, len), _] = buffer.as_raw_parts();
+        let data = unsafe { std::slice::from_raw_parts(ptr, len) };
+
+        data.split(|&b| b == 0)
+            .filter(|event| !event.is_empty())
+            .map(|event| serde_json::from_slice(event).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_trace_parent_parse() {
+        let value = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let parent: TraceParent = value.parse().unwrap();
+        assert_eq!(parent.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(parent.parent_id, 0x00f067aa0ba902b7);
+        assert_eq!(parent.flags, TRACE_FLAGS_SAMPLED);
+        assert_eq!(parent.to_string(), value);
+
+        // future versions may have more fields
+        assert!(
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra"
+                .parse::<TraceParent>()
+                .is_ok()
+        );
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+        ] {
+            assert!(invalid.parse::<TraceParent>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_otlp_remote_parent() {
+        let buffer = SharedEventBuffer::new(VecEventBuffer::new(1024 * 1024));
+        let subscriber = tracing_subscriber::registry().with(OtlpTraceLayer::new(buffer.clone()));
+
+        let current = tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!(
+                "perform",
+                traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00"
+            )
+            .entered();
+
+            current_trace_parent()
+        })
+        .unwrap();
+
+        let spans = read_spans(&buffer);
+        assert_eq!(spans[0]["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(spans[0]["parentSpanId"], "00f067aa0ba902b7");
+        assert!(!spans[0]["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|a| a["key"] == "traceparent"));
+
+        assert_eq!(current.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(format!("{:016x}", current.parent_id), spans[0]["spanId"]);
+        assert_eq!(current.flags, 0);
+    }
+
+    #[test]
+    fn test_otlp_spans() {
+        let buffer = SharedEventBuffer::new(VecEventBuffer::new(1024 * 1024));
+        let subscriber = tracing_subscriber::registry().with(OtlpTraceLayer::new(buffer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let outer = tracing::info_span!("outer", url = "http://example.com").entered();
+            {
+                let inner =
+                    tracing::debug_span!("inner", id = 1u64, status = tracing::field::Empty)
+                        .entered();
+                inner.record("status", 200u64);
+                tracing::error!(reason = "boom", "Request failed");
+            }
+            drop(outer);
+        });
+
+        let spans = read_spans(&buffer);
+        assert_eq!(spans.len(), 2);
+        let (inner, outer) = (&spans[0], &spans[1]);
+
+        assert_eq!(outer["name"], "outer");
+        assert_eq!(outer["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(outer["spanId"].as_str().unwrap().len(), 16);
+        assert!(outer.get("parentSpanId").is_none());
+        assert_eq!(outer["status"]["code"], STATUS_CODE_UNSET);
+        assert!(outer["attributes"].as_array().unwrap().contains(
+            &serde_json::json!({ "key": "url", "value": { "stringValue": "http://example.com" } })
+        ));
+
+        assert_eq!(inner["name"], "inner");
+        assert_eq!(inner["traceId"], outer["traceId"]);
+        assert_eq!(inner["parentSpanId"], outer["spanId"]);
+        assert_eq!(inner["status"]["code"], STATUS_CODE_ERROR);
+        assert!(inner["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!({ "key": "status", "value": { "intValue": "200" } })));
+        assert_eq!(inner["events"][0]["name"], "Request failed");
+        assert!(inner["events"][0]["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!({ "key": "reason", "value": { "stringValue": "boom" } })));
+    }
+}
@@ -0,0 +1,201 @@
+//! Structured JSON format of the user log.
+//!
+//! Each event is written as one JSON object per line. Context from enclosing spans is lifted into stable top-level fields
+//! so that log pipelines can index them without knowing the span structure.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    fmt::{
+        format::{FormatEvent, FormatFields, Writer},
+        FmtContext, FormattedFields,
+    },
+    registry::LookupSpan,
+};
+
+/// Top-level fields present on each line, `null` when not known.
+const CONTEXT_FIELDS: [&str; 5] = [
+    "perform_id",
+    "profile",
+    "provider",
+    "usecase",
+    "http_request_id",
+];
+
+/// Maps a field of span `span_name` to the top-level context field it is reported as.
+fn context_field(span_name: &str, field: &str) -> Option<&'static str> {
+    match (span_name, field) {
+        ("perform", "perform_id") => Some("perform_id"),
+        ("perform", "profile") => Some("profile"),
+        ("perform", "provider") => Some("provider"),
+        ("perform", "usecase") => Some("usecase"),
+        ("HTTP Request" | "HTTP Response", "id") => Some("http_request_id"),
+        _ => None,
+    }
+}
+
+/// Collects event fields into a JSON object.
+pub(super) struct JsonVisitor<'a>(pub(super) &'a mut Map<String, Value>);
+impl Visit for JsonVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+/// Formats events as JSON lines with stable context fields.
+///
+/// Must be used with [JsonFields](tracing_subscriber::fmt::format::JsonFields) so that span fields are stored as JSON.
+pub struct UserJsonFormat;
+impl<S, N> FormatEvent<S, N> for UserJsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                .into(),
+        );
+        line.insert(
+            "level".to_string(),
+            event.metadata().level().as_str().into(),
+        );
+        for field in CONTEXT_FIELDS {
+            line.insert(field.to_string(), Value::Null);
+        }
+
+        // inner spans are visited last so they take precedence
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                let fields = match extensions.get::<FormattedFields<N>>() {
+                    Some(fields) => fields,
+                    None => continue,
+                };
+
+                if let Ok(Value::Object(fields)) = serde_json::from_str::<Value>(fields) {
+                    for (field, value) in fields {
+                        if let Some(key) = context_field(span.name(), &field) {
+                            line.insert(key.to_string(), value);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+        line.insert(
+            "message".to_string(),
+            fields.remove("message").unwrap_or(Value::Null),
+        );
+        line.insert("fields".to_string(), Value::Object(fields));
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing_subscriber::{fmt::format::JsonFields, layer::SubscriberExt};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct TestWriter(Arc<Mutex<Vec<u8>>>);
+    impl Write for TestWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_user_json_format() {
+        let output = TestWriter::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .fmt_fields(JsonFields::new())
+                .event_format(UserJsonFormat)
+                .with_writer(move || writer.clone()),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let perform = tracing::info_span!(
+                "perform",
+                perform_id = "abc",
+                profile = tracing::field::Empty,
+                usecase = "Example"
+            )
+            .entered();
+            perform.record("profile", "scope/name");
+            tracing::info!(map = "printed");
+
+            let _request = tracing::debug_span!("HTTP Request", id = 3).entered();
+            tracing::debug!("\nGET / HTTP/1.1");
+        });
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["perform_id"], "abc");
+        assert_eq!(lines[0]["profile"], "scope/name");
+        assert_eq!(lines[0]["provider"], Value::Null);
+        assert_eq!(lines[0]["usecase"], "Example");
+        assert_eq!(lines[0]["http_request_id"], Value::Null);
+        assert_eq!(lines[0]["message"], Value::Null);
+        assert_eq!(lines[0]["fields"]["map"], "printed");
+
+        assert_eq!(lines[1]["perform_id"], "abc");
+        assert_eq!(lines[1]["http_request_id"], 3);
+        assert_eq!(lines[1]["message"], "\nGET / HTTP/1.1");
+    }
+}
@@ -1,8 +1,18 @@
-use std::{collections::BTreeMap, str::FromStr};
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, TryLockError,
+    },
+    time::Instant,
+};
 
+use base64::Engine;
 use sf_std::unstable::{
+    configure::ConfigureInput,
     exception::{PerformException, PerformExceptionErrorCode},
-    perform::PerformInput,
+    perform::{CancelInput, PerformInput, RecoverInput},
     HostValue,
 };
 
@@ -14,30 +24,37 @@ use map_std::unstable::{
 };
 
 use crate::{
-    bindings::{MessageExchangeFfi, StreamExchangeFfi},
+    bindings::{close_abandoned_spans, MessageExchangeFfi, StreamExchangeFfi},
+    observability::{self, metrics::PerformTimings, take_panic_report, traces::IdGenerator},
     sf_core::{
-        exception::FromJsonSchemaValidationError, json_schema_validator::JsonSchemaValidator,
+        exception::{perform_cancelled, perform_panicked, FromJsonSchemaValidationError},
+        json_schema_validator::JsonSchemaValidator,
         metrics::PerformMetricsData,
     },
 };
 
 mod cache;
+mod cancellation;
 mod config;
 mod digest;
+mod documents;
+mod examples;
 mod exception;
 mod json_schema_validator;
 mod map_std_impl;
 mod metrics;
 mod profile_validator;
+mod redirect;
 
 // use crate::profile_validator::ProfileValidator;
-use cache::DocumentCache;
-pub use config::CoreConfiguration;
+use cache::{DocumentCache, DocumentCacheSettings};
+use cancellation::Cancellations;
+pub use config::{CoreConfiguration, DeveloperDumpFormat, HttpCassetteMode, UserLogFormat};
 use map_std_impl::MapStdImpl;
 
 use self::{
     cache::{MapCacheEntry, ProfileCacheEntry, ProviderJsonCacheEntry},
-    map_std_impl::MapStdImplConfig,
+    map_std_impl::{HttpCassette, HttpPolicy, MapStdImplConfig, TracePropagation},
 };
 
 type Fs = sf_std::unstable::fs::FsConvenience<MessageExchangeFfi, StreamExchangeFfi>;
@@ -45,6 +62,11 @@ type HttpRequest = sf_std::unstable::http::HttpRequest<MessageExchangeFfi, Strea
 type HttpResponse = sf_std::unstable::http::HttpResponse<StreamExchangeFfi>;
 type IoStream = sf_std::unstable::IoStream<StreamExchangeFfi>;
 
+/// Whether `mutex` is locked, which outside of a perform only happens if the perform trapped while holding the lock.
+fn is_locked<T>(mutex: &Mutex<T>) -> bool {
+    matches!(mutex.try_lock(), Err(TryLockError::WouldBlock))
+}
+
 #[derive(Debug)]
 pub struct OneClientCore {
     profile_cache: DocumentCache<ProfileCacheEntry>,
@@ -52,7 +74,16 @@ pub struct OneClientCore {
     map_cache: DocumentCache<MapCacheEntry>,
     security_validator: JsonSchemaValidator,
     parameters_validator: JsonSchemaValidator,
-    mapstd_config: MapStdImplConfig,
+    provider_json_validator: JsonSchemaValidator,
+    mapstd_config: Mutex<MapStdImplConfig>,
+    /// Generates ids of performs which didn't receive one from the host
+    perform_ids: IdGenerator,
+    /// Cassette of recorded HTTP calls, shared with the map std of each perform
+    http_cassette: Option<Arc<Mutex<HttpCassette>>>,
+    /// Cancellation flags of running performs by their id
+    cancellations: Cancellations,
+    /// Configuration in effect, changed at runtime by [Self::configure]
+    configuration: Mutex<CoreConfiguration>,
 }
 impl OneClientCore {
     const MAP_STDLIB_JS: &str = include_str!("../assets/js/map_std.js");
@@ -60,6 +91,7 @@ impl OneClientCore {
         include_str!("../assets/schemas/security_values.json");
     const PARAMETERS_VALUES_JSON_SCHEMA: &str =
         include_str!("../assets/schemas/parameters_values.json");
+    const PROVIDER_JSON_JSON_SCHEMA: &str = include_str!("../assets/schemas/provider_json.json");
 
     // TODO: Use thiserror and define specific errors
     pub fn new(config: &CoreConfiguration) -> anyhow::Result<Self> {
@@ -67,22 +99,15 @@ impl OneClientCore {
 
         crate::observability::metrics::log_metric!(Init);
 
+        Self::with_config(config)
+    }
+
+    /// Creates the state of core without logging the init metric.
+    fn with_config(config: &CoreConfiguration) -> anyhow::Result<Self> {
         Ok(Self {
-            profile_cache: DocumentCache::new(
-                config.cache_duration,
-                config.registry_url.clone(),
-                Some(config.user_agent.clone()),
-            ),
-            provider_cache: DocumentCache::new(
-                config.cache_duration,
-                config.registry_url.clone(),
-                Some(config.user_agent.clone()),
-            ),
-            map_cache: DocumentCache::new(
-                config.cache_duration,
-                config.registry_url.clone(),
-                Some(config.user_agent.clone()),
-            ),
+            profile_cache: DocumentCache::new(Self::document_cache_settings(config)),
+            provider_cache: DocumentCache::new(Self::document_cache_settings(config)),
+            map_cache: DocumentCache::new(Self::document_cache_settings(config)),
             security_validator: JsonSchemaValidator::new(
                 &serde_json::Value::from_str(&OneClientCore::SECURITY_VALUES_JSON_SCHEMA)
                     .expect("Valid JSON"),
@@ -93,21 +118,73 @@ impl OneClientCore {
                     .expect("Valid JSON"),
             )
             .expect("Valid JSON Schema for parameters values exists"),
-            mapstd_config: MapStdImplConfig {
-                log_http_transactions: config.user_log,
-                log_http_transactions_body_max_size: config.user_log_http_body_max_size,
-                user_agent: config.user_agent.clone(),
-            },
+            provider_json_validator: JsonSchemaValidator::new(
+                &serde_json::Value::from_str(&OneClientCore::PROVIDER_JSON_JSON_SCHEMA)
+                    .expect("Valid JSON"),
+            )
+            .expect("Valid JSON Schema for provider JSON exists"),
+            mapstd_config: Mutex::new(Self::mapstd_config(config)),
+            perform_ids: IdGenerator::new(),
+            http_cassette: HttpCassette::from_config(config)?.map(|c| Arc::new(Mutex::new(c))),
+            cancellations: Cancellations::default(),
+            configuration: Mutex::new(config.clone()),
         })
     }
 
+    fn document_cache_settings(config: &CoreConfiguration) -> DocumentCacheSettings {
+        DocumentCacheSettings {
+            cache_duration: config.cache_duration,
+            registry_url: config.registry_url.clone(),
+            user_agent: Some(config.user_agent.clone()),
+            registry_version_ranges: config.registry_version_ranges,
+        }
+    }
+
+    fn mapstd_config(config: &CoreConfiguration) -> MapStdImplConfig {
+        MapStdImplConfig {
+            log_http_transactions: config.user_log,
+            log_http_transactions_body_max_size: config.user_log_http_body_max_size,
+            user_agent: config.user_agent.clone(),
+        }
+    }
+
+    /// Applies settings received through the configure input without tearing core down.
+    ///
+    /// Logging settings take effect immediately, the others for performs started afterwards.
+    /// Document caches are cleared unless the host asks to keep them.
+    pub fn configure(&self) -> Result<(), config::CoreConfigurationUpdateError> {
+        let input = ConfigureInput::take_in(MessageExchangeFfi)?;
+
+        let config = {
+            let mut config = self.configuration.lock().unwrap();
+            config.update(input.configuration)?;
+            config.clone()
+        };
+        tracing::info!(target: "@user", config = ?config, keep_caches = input.keep_caches, "Core reconfigured");
+
+        observability::reconfigure(&config);
+        self.profile_cache
+            .reconfigure(Self::document_cache_settings(&config), input.keep_caches);
+        self.provider_cache
+            .reconfigure(Self::document_cache_settings(&config), input.keep_caches);
+        self.map_cache
+            .reconfigure(Self::document_cache_settings(&config), input.keep_caches);
+        *self.mapstd_config.lock().unwrap() = Self::mapstd_config(&config);
+
+        Ok(())
+    }
+
     /// Converts HostValue into MapValue.
     ///
     /// For primitive types this is a simple move. For custom types with drop code this might include adding
     /// reference counting and registering handles.
-    fn host_value_to_map_value(&mut self, value: HostValue) -> MapValue {
+    fn host_value_to_map_value(&self, value: HostValue) -> MapValue {
         match value {
             HostValue::Stream(_) => todo!(),
+            // maps have no binary value type yet
+            HostValue::Bytes(b) => {
+                MapValue::String(base64::engine::general_purpose::STANDARD.encode(b))
+            }
             HostValue::None => MapValue::None,
             HostValue::Bool(b) => MapValue::Bool(b),
             HostValue::Number(n) => MapValue::Number(n),
@@ -127,7 +204,7 @@ impl OneClientCore {
     /// Converts MapValue into HostValue.
     ///
     /// This is the opposite action to [host_value_to_map_value].
-    fn map_value_to_host_value(&mut self, value: MapValue) -> HostValue {
+    fn map_value_to_host_value(&self, value: MapValue) -> HostValue {
         match value {
             MapValue::None => HostValue::None,
             MapValue::Bool(b) => HostValue::Bool(b),
@@ -145,12 +222,161 @@ impl OneClientCore {
         }
     }
 
-    pub fn perform(&mut self) -> Result<Result<HostValue, HostValue>, PerformException> {
+    /// Runs a perform, returns its id together with the outcome.
+    ///
+    /// The id is `None` if the perform input couldn't be received.
+    pub fn perform(
+        &self,
+    ) -> (
+        Option<String>,
+        Result<Result<HostValue, HostValue>, PerformException>,
+    ) {
+        let perform_start = Instant::now();
+
         // we can't send metrics if we don't even know the profile and provider urls
-        let perform_input = PerformInput::take_in(MessageExchangeFfi)?;
+        let mut perform_input = match PerformInput::take_in(MessageExchangeFfi) {
+            Ok(perform_input) => perform_input,
+            Err(err) => {
+                let exception = PerformException::from(err);
+                tracing::error!(target: "@user", "Perform failed unexpectedly: {}", exception);
+                return (None, Err(exception));
+            }
+        };
+
+        let perform_id = match perform_input.perform_id.take() {
+            Some(id) if !id.is_empty() => id,
+            _ => format!("{:032x}", self.perform_ids.next_u128()),
+        };
+        // this span provides context for everything logged during the perform
+        // and continues the trace of the caller when trace context is passed in
+        let span = tracing::info_span!(
+            target: "@user",
+            "perform",
+            perform_id = %perform_id,
+            profile = tracing::field::Empty,
+            provider = tracing::field::Empty,
+            usecase = %perform_input.usecase,
+            traceparent = perform_input
+                .trace_context
+                .as_ref()
+                .map(|context| context.traceparent.as_str())
+        );
+
+        let result =
+            span.in_scope(|| self.run_perform_guarded(perform_start, &perform_id, perform_input));
+        if let Err(ref exception) = result {
+            span.in_scope(
+                || tracing::error!(target: "@user", "Perform failed unexpectedly: {}", exception),
+            );
+        }
+
+        (Some(perform_id), result)
+    }
+
+    /// Runs a perform which the host can cancel by its id, a panic of the perform is returned as its exception.
+    fn run_perform_guarded(
+        &self,
+        perform_start: Instant,
+        perform_id: &str,
+        perform_input: PerformInput,
+    ) -> Result<Result<HostValue, HostValue>, PerformException> {
+        let cancelled = self.cancellations.register(perform_id);
+        // panics only unwind on native targets, on wasm they abort and the host calls `recover` after the trap instead
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.run_perform(perform_start, perform_id, perform_input, cancelled)
+        }))
+        .unwrap_or_else(|_| Err(perform_panicked(take_panic_report())));
+        #[cfg(target_arch = "wasm32")]
+        let result = self.run_perform(perform_start, perform_id, perform_input, cancelled);
+        self.cancellations.remove(perform_id);
+
+        result
+    }
+
+    /// Cancels the running perform whose id is received through the cancel input.
+    ///
+    /// The perform fails with a `Cancelled` exception at its next call into map std, which also closes its outstanding
+    /// HTTP requests and streams. Since performs only yield to the host in such calls, this is all that is needed.
+    /// A map which runs without calling map std, for example in a busy loop, is not interrupted, as the QuickJS bindings
+    /// do not expose an interrupt handler.
+    /// Ids of performs which are not running, including ones which already finished, are ignored.
+    pub fn cancel(&self) -> Result<(), PerformException> {
+        let input = CancelInput::take_in(MessageExchangeFfi)?;
+
+        if self.cancellations.cancel(&input.perform_id) {
+            tracing::debug!(target: "@user", perform_id = %input.perform_id, "Perform cancelled by host");
+        } else {
+            tracing::debug!(target: "@user", perform_id = %input.perform_id, "No running perform to cancel");
+        }
+
+        Ok(())
+    }
+
+    /// Recovers after a perform trapped, returns the id of the perform together with its exception.
+    ///
+    /// Only state of the trapped perform is discarded, document caches and observability buffers are kept.
+    /// Panics if the perform trapped while holding a lock on shared state, the host then has to instantiate core again.
+    pub fn recover(&self) -> (Option<String>, PerformException) {
+        if self.is_shared_state_locked() {
+            panic!("Shared state locked by trapped perform, core cannot be recovered");
+        }
+
+        // other suspended performs have parked their spans, so all entered spans belong to the trapped perform
+        close_abandoned_spans();
+
+        let perform_id = match RecoverInput::take_in(MessageExchangeFfi) {
+            Ok(input) => input.perform_id,
+            Err(err) => {
+                tracing::error!(target: "@user", "Failed to receive recover input: {}", err);
+                None
+            }
+        };
+        let exception = self.discard_trapped_perform(perform_id.as_deref());
+        tracing::error!(
+            target: "@user",
+            perform_id = perform_id.as_deref(),
+            "Perform failed unexpectedly: {}",
+            exception
+        );
+
+        (perform_id, exception)
+    }
+
+    /// Whether a perform trapped while holding a lock on state shared by all performs, core can't be recovered then.
+    fn is_shared_state_locked(&self) -> bool {
+        self.profile_cache.is_locked()
+            || self.provider_cache.is_locked()
+            || self.map_cache.is_locked()
+            || self.cancellations.is_locked()
+            || is_locked(&self.mapstd_config)
+            || is_locked(&self.configuration)
+            || self
+                .http_cassette
+                .as_deref()
+                .map(is_locked)
+                .unwrap_or(false)
+    }
+
+    /// Discards the state of a trapped perform which core keeps outside of the perform, returns its exception.
+    fn discard_trapped_perform(&self, perform_id: Option<&str>) -> PerformException {
+        if let Some(perform_id) = perform_id {
+            self.cancellations.remove(perform_id);
+        }
+
+        perform_panicked(take_panic_report())
+    }
 
+    fn run_perform(
+        &self,
+        perform_start: Instant,
+        perform_id: &str,
+        perform_input: PerformInput,
+        cancelled: Arc<AtomicBool>,
+    ) -> Result<Result<HostValue, HostValue>, PerformException> {
         // information we have so far parsed from the available data, might be partial if an exception happens
         let mut metrics_data = PerformMetricsData {
+            perform_id,
             profile_url: &perform_input.profile_url,
             provider_url: &perform_input.provider_url,
             map_url: &perform_input.map_url,
@@ -162,9 +388,14 @@ impl OneClientCore {
         macro_rules! try_metrics {
             (Send $success: expr) => {
                 tracing::debug!(perform_metrics = ?metrics_data);
+                let timings = PerformTimings {
+                    total: Some(perform_start.elapsed()),
+                    ..metrics_data.timings
+                };
                 // Cleanup code is this
                 crate::observability::metrics::log_metric!(
                     Perform
+                    perform_id = metrics_data.perform_id,
                     success = $success,
                     profile = metrics_data.get_profile().as_ref(),
                     profile_url = metrics_data.profile_url,
@@ -173,7 +404,9 @@ impl OneClientCore {
                     provider_url = metrics_data.provider_url,
                     provider_content_hash = metrics_data.provider_content_hash,
                     map_url = metrics_data.map_url,
-                    map_content_hash = metrics_data.map_content_hash
+                    map_content_hash = metrics_data.map_content_hash,
+                    timings = &timings,
+                    http = &metrics_data.http
                 );
             };
 
@@ -190,19 +423,15 @@ impl OneClientCore {
         }
 
         // first cache documents
-        try_metrics!(self
-            .profile_cache
-            .cache(&perform_input.profile_url, ProfileCacheEntry::from_data));
-        try_metrics!(self.provider_cache.cache(
-            &perform_input.provider_url,
-            ProviderJsonCacheEntry::from_data
-        ));
-        try_metrics!(self.map_cache.cache(&perform_input.map_url, |data| {
-            // TODO: this is temporary, should be extracted from the map manifest
-            let file_name = perform_input.map_url.split('/').last().unwrap().to_string();
-
-            MapCacheEntry::new(data, file_name)
-        }));
+        let phase_start = Instant::now();
+        let profile_entry = try_metrics!(self.cache_profile(&perform_input.profile_url));
+        let provider_entry = try_metrics!(self.cache_provider(&perform_input.provider_url));
+        let map_entry = try_metrics!(self.cache_map(&perform_input.map_url));
+        metrics_data.timings.document_load = Some(phase_start.elapsed());
+        if cancelled.load(Ordering::Relaxed) {
+            try_metrics!(Err::<(), _>(perform_cancelled()));
+        }
+        let phase_start = Instant::now();
 
         // process map input and parameters
         let map_input = self.host_value_to_map_value(perform_input.map_input);
@@ -257,14 +486,11 @@ impl OneClientCore {
         let ProviderJsonCacheEntry {
             provider_json,
             content_hash: provider_json_content_hash,
-        } = self
-            .provider_cache
-            .get(&perform_input.provider_url)
-            .unwrap();
-        // TODO: validate provider json with json schema, to verify OneClient will understand it?
+        } = provider_entry.as_ref();
 
         metrics_data.provider_content_hash = Some(provider_json_content_hash);
         metrics_data.provider = Some(&provider_json.name);
+        tracing::Span::current().record("provider", provider_json.name.as_str());
 
         // process provider and combine with inputs
         let mut provider_parameters = prepare_provider_parameters(provider_json);
@@ -274,19 +500,76 @@ impl OneClientCore {
             provider_json,
             &perform_input.map_security
         ));
-        let map_services = prepare_services_map(provider_json, &map_parameters);
+        let map_services = try_metrics!(prepare_services_map(provider_json, &map_parameters));
+        let trace_propagation = TracePropagation {
+            context: perform_input.trace_context.clone(),
+            excluded_base_urls: match map_services {
+                MapValue::Object(ref services) => provider_json
+                    .services
+                    .iter()
+                    .filter(|service| service.propagate_trace_context == Some(false))
+                    .filter_map(|service| match services.get(&service.id) {
+                        Some(MapValue::String(base_url)) => Some(base_url.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            },
+        };
+        let http_policy = HttpPolicy::new(
+            match map_services {
+                MapValue::Object(ref services) => services
+                    .values()
+                    .filter_map(|base_url| match base_url {
+                        MapValue::String(base_url) => Some(base_url.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+                _ => Vec::new(),
+            },
+            self.configuration
+                .lock()
+                .unwrap()
+                .http_allowed_hosts
+                .clone(),
+        );
 
         let ProfileCacheEntry {
             profile: _,
             content_hash: profile_content_hash,
-        } = self.profile_cache.get(&perform_input.profile_url).unwrap();
+            id: profile_id,
+            version: profile_version,
+        } = profile_entry.as_ref();
         metrics_data.profile_content_hash = Some(profile_content_hash);
+        metrics_data.profile = profile_id.as_ref().map(|id| id.to_string());
+        metrics_data.profile_version = profile_version.as_ref().map(|v| v.to_string());
+        tracing::Span::current().record("profile", metrics_data.get_profile().as_ref());
+
+        let MapCacheEntry {
+            map,
+            content_hash: map_content_hash,
+            file_name: map_file_name,
+            manifest: map_manifest,
+        } = map_entry.as_ref();
+        metrics_data.map_content_hash = Some(map_content_hash);
+        metrics_data.map_version = map_manifest
+            .as_ref()
+            .and_then(|manifest| manifest.version.clone());
+        // verify that the map was written for this profile and provider
+        try_metrics!(map_entry.check_manifest(
+            profile_id.as_ref(),
+            profile_version.as_ref(),
+            &provider_json.name
+        ));
+
+        metrics_data.timings.validation = Some(phase_start.elapsed());
 
         // start interpreting stdlib and then map code
+        let phase_start = Instant::now();
         // TODO: should this be here or should we hold an instance of the interpreter in global state
         // and clear per-perform data each time it is called?
         let mut interpreter = try_metrics!(JsInterpreter::new(MapStdImpl::new(
-            self.mapstd_config.to_owned()
+            self.mapstd_config.lock().unwrap().clone()
         )));
         // here we allow runtime stdlib replacement for development purposes
         // this might be removed in the future
@@ -303,12 +586,6 @@ impl OneClientCore {
             }
         });
 
-        let MapCacheEntry {
-            map,
-            content_hash: map_content_hash,
-            file_name: map_file_name,
-        } = self.map_cache.get(&perform_input.map_url).unwrap();
-        metrics_data.map_content_hash = Some(map_content_hash);
         let map_result = {
             interpreter.state_mut().set_context(
                 map_std::map_value!({
@@ -318,7 +595,27 @@ impl OneClientCore {
                 }),
                 Some(map_security),
             );
-            try_metrics!(interpreter.run(map_file_name, map, &perform_input.usecase));
+            interpreter
+                .state_mut()
+                .set_trace_propagation(trace_propagation);
+            interpreter.state_mut().set_http_policy(http_policy);
+            interpreter
+                .state_mut()
+                .set_http_cassette(self.http_cassette.clone());
+            interpreter.state_mut().set_cancellation(cancelled.clone());
+            let run_result = interpreter.run(map_file_name, map, &perform_input.usecase);
+            if let Some(ref cassette) = self.http_cassette {
+                if let Err(err) = HttpCassette::save(cassette) {
+                    tracing::error!(target: "@user", "{}", err);
+                }
+            }
+            metrics_data.http = interpreter.state_mut().http_metrics().clone();
+            metrics_data.timings.interpretation = Some(phase_start.elapsed());
+            // whatever the map did after it was interrupted is not relevant
+            if cancelled.load(Ordering::Relaxed) {
+                try_metrics!(Err::<(), _>(perform_cancelled()));
+            }
+            try_metrics!(run_result);
 
             interpreter.state_mut().take_output().unwrap()
         };
@@ -331,3 +628,50 @@ impl OneClientCore {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::observability::{store_panic_report, PanicReport};
+
+    #[test]
+    fn test_recover_outside_locks() {
+        let core = OneClientCore::with_config(&CoreConfiguration::default()).unwrap();
+
+        // the perform trapped while running, but not while holding any shared state
+        let trapped = core.cancellations.register("perform-1");
+        store_panic_report(PanicReport::new("Requested panic!", None));
+        assert!(!core.is_shared_state_locked());
+
+        let exception = core.discard_trapped_perform(Some("perform-1"));
+        assert!(matches!(
+            exception.error_code,
+            PerformExceptionErrorCode::Panic
+        ));
+        assert!(exception.message.contains("Requested panic!"));
+        drop(trapped);
+
+        // the next perform reusing the id starts with a fresh cancellation flag and can use the document caches
+        let next = core.cancellations.register("perform-1");
+        assert!(!next.load(Ordering::Relaxed));
+        let profile = base64::engine::general_purpose::STANDARD
+            .encode("name = \"scope/name\"\nversion = \"1.0.0\"\n");
+        core.profile_cache
+            .cache(
+                &format!("data:;base64,{}", profile),
+                ProfileCacheEntry::from_data,
+            )
+            .unwrap();
+        core.cancellations.remove("perform-1");
+        assert!(!core.is_shared_state_locked());
+    }
+
+    #[test]
+    fn test_recover_inside_lock() {
+        let core = OneClientCore::with_config(&CoreConfiguration::default()).unwrap();
+
+        // a trap while holding the lock never releases it
+        std::mem::forget(core.configuration.lock().unwrap());
+        assert!(core.is_shared_state_locked());
+    }
+}
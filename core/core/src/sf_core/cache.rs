@@ -1,17 +1,31 @@
 use std::{
     collections::HashMap,
     io::Read,
+    sync::{Arc, Mutex, TryLockError},
     time::{Duration, Instant},
 };
 
+use regex::Regex;
 use url::Url;
 
+use comlink_language::parser::{
+    MapManifest, MapManifestError, ProfileId, ProfileVersion, ProfileVersionRange,
+};
 use sf_std::{
-    unstable::{http::HttpCallError, provider::ProviderJson},
+    unstable::{
+        exception::{PerformException, PerformExceptionErrorCode},
+        http::HttpCallError,
+        provider::ProviderJson,
+    },
     HeaderName, HeadersMultiMap,
 };
 
-use super::{digest, Fs, HttpRequest};
+use super::{
+    digest,
+    json_schema_validator::{JsonSchemaValidator, JsonSchemaValidatorError},
+    redirect::RedirectableRequest,
+    Fs, HttpRequest,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ProfileCacheEntryError {
@@ -20,14 +34,32 @@ pub enum ProfileCacheEntryError {
 }
 #[derive(Debug)]
 pub struct ProfileCacheEntry {
-    pub profile: String, // TODO: parsed so we can extract the version
+    pub profile: String,
     pub content_hash: String,
+    /// Profile id as parsed from the profile header, only available for Comlink profiles
+    pub id: Option<ProfileId>,
+    /// Profile version as parsed from the profile header, only available for Comlink profiles
+    pub version: Option<ProfileVersion>,
 }
 impl ProfileCacheEntry {
     pub fn from_data(data: Vec<u8>) -> Result<Self, ProfileCacheEntryError> {
+        let content_hash = digest::content_hash(&data);
+        let profile = String::from_utf8(data)?;
+
+        // profiles might also be TypeScript, in which case there is no Comlink header to parse
+        let (document, _errors) = comlink_language::parser::parse_profile(&profile);
+        let header = document.header();
+        let id = header.as_ref().and_then(|h| h.name()).and_then(|n| n.id());
+        let version = header
+            .as_ref()
+            .and_then(|h| h.version())
+            .and_then(|v| v.value());
+
         Ok(Self {
-            content_hash: digest::content_hash(&data),
-            profile: String::from_utf8(data)?,
+            profile,
+            content_hash,
+            id,
+            version,
         })
     }
 }
@@ -36,6 +68,12 @@ impl ProfileCacheEntry {
 pub enum ProviderJsonCacheEntryError {
     #[error("Failed to deserialize provider JSON: {0}")]
     ParseError(#[from] serde_json::Error),
+    #[error("Provider JSON is not valid: {0}")]
+    ValidationError(#[from] JsonSchemaValidatorError),
+    #[error("Provider JSON is not valid: default service \"{0}\" is not defined in services on path: /defaultService")]
+    UnknownDefaultService(String),
+    #[error("Provider JSON is not valid: parameter \"{parameter}\" is not defined in parameters on path: {path}")]
+    UndeclaredParameter { parameter: String, path: String },
 }
 #[derive(Debug)]
 pub struct ProviderJsonCacheEntry {
@@ -43,18 +81,68 @@ pub struct ProviderJsonCacheEntry {
     pub content_hash: String,
 }
 impl ProviderJsonCacheEntry {
-    pub fn from_data(data: Vec<u8>) -> Result<Self, ProviderJsonCacheEntryError> {
+    pub fn from_data(
+        data: Vec<u8>,
+        validator: &JsonSchemaValidator,
+    ) -> Result<Self, ProviderJsonCacheEntryError> {
+        let content_hash = digest::content_hash(&data);
+
+        let provider_json = serde_json::from_slice::<serde_json::Value>(&data)?;
+        validator.validate_value(&provider_json)?;
+        let provider_json = serde_json::from_value::<ProviderJson>(provider_json)?;
+        Self::check_references(&provider_json)?;
+
         Ok(Self {
-            content_hash: digest::content_hash(&data),
-            provider_json: serde_json::from_slice::<ProviderJson>(&data)?,
+            content_hash,
+            provider_json,
         })
     }
+
+    /// Checks that services and parameters referenced in the provider JSON are defined.
+    fn check_references(provider_json: &ProviderJson) -> Result<(), ProviderJsonCacheEntryError> {
+        if let Some(ref default_service) = provider_json.default_service {
+            if !provider_json
+                .services
+                .iter()
+                .any(|service| &service.id == default_service)
+            {
+                return Err(ProviderJsonCacheEntryError::UnknownDefaultService(
+                    default_service.clone(),
+                ));
+            }
+        }
+
+        // same pattern as used when replacing parameters in services
+        let re = Regex::new(r"\{\s*([^}\s]*)\s*\}").unwrap();
+        for (index, service) in provider_json.services.iter().enumerate() {
+            for cap in re.captures_iter(&service.base_url) {
+                let declared = provider_json
+                    .parameters
+                    .iter()
+                    .flatten()
+                    .any(|parameter| parameter.name == cap[1]);
+
+                if !declared {
+                    return Err(ProviderJsonCacheEntryError::UndeclaredParameter {
+                        parameter: cap[1].to_string(),
+                        path: format!("/services/{}/baseUrl", index),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum MapCacheEntryError {
     #[error("Failed to parse map data as utf8: {0}")]
     ParseError(#[from] std::string::FromUtf8Error),
+    #[error("Failed to parse map manifest: {0}")]
+    ManifestParseError(String),
+    #[error("Invalid map manifest: {0}")]
+    InvalidManifest(#[from] MapManifestError),
 }
 #[derive(Debug)]
 pub struct MapCacheEntry {
@@ -62,19 +150,92 @@ pub struct MapCacheEntry {
     pub content_hash: String,
     /// This is for the purposes of stacktraces in JsInterpreter
     pub file_name: String,
+    /// Manifest declared at the start of the map, if any
+    pub manifest: Option<MapManifest>,
 }
 impl MapCacheEntry {
-    // TODO: name should be taken from the manifest
-    pub fn new(data: Vec<u8>, file_name: String) -> Result<Self, MapCacheEntryError> {
+    /// Creates a new map entry, parsing the map manifest if the map declares one.
+    ///
+    /// The file name is derived from the manifest, `fallback_file_name` is only used for maps without a manifest.
+    pub fn new(data: Vec<u8>, fallback_file_name: String) -> Result<Self, MapCacheEntryError> {
         let content_hash = digest::content_hash(&data);
         let map = String::from_utf8(data)?;
 
+        let manifest = match comlink_language::parser::parse_map_header(&map) {
+            None => None,
+            Some((header, errors)) => match errors.first() {
+                Some(err) => {
+                    return Err(MapCacheEntryError::ManifestParseError(format!(
+                        "{} at offset {}",
+                        err.message, err.offset
+                    )))
+                }
+                None => Some(header.manifest()?),
+            },
+        };
+        let file_name = match manifest {
+            None => fallback_file_name,
+            Some(ref manifest) => format!(
+                "{}.{}{}.map.js",
+                manifest.profile.to_string().replace('/', "."),
+                manifest.provider,
+                manifest
+                    .variant
+                    .as_ref()
+                    .map(|v| format!(".{}", v))
+                    .unwrap_or_default()
+            ),
+        };
+
         Ok(Self {
             content_hash,
             map,
             file_name,
+            manifest,
         })
     }
+
+    /// Checks that the map was written for `profile` and `provider` and for a version compatible with `profile_version`.
+    ///
+    /// Maps without a manifest are not checked, neither is the profile if its id or version is not known.
+    pub fn check_manifest(
+        &self,
+        profile: Option<&ProfileId>,
+        profile_version: Option<&ProfileVersion>,
+        provider: &str,
+    ) -> Result<(), PerformException> {
+        let manifest = match self.manifest {
+            None => return Ok(()),
+            Some(ref manifest) => manifest,
+        };
+        let profile_name = profile
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        if manifest.provider != provider || matches!(profile, Some(id) if id != &manifest.profile) {
+            return Err(PerformException {
+                error_code: PerformExceptionErrorCode::MapManifestMismatch,
+                message: format!(
+                    "Map was written for profile \"{}\" and provider \"{}\", but it is used with profile \"{}\" and provider \"{}\"",
+                    manifest.profile, manifest.provider, profile_name, provider
+                ),
+            });
+        }
+
+        if let Some(profile_version) = profile_version {
+            if !manifest.profile_version.is_compatible_with(profile_version) {
+                return Err(PerformException {
+                    error_code: PerformExceptionErrorCode::ProfileVersionMismatch,
+                    message: format!(
+                        "Map was written for profile version {}, which is not compatible with profile \"{}\" version {}",
+                        manifest.profile_version, profile_name, profile_version
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -85,13 +246,15 @@ pub enum DocumentCacheError<PostProcessError: std::error::Error> {
     HttpLoadFailed(String, HttpCallError),
     #[error("Failed to read http body: {0}")]
     HttpBodyReadFailed(std::io::Error),
+    #[error("Failed to resolve version of document \"{0}\": {1}")]
+    VersionResolutionFailed(String, String),
     #[error("Failed to post process data: {0}")]
     PostProcessError(PostProcessError),
 }
 
 struct DocumentCacheEntry<E> {
     store_time: Instant,
-    data: E,
+    data: Arc<E>,
 }
 impl<E: std::fmt::Debug> std::fmt::Debug for DocumentCacheEntry<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -104,11 +267,39 @@ impl<E: std::fmt::Debug> std::fmt::Debug for DocumentCacheEntry<E> {
     }
 }
 
+/// Registry document id with a version range resolved to the id of a concrete version.
+#[derive(Debug)]
+struct ResolvedRegistryId {
+    store_time: Instant,
+    id: String,
+}
+
+/// Cache of documents shared by all performs.
+///
+/// Entries are reference counted so that performs running concurrently keep using the document they loaded
+/// even if another perform replaces it in the meantime. The map is never locked while loading a document.
 pub struct DocumentCache<E> {
-    map: HashMap<String, DocumentCacheEntry<E>>,
-    cache_duration: Duration,
-    registry_url: Url,
-    user_agent: Option<String>,
+    map: Mutex<HashMap<String, DocumentCacheEntry<E>>>,
+    /// Registry ids with a version range by the range id, documents are stored under the resolved id
+    resolved_ids: Mutex<HashMap<String, ResolvedRegistryId>>,
+    settings: Mutex<DocumentCacheSettings>,
+}
+/// Settings of a document cache, which can be changed at runtime.
+#[derive(Debug, Clone)]
+pub struct DocumentCacheSettings {
+    pub cache_duration: Duration,
+    pub registry_url: Url,
+    pub user_agent: Option<String>,
+    /// Whether version ranges in registry document ids are resolved, see [DocumentCache::resolve_registry_version].
+    pub registry_version_ranges: bool,
+}
+/// Document stored in a [DocumentCache], as returned by [DocumentCache::list].
+pub struct DocumentCacheListing<E> {
+    pub url: String,
+    pub age: Duration,
+    /// Whether the document is older than the cache duration.
+    pub expired: bool,
+    pub data: Arc<E>,
 }
 impl<E> DocumentCache<E> {
     const FILE_URL_PREFIX: &str = "file://";
@@ -116,34 +307,102 @@ impl<E> DocumentCache<E> {
     const HTTPS_URL_PREFIX: &str = "https://";
     const BASE64_URL_PREFIX: &str = "data:;base64,";
 
-    pub fn new(cache_duration: Duration, registry_url: Url, user_agent: Option<String>) -> Self {
+    pub fn new(settings: DocumentCacheSettings) -> Self {
         Self {
-            map: HashMap::new(),
-            cache_duration,
-            registry_url,
-            user_agent,
+            map: Mutex::new(HashMap::new()),
+            resolved_ids: Mutex::new(HashMap::new()),
+            settings: Mutex::new(settings),
+        }
+    }
+
+    /// Replaces settings of the cache, documents cached so far are dropped unless `keep_entries` is set.
+    ///
+    /// Performs which are already running keep using the documents they loaded.
+    pub fn reconfigure(&self, settings: DocumentCacheSettings, keep_entries: bool) {
+        *self.settings.lock().unwrap() = settings;
+        if !keep_entries {
+            self.resolved_ids.lock().unwrap().clear();
+            self.map.lock().unwrap().clear();
         }
     }
 
-    pub fn get(&self, url: &str) -> Option<&E> {
-        self.map.get(url).map(|e| &e.data)
+    /// Drops the document cached under `url`, or all documents if `url` is `None`.
+    ///
+    /// A registry id with a version range drops the resolution of the range together with the document it resolved to.
+    ///
+    /// Returns the number of documents dropped.
+    pub fn invalidate(&self, url: Option<&str>) -> usize {
+        let mut resolved_ids = self.resolved_ids.lock().unwrap();
+        let mut map = self.map.lock().unwrap();
+        match url {
+            None => {
+                resolved_ids.clear();
+                let count = map.len();
+                map.clear();
+                count
+            }
+            Some(url) => {
+                let resolved = resolved_ids.remove(url);
+                let key = resolved.as_ref().map(|r| r.id.as_str()).unwrap_or(url);
+                map.remove(key).map(|_| 1).unwrap_or(0)
+            }
+        }
+    }
+
+    /// Lists documents currently stored in the cache, including those which are too old and will be loaded again.
+    pub fn list(&self) -> Vec<DocumentCacheListing<E>> {
+        let cache_duration = self.settings.lock().unwrap().cache_duration;
+        self.map
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(url, entry)| {
+                let age = entry.store_time.elapsed();
+                DocumentCacheListing {
+                    url: url.clone(),
+                    age,
+                    expired: age > cache_duration,
+                    data: entry.data.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the cache is locked, which outside of [Self::cache] only happens if a perform trapped while holding the lock.
+    pub fn is_locked(&self) -> bool {
+        matches!(self.map.try_lock(), Err(TryLockError::WouldBlock))
+            || matches!(self.resolved_ids.try_lock(), Err(TryLockError::WouldBlock))
+            || matches!(self.settings.try_lock(), Err(TryLockError::WouldBlock))
     }
 
+    /// Returns the cached document, loading it first if it is not cached or is too old.
+    ///
+    /// Registry document ids with a version range are resolved first, the resolution is kept for the cache duration
+    /// so that newly published versions are picked up after it expires. The document is cached under the resolved id.
     pub fn cache<PostProcessError: std::error::Error>(
-        &mut self,
+        &self,
         url: &str,
         post_process_fn: impl FnOnce(Vec<u8>) -> Result<E, PostProcessError>,
-    ) -> Result<(), DocumentCacheError<PostProcessError>> {
+    ) -> Result<Arc<E>, DocumentCacheError<PostProcessError>> {
         let _span = tracing::debug_span!("cache_document").entered();
 
         tracing::debug!(url);
 
-        match self.map.get(url) {
-            Some(DocumentCacheEntry { store_time, .. })
-                if store_time.elapsed() <= self.cache_duration =>
+        let settings = self.settings.lock().unwrap().clone();
+        let resolved_url;
+        let url = if Self::is_registry_id(url) {
+            resolved_url = self.resolve_registry_version(&settings, url)?;
+            resolved_url.as_str()
+        } else {
+            url
+        };
+
+        match self.map.lock().unwrap().get(url) {
+            Some(DocumentCacheEntry { store_time, data })
+                if store_time.elapsed() <= settings.cache_duration =>
             {
                 tracing::debug!("already cached");
-                return Ok(());
+                return Ok(data.clone());
             }
             _ => (),
         }
@@ -154,17 +413,17 @@ impl<E> DocumentCache<E> {
             url => {
                 if url.starts_with(Self::HTTP_URL_PREFIX) || url.starts_with(Self::HTTPS_URL_PREFIX)
                 {
-                    Self::cache_http(url, self.user_agent.as_deref())
+                    Self::cache_http(url, settings.user_agent.as_deref())
                 } else {
                     let file = format!("{}.js", url);
-                    let full_url = self.registry_url.join(&file).map_err(|_e| {
+                    let full_url = settings.registry_url.join(&file).map_err(|_e| {
                         DocumentCacheError::HttpLoadFailed(
                             url.to_string(),
                             HttpCallError::InvalidUrl(file.clone()),
                         )
                     })?;
 
-                    Self::cache_http(full_url.as_str(), self.user_agent.as_deref())
+                    Self::cache_http(full_url.as_str(), settings.user_agent.as_deref())
                 }
             }
         }?;
@@ -176,14 +435,111 @@ impl<E> DocumentCache<E> {
             }
         }
 
-        self.map.insert(
+        let data =
+            Arc::new(post_process_fn(data).map_err(|e| DocumentCacheError::PostProcessError(e))?);
+        self.map.lock().unwrap().insert(
             url.to_string(),
             DocumentCacheEntry {
                 store_time: Instant::now(),
-                data: post_process_fn(data).map_err(|e| DocumentCacheError::PostProcessError(e))?,
+                data: data.clone(),
             },
         );
-        Ok(())
+        Ok(data)
+    }
+
+    /// Whether `url` is an id of a document in the registry rather than an url.
+    fn is_registry_id(url: &str) -> bool {
+        ![
+            Self::FILE_URL_PREFIX,
+            Self::HTTP_URL_PREFIX,
+            Self::HTTPS_URL_PREFIX,
+            Self::BASE64_URL_PREFIX,
+        ]
+        .iter()
+        .any(|prefix| url.starts_with(prefix))
+    }
+
+    /// Splits a registry document id such as `scope/name@^1.2` into its name and version range.
+    ///
+    /// Returns `None` for ids without a version or with an exact version.
+    fn registry_version_range(id: &str) -> Option<(&str, ProfileVersionRange)> {
+        let (name, range) = id.split_once('@')?;
+        match range.parse::<ProfileVersionRange>() {
+            Ok(ProfileVersionRange::Exact(_)) | Err(_) => None,
+            Ok(range) => Some((name, range)),
+        }
+    }
+
+    /// Resolves a version range in a registry document id such as `scope/name@^1.2` to a concrete version.
+    ///
+    /// This is opt-in through [DocumentCacheSettings::registry_version_ranges] because it requires the registry to list
+    /// the available versions at `<registry_url>/<scope>/<name>/versions` as a JSON array of strings, as documented
+    /// in the readme. When disabled, or for ids without a version or with an exact version, the id is returned unchanged.
+    ///
+    /// Resolutions are reused for the cache duration.
+    fn resolve_registry_version<PostProcessError: std::error::Error>(
+        &self,
+        settings: &DocumentCacheSettings,
+        id: &str,
+    ) -> Result<String, DocumentCacheError<PostProcessError>> {
+        if !settings.registry_version_ranges {
+            return Ok(id.to_string());
+        }
+        let (name, range) = match Self::registry_version_range(id) {
+            None => return Ok(id.to_string()),
+            Some(name_range) => name_range,
+        };
+
+        match self.resolved_ids.lock().unwrap().get(id) {
+            Some(ResolvedRegistryId { store_time, id })
+                if store_time.elapsed() <= settings.cache_duration =>
+            {
+                tracing::debug!(resolved = %id, "registry version already resolved");
+                return Ok(id.clone());
+            }
+            _ => (),
+        }
+
+        let versions_url = settings
+            .registry_url
+            .join(&format!("{}/versions", name))
+            .map_err(|_e| {
+                DocumentCacheError::HttpLoadFailed(
+                    id.to_string(),
+                    HttpCallError::InvalidUrl(format!("{}/versions", name)),
+                )
+            })?;
+        let data = Self::cache_http(versions_url.as_str(), settings.user_agent.as_deref())?;
+        let resolved = Self::resolve_from_listing(name, &range, &data)
+            .map_err(|err| DocumentCacheError::VersionResolutionFailed(id.to_string(), err))?;
+        tracing::debug!(%resolved, "resolved registry version");
+        self.resolved_ids.lock().unwrap().insert(
+            id.to_string(),
+            ResolvedRegistryId {
+                store_time: Instant::now(),
+                id: resolved.clone(),
+            },
+        );
+
+        Ok(resolved)
+    }
+
+    /// Picks the highest version matching `range` from a registry versions listing, returns the id of that version.
+    fn resolve_from_listing(
+        name: &str,
+        range: &ProfileVersionRange,
+        listing: &[u8],
+    ) -> Result<String, String> {
+        let versions: Vec<ProfileVersion> = serde_json::from_slice::<Vec<String>>(listing)
+            .map_err(|err| err.to_string())?
+            .iter()
+            .filter_map(|v| v.parse().ok())
+            .collect();
+
+        match range.resolve(&versions) {
+            None => Err(format!("no version matches range {}", range)),
+            Some(version) => Ok(format!("{}@{}", name, version)),
+        }
     }
 
     fn cache_file<PostProcessError: std::error::Error>(
@@ -208,10 +564,31 @@ impl<E> DocumentCache<E> {
             headers.insert(HeaderName::from("user-agent"), vec![user_agent.to_string()]);
         }
 
-        let mut response = HttpRequest::fetch("GET", url, &headers, &Default::default(), None)
+        // hosts don't follow redirects, documents come from trusted locations so any redirect is followed
+        let mut request = RedirectableRequest::new("GET", url, &headers, &Default::default(), None);
+        let mut response = loop {
+            let response = HttpRequest::fetch(
+                &request.method,
+                &request.url,
+                &request.headers,
+                &Default::default(),
+                None,
+            )
             .and_then(|v| v.into_response())
             .map_err(|err| DocumentCacheError::HttpLoadFailed(url.to_string(), err))?;
 
+            match request.follow(response.status(), response.headers()) {
+                Ok(false) => break response,
+                Ok(true) => (),
+                Err(err) => {
+                    return Err(DocumentCacheError::HttpLoadFailed(
+                        url.to_string(),
+                        HttpCallError::Unknown(err.to_string()),
+                    ))
+                }
+            }
+        };
+
         let mut data = Vec::new();
         response
             .body()
@@ -239,7 +616,284 @@ impl<E: std::fmt::Debug> std::fmt::Debug for DocumentCache<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("DocumentCache")
             .field("map", &self.map)
-            .field("cache_duration", &self.cache_duration)
+            .field(
+                "cache_duration",
+                &self.settings.lock().unwrap().cache_duration,
+            )
             .finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn provider_json(value: serde_json::Value) -> ProviderJson {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_provider_json_references() {
+        let valid = provider_json(serde_json::json!({
+            "name": "example",
+            "services": [{ "id": "default", "baseUrl": "https://{ REGION }.example.com" }],
+            "defaultService": "default",
+            "parameters": [{ "name": "REGION" }]
+        }));
+        assert!(ProviderJsonCacheEntry::check_references(&valid).is_ok());
+
+        let unknown_service = provider_json(serde_json::json!({
+            "name": "example",
+            "services": [{ "id": "default", "baseUrl": "https://example.com" }],
+            "defaultService": "other"
+        }));
+        assert!(matches!(
+            ProviderJsonCacheEntry::check_references(&unknown_service),
+            Err(ProviderJsonCacheEntryError::UnknownDefaultService(service)) if service == "other"
+        ));
+
+        let undeclared_parameter = provider_json(serde_json::json!({
+            "name": "example",
+            "services": [
+                { "id": "default", "baseUrl": "https://example.com" },
+                { "id": "regional", "baseUrl": "https://{REGION}.example.com" }
+            ],
+            "defaultService": "default"
+        }));
+        assert!(matches!(
+            ProviderJsonCacheEntry::check_references(&undeclared_parameter),
+            Err(ProviderJsonCacheEntryError::UndeclaredParameter { parameter, path })
+                if parameter == "REGION" && path == "/services/1/baseUrl"
+        ));
+    }
+
+    #[test]
+    fn test_check_manifest() {
+        let map = |manifest: &str| {
+            MapCacheEntry::new(
+                format!("{}\nvar Foo = () => {{}};", manifest).into_bytes(),
+                "fallback.map.js".to_string(),
+            )
+            .unwrap()
+        };
+        let version = |version: &str| version.parse::<ProfileVersion>().unwrap();
+        let id = |id: &str| ProfileId::from(id);
+
+        let entry = map("const manifest = { profile: 'scope/name@1.2', provider: 'localhost' };");
+        entry
+            .check_manifest(
+                Some(&id("scope/name")),
+                Some(&version("1.3.0")),
+                "localhost",
+            )
+            .unwrap();
+        entry.check_manifest(None, None, "localhost").unwrap();
+
+        assert!(matches!(
+            entry.check_manifest(
+                Some(&id("scope/name")),
+                Some(&version("1.1.0")),
+                "localhost"
+            ),
+            Err(PerformException {
+                error_code: PerformExceptionErrorCode::ProfileVersionMismatch,
+                ..
+            })
+        ));
+        assert!(matches!(
+            entry.check_manifest(
+                Some(&id("scope/name")),
+                Some(&version("2.2.0")),
+                "localhost"
+            ),
+            Err(PerformException {
+                error_code: PerformExceptionErrorCode::ProfileVersionMismatch,
+                ..
+            })
+        ));
+        assert!(matches!(
+            entry.check_manifest(Some(&id("scope/other")), None, "localhost"),
+            Err(PerformException {
+                error_code: PerformExceptionErrorCode::MapManifestMismatch,
+                ..
+            })
+        ));
+        assert!(matches!(
+            entry.check_manifest(Some(&id("scope/name")), None, "other"),
+            Err(PerformException {
+                error_code: PerformExceptionErrorCode::MapManifestMismatch,
+                ..
+            })
+        ));
+
+        // maps without a manifest can't be checked
+        map("// @ts-check")
+            .check_manifest(Some(&id("scope/other")), Some(&version("9.0.0")), "other")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_registry_version_resolution() {
+        type Cache = DocumentCache<u32>;
+
+        assert!(Cache::is_registry_id("scope/name@1.2"));
+        assert!(!Cache::is_registry_id("https://example.com/scope/name@1.2"));
+        assert!(!Cache::is_registry_id("file://scope.name.profile"));
+
+        assert!(Cache::registry_version_range("scope/name").is_none());
+        assert!(Cache::registry_version_range("scope/name@1.2.3").is_none());
+        let (name, range) = Cache::registry_version_range("scope/name@^1.2").unwrap();
+        assert_eq!(name, "scope/name");
+
+        let listing = br#"["1.1.0", "1.2.0", "1.4.1", "1.5.0-beta", "2.0.0", "not a version"]"#;
+        assert_eq!(
+            Cache::resolve_from_listing(name, &range, listing),
+            Ok("scope/name@1.4.1".to_string())
+        );
+        let (_, range) = Cache::registry_version_range("scope/name@~1.2").unwrap();
+        assert_eq!(
+            Cache::resolve_from_listing(name, &range, listing),
+            Ok("scope/name@1.2.0".to_string())
+        );
+        let (_, range) = Cache::registry_version_range("scope/name@^3.0").unwrap();
+        assert!(Cache::resolve_from_listing(name, &range, listing).is_err());
+        assert!(Cache::resolve_from_listing(name, &range, b"{}").is_err());
+
+        // without the opt-in the id is requested as is, without listing versions
+        let settings = DocumentCacheSettings {
+            cache_duration: Duration::from_secs(60),
+            registry_url: Url::parse("http://localhost:8321").unwrap(),
+            user_agent: None,
+            registry_version_ranges: false,
+        };
+        let cache = Cache::new(settings.clone());
+        assert_eq!(
+            cache
+                .resolve_registry_version::<std::io::Error>(&settings, "scope/name@^1.2")
+                .unwrap(),
+            "scope/name@^1.2"
+        );
+    }
+
+    #[test]
+    fn test_registry_version_resolution_cached() {
+        let cache = DocumentCache::<u32>::new(DocumentCacheSettings {
+            cache_duration: Duration::from_secs(60),
+            registry_url: Url::parse("http://localhost:8321").unwrap(),
+            user_agent: None,
+            registry_version_ranges: true,
+        });
+        cache.resolved_ids.lock().unwrap().insert(
+            "scope/name@^1.2".to_string(),
+            ResolvedRegistryId {
+                store_time: Instant::now(),
+                id: "scope/name@1.4.1".to_string(),
+            },
+        );
+        cache.map.lock().unwrap().insert(
+            "scope/name@1.4.1".to_string(),
+            DocumentCacheEntry {
+                store_time: Instant::now(),
+                data: Arc::new(1),
+            },
+        );
+
+        // neither the versions listing nor the document is requested again
+        let data = cache
+            .cache("scope/name@^1.2", |_| -> Result<u32, std::io::Error> {
+                unreachable!()
+            })
+            .unwrap();
+        assert_eq!(*data, 1);
+
+        // invalidating the range id drops the document it resolved to
+        assert_eq!(cache.invalidate(Some("scope/name@^1.2")), 1);
+        assert!(cache.resolved_ids.lock().unwrap().is_empty());
+        assert!(cache.list().is_empty());
+    }
+
+    #[test]
+    fn test_reconfigure() {
+        let settings = DocumentCacheSettings {
+            cache_duration: Duration::from_secs(60),
+            registry_url: Url::parse("http://localhost:8321").unwrap(),
+            user_agent: None,
+            registry_version_ranges: false,
+        };
+        let cache = DocumentCache::<u32>::new(settings.clone());
+        cache.map.lock().unwrap().insert(
+            "file://profile.js".to_string(),
+            DocumentCacheEntry {
+                store_time: Instant::now(),
+                data: Arc::new(1),
+            },
+        );
+
+        cache.reconfigure(
+            DocumentCacheSettings {
+                cache_duration: Duration::from_secs(10),
+                ..settings.clone()
+            },
+            true,
+        );
+        assert_eq!(cache.map.lock().unwrap().len(), 1);
+        assert_eq!(
+            cache.settings.lock().unwrap().cache_duration,
+            Duration::from_secs(10)
+        );
+
+        cache.resolved_ids.lock().unwrap().insert(
+            "scope/name@^1.2".to_string(),
+            ResolvedRegistryId {
+                store_time: Instant::now(),
+                id: "scope/name@1.4.1".to_string(),
+            },
+        );
+        cache.reconfigure(settings, false);
+        assert!(cache.map.lock().unwrap().is_empty());
+        assert!(cache.resolved_ids.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalidate_and_list() {
+        let cache = DocumentCache::<u32>::new(DocumentCacheSettings {
+            cache_duration: Duration::from_secs(60),
+            registry_url: Url::parse("http://localhost:8321").unwrap(),
+            user_agent: None,
+            registry_version_ranges: false,
+        });
+        for (index, url) in ["file://a.js", "file://b.js", "file://c.js"]
+            .into_iter()
+            .enumerate()
+        {
+            cache.map.lock().unwrap().insert(
+                url.to_string(),
+                DocumentCacheEntry {
+                    store_time: Instant::now(),
+                    data: Arc::new(index as u32),
+                },
+            );
+        }
+
+        let mut listed = cache
+            .list()
+            .into_iter()
+            .map(|entry| (entry.url, *entry.data, entry.expired))
+            .collect::<Vec<_>>();
+        listed.sort();
+        assert_eq!(
+            listed,
+            vec![
+                ("file://a.js".to_string(), 0, false),
+                ("file://b.js".to_string(), 1, false),
+                ("file://c.js".to_string(), 2, false),
+            ]
+        );
+
+        assert_eq!(cache.invalidate(Some("file://b.js")), 1);
+        assert_eq!(cache.invalidate(Some("file://b.js")), 0);
+        assert_eq!(cache.list().len(), 2);
+        assert_eq!(cache.invalidate(None), 2);
+        assert!(cache.list().is_empty());
+    }
+}
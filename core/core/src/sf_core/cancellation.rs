@@ -0,0 +1,81 @@
+//! Cancellation flags of running performs.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, TryLockError,
+    },
+};
+
+/// Cancellation flags of running performs by their id.
+///
+/// Flags only exist while their perform is running, so cancelling a perform which is not running has no effect
+/// and a later perform reusing its id is not affected.
+#[derive(Debug, Default)]
+pub struct Cancellations {
+    flags: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+impl Cancellations {
+    /// Registers a running perform and returns its cancellation flag.
+    ///
+    /// Performs running concurrently under the same id share the flag.
+    pub fn register(&self, perform_id: &str) -> Arc<AtomicBool> {
+        self.flags
+            .lock()
+            .unwrap()
+            .entry(perform_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Removes the flag of a perform which is no longer running.
+    pub fn remove(&self, perform_id: &str) {
+        self.flags.lock().unwrap().remove(perform_id);
+    }
+
+    /// Sets the flag of a running perform, returns `false` if no perform with this id is running.
+    pub fn cancel(&self, perform_id: &str) -> bool {
+        match self.flags.lock().unwrap().get(perform_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the flags are locked, which outside of a perform only happens if the perform trapped while holding the lock.
+    pub fn is_locked(&self) -> bool {
+        matches!(self.flags.try_lock(), Err(TryLockError::WouldBlock))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cancel_running() {
+        let cancellations = Cancellations::default();
+
+        let flag = cancellations.register("perform-1");
+        assert!(cancellations.cancel("perform-1"));
+        assert!(flag.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_cancel_after_finish() {
+        let cancellations = Cancellations::default();
+
+        cancellations.register("request-1");
+        cancellations.remove("request-1");
+        assert!(!cancellations.cancel("request-1"));
+        assert!(!cancellations.cancel("unknown"));
+        assert!(cancellations.flags.lock().unwrap().is_empty());
+
+        // a perform reusing the id of a finished perform is not cancelled
+        let flag = cancellations.register("request-1");
+        assert!(!flag.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,151 @@
+//! Loads documents into the document caches ahead of performs and exposes the cache contents to the host.
+//!
+//! Documents are otherwise loaded lazily by the first perform which uses them, preloading them during deployment
+//! moves the registry downloads out of the first request.
+
+use std::sync::Arc;
+
+use sf_std::unstable::{
+    document_cache::{
+        CachedDocument, DocumentKind, InvalidateCacheInput, PreloadInput, PreloadedDocument,
+        TakeDocumentCacheInputError,
+    },
+    exception::PerformException,
+};
+
+use super::{
+    cache::{DocumentCacheListing, MapCacheEntry, ProfileCacheEntry, ProviderJsonCacheEntry},
+    OneClientCore,
+};
+use crate::bindings::MessageExchangeFfi;
+
+impl OneClientCore {
+    pub(super) fn cache_profile(
+        &self,
+        url: &str,
+    ) -> Result<Arc<ProfileCacheEntry>, PerformException> {
+        Ok(self
+            .profile_cache
+            .cache(url, ProfileCacheEntry::from_data)?)
+    }
+
+    pub(super) fn cache_provider(
+        &self,
+        url: &str,
+    ) -> Result<Arc<ProviderJsonCacheEntry>, PerformException> {
+        Ok(self.provider_cache.cache(url, |data| {
+            ProviderJsonCacheEntry::from_data(data, &self.provider_json_validator)
+        })?)
+    }
+
+    pub(super) fn cache_map(&self, url: &str) -> Result<Arc<MapCacheEntry>, PerformException> {
+        Ok(self.map_cache.cache(url, |data| {
+            // only used when the map doesn't declare a manifest
+            let file_name = url.split('/').next_back().unwrap().to_string();
+
+            MapCacheEntry::new(data, file_name)
+        })?)
+    }
+
+    /// Loads documents received through the preload input into the caches.
+    ///
+    /// Documents which are already cached and not expired are not loaded again. A document which fails to load
+    /// doesn't prevent the others from loading, the outcome of each is reported in the input order.
+    pub fn preload(&self) -> Result<Vec<PreloadedDocument>, TakeDocumentCacheInputError> {
+        let input = PreloadInput::take_in(MessageExchangeFfi)?;
+
+        fn preloaded<E>(
+            kind: DocumentKind,
+            url: String,
+            result: Result<Arc<E>, PerformException>,
+            content_hash: impl FnOnce(&E) -> &str,
+        ) -> PreloadedDocument {
+            match result {
+                Ok(entry) => PreloadedDocument {
+                    kind,
+                    url,
+                    content_hash: Some(content_hash(&entry).to_string()),
+                    error: None,
+                },
+                Err(err) => {
+                    tracing::error!(target: "@user", "Failed to preload {}: {}", url, err.message);
+                    PreloadedDocument {
+                        kind,
+                        url,
+                        content_hash: None,
+                        error: Some(err.message),
+                    }
+                }
+            }
+        }
+
+        let mut documents = Vec::new();
+        for url in input.profile_urls {
+            let result = self.cache_profile(&url);
+            documents.push(preloaded(DocumentKind::Profile, url, result, |e| {
+                &e.content_hash
+            }));
+        }
+        for url in input.provider_urls {
+            let result = self.cache_provider(&url);
+            documents.push(preloaded(DocumentKind::Provider, url, result, |e| {
+                &e.content_hash
+            }));
+        }
+        for url in input.map_urls {
+            let result = self.cache_map(&url);
+            documents.push(preloaded(DocumentKind::Map, url, result, |e| {
+                &e.content_hash
+            }));
+        }
+
+        Ok(documents)
+    }
+
+    /// Drops the document received through the invalidate cache input from all caches, or all documents if no url is received.
+    ///
+    /// Returns the number of documents dropped. Performs which are already running keep using the documents they loaded.
+    pub fn invalidate_cache(&self) -> Result<usize, TakeDocumentCacheInputError> {
+        let input = InvalidateCacheInput::take_in(MessageExchangeFfi)?;
+        let url = input.url.as_deref();
+
+        Ok(self.profile_cache.invalidate(url)
+            + self.provider_cache.invalidate(url)
+            + self.map_cache.invalidate(url))
+    }
+
+    /// Lists documents in all caches.
+    pub fn list_cache(&self) -> Vec<CachedDocument> {
+        fn cached<E>(
+            kind: DocumentKind,
+            listing: DocumentCacheListing<E>,
+            content_hash: impl FnOnce(&E) -> &str,
+        ) -> CachedDocument {
+            CachedDocument {
+                kind,
+                content_hash: content_hash(&listing.data).to_string(),
+                url: listing.url,
+                age: listing.age.as_millis() as u64,
+                expired: listing.expired,
+            }
+        }
+
+        let profiles = self
+            .profile_cache
+            .list()
+            .into_iter()
+            .map(|l| cached(DocumentKind::Profile, l, |e| &e.content_hash));
+        let providers = self
+            .provider_cache
+            .list()
+            .into_iter()
+            .map(|l| cached(DocumentKind::Provider, l, |e| &e.content_hash));
+        let maps = self
+            .map_cache
+            .list()
+            .into_iter()
+            .map(|l| cached(DocumentKind::Map, l, |e| &e.content_hash));
+
+        profiles.chain(providers).chain(maps).collect()
+    }
+}
@@ -0,0 +1,369 @@
+//! Runs use-case examples declared in a profile against a provider and map.
+//!
+//! Each example is performed like a regular perform and the map output is structurally matched against the example
+//! result or error, so profile examples can serve as a conformance suite. HTTP can be mocked using a replayed cassette.
+
+use std::time::Instant;
+
+use comlink_language::parser::{LiteralNode, ProfileDocumentDefinitionNode};
+use serde_json::Value as JsonValue;
+use sf_std::unstable::{
+    examples::{ExampleOutcome, ExampleReport, ExamplesInput, ExamplesReport},
+    exception::{PerformException, PerformExceptionErrorCode},
+    perform::PerformInput,
+    HostValue,
+};
+
+use super::{exception::perform_cancelled, OneClientCore};
+use crate::bindings::MessageExchangeFfi;
+
+/// Checks that `actual` structurally matches `expected`.
+///
+/// Objects match when every field of `expected` matches, fields not present in `expected` are ignored. A `None` literal
+/// also matches a missing field. Lists must have the same length. Returns the path of the first mismatch.
+fn structural_match(expected: &JsonValue, actual: &HostValue, path: &str) -> Result<(), String> {
+    let matches = match (expected, actual) {
+        (JsonValue::Null, HostValue::None) => true,
+        (JsonValue::Bool(e), HostValue::Bool(a)) => e == a,
+        (JsonValue::String(e), HostValue::String(a)) => e == a,
+        (JsonValue::Number(e), HostValue::Number(a)) => e.as_f64() == a.as_f64(),
+        (JsonValue::Array(e), HostValue::Array(a)) => {
+            if e.len() != a.len() {
+                return Err(format!(
+                    "{}: expected {} items, found {}",
+                    path,
+                    e.len(),
+                    a.len()
+                ));
+            }
+            for (index, (e, a)) in e.iter().zip(a.iter()).enumerate() {
+                structural_match(e, a, &format!("{}[{}]", path, index))?;
+            }
+
+            true
+        }
+        (JsonValue::Object(e), HostValue::Object(a)) => {
+            for (key, e) in e {
+                let field_path = format!("{}.{}", path, key);
+                match a.get(key) {
+                    Some(a) => structural_match(e, a, &field_path)?,
+                    None if e.is_null() => (),
+                    None => return Err(format!("{}: field is missing", field_path)),
+                }
+            }
+
+            true
+        }
+        _ => false,
+    };
+
+    if matches {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}: expected {}, found {}",
+            path,
+            expected,
+            serde_json::to_string(actual).unwrap_or_default()
+        ))
+    }
+}
+
+/// Outcome declared by an example.
+enum Expected {
+    Result(JsonValue),
+    Error(JsonValue),
+    /// The example only declares input, it passes unless the perform throws an exception
+    Any,
+}
+
+fn literal_value(literal: Option<LiteralNode>) -> Result<JsonValue, String> {
+    literal
+        .and_then(|literal| literal.json_value())
+        .ok_or_else(|| "Example literal is not complete".to_string())
+}
+
+/// Example extracted from the profile, or the reason why it couldn't be.
+struct ProfileExample {
+    usecase: String,
+    name: Option<String>,
+    input: Result<(JsonValue, Expected), String>,
+}
+
+fn profile_examples(profile: &str, usecase_filter: Option<&str>) -> Vec<ProfileExample> {
+    let (document, _errors) = comlink_language::parser::parse_profile(profile);
+
+    let mut examples = Vec::new();
+    for definition in document.definitions() {
+        let usecase = match definition {
+            ProfileDocumentDefinitionNode::UseCaseDefinition(usecase) => usecase,
+            _ => continue,
+        };
+        let usecase_name = match usecase.name() {
+            Some(name) => name.value().to_string(),
+            None => continue,
+        };
+        if usecase_filter.map(|f| f != usecase_name).unwrap_or(false) {
+            continue;
+        }
+
+        for example in usecase.examples() {
+            let input = example
+                .input_value()
+                .ok_or_else(|| "Example input is not complete".to_string())
+                .and_then(|input| {
+                    let expected = if let Some(result) = example.result() {
+                        Expected::Result(literal_value(result.literal())?)
+                    } else if let Some(result) = example.async_result() {
+                        Expected::Result(literal_value(result.literal())?)
+                    } else if let Some(error) = example.error() {
+                        Expected::Error(literal_value(error.literal())?)
+                    } else {
+                        Expected::Any
+                    };
+
+                    Ok((JsonValue::Object(input), expected))
+                });
+
+            examples.push(ProfileExample {
+                usecase: usecase_name.clone(),
+                name: example.name().map(|name| name.value().to_string()),
+                input,
+            });
+        }
+    }
+
+    examples
+}
+
+impl OneClientCore {
+    /// Runs examples from the profile passed in the examples input and reports which of them pass.
+    ///
+    /// Each example runs like a regular perform, under the perform id from the input if there is one so that the host can
+    /// cancel it. Examples after a cancelled one are not run.
+    pub fn run_examples(&self) -> ExamplesReport {
+        let input = match ExamplesInput::take_in(MessageExchangeFfi) {
+            Ok(input) => input,
+            Err(err) => {
+                return ExamplesReport {
+                    exception: Some(err.into()),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let profile_entry = match self.cache_profile(&input.profile_url) {
+            Ok(entry) => entry,
+            Err(err) => {
+                return ExamplesReport {
+                    exception: Some(err),
+                    ..Default::default()
+                }
+            }
+        };
+        let examples = profile_examples(&profile_entry.profile, input.usecase.as_deref());
+
+        // HostValue is not clone, but every example needs its own copy
+        let map_parameters = serde_json::to_value(&input.map_parameters).unwrap();
+        let map_security = serde_json::to_value(&input.map_security).unwrap();
+
+        let mut report = ExamplesReport::default();
+        for example in examples {
+            let example_report = match example.input {
+                Err(message) => ExampleReport {
+                    usecase: example.usecase,
+                    name: example.name,
+                    passed: false,
+                    message: Some(message),
+                    expected: None,
+                    actual: None,
+                },
+                Ok((map_input, expected)) => {
+                    let perform_input = PerformInput {
+                        profile_url: input.profile_url.clone(),
+                        provider_url: input.provider_url.clone(),
+                        map_url: input.map_url.clone(),
+                        usecase: example.usecase.clone(),
+                        map_input: serde_json::from_value(map_input).unwrap(),
+                        map_parameters: serde_json::from_value(map_parameters.clone()).unwrap(),
+                        map_security: serde_json::from_value(map_security.clone()).unwrap(),
+                        trace_context: None,
+                        perform_id: None,
+                    };
+
+                    let perform_id = match input.perform_id {
+                        Some(ref id) if !id.is_empty() => id.clone(),
+                        _ => format!("{:032x}", self.perform_ids.next_u128()),
+                    };
+
+                    self.run_example(
+                        example.usecase,
+                        example.name,
+                        &perform_id,
+                        perform_input,
+                        expected,
+                    )
+                }
+            };
+
+            if example_report.passed {
+                report.passed += 1;
+            } else {
+                report.failed += 1;
+            }
+            let cancelled = matches!(
+                example_report.actual,
+                Some(ExampleOutcome::Exception(PerformException {
+                    error_code: PerformExceptionErrorCode::Cancelled,
+                    ..
+                }))
+            );
+            report.examples.push(example_report);
+
+            if cancelled {
+                report.exception = Some(perform_cancelled());
+                break;
+            }
+        }
+
+        report
+    }
+
+    fn run_example(
+        &self,
+        usecase: String,
+        name: Option<String>,
+        perform_id: &str,
+        perform_input: PerformInput,
+        expected: Expected,
+    ) -> ExampleReport {
+        let span = tracing::info_span!(
+            target: "@user",
+            "perform",
+            perform_id = %perform_id,
+            profile = tracing::field::Empty,
+            provider = tracing::field::Empty,
+            usecase = %usecase,
+            example = name.as_deref()
+        );
+        let result =
+            span.in_scope(|| self.run_perform_guarded(Instant::now(), perform_id, perform_input));
+
+        let (passed, message) = match (&expected, &result) {
+            (Expected::Result(expected), Ok(Ok(actual)))
+            | (Expected::Error(expected), Ok(Err(actual))) => {
+                match structural_match(expected, actual, "$") {
+                    Ok(()) => (true, None),
+                    Err(mismatch) => (false, Some(mismatch)),
+                }
+            }
+            (Expected::Any, Ok(_)) => (true, None),
+            (Expected::Result(_), Ok(Err(_))) => (
+                false,
+                Some("Expected result, map returned error".to_string()),
+            ),
+            (Expected::Error(_), Ok(Ok(_))) => (
+                false,
+                Some("Expected error, map returned result".to_string()),
+            ),
+            (_, Err(exception)) => (false, Some(exception.to_string())),
+        };
+        if !passed {
+            span.in_scope(|| {
+                tracing::warn!(target: "@user", "Example failed: {}", message.as_deref().unwrap_or_default())
+            });
+        }
+
+        let to_host_value = |value: JsonValue| {
+            serde_json::from_value::<HostValue>(value).unwrap_or(HostValue::None)
+        };
+        ExampleReport {
+            usecase,
+            name,
+            passed,
+            message,
+            expected: match expected {
+                Expected::Result(value) => Some(ExampleOutcome::Result(to_host_value(value))),
+                Expected::Error(value) => Some(ExampleOutcome::Error(to_host_value(value))),
+                Expected::Any => None,
+            },
+            actual: Some(match result {
+                Ok(Ok(value)) => ExampleOutcome::Result(value),
+                Ok(Err(value)) => ExampleOutcome::Error(value),
+                Err(exception) => ExampleOutcome::Exception(exception),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn host_value(value: JsonValue) -> HostValue {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_structural_match() {
+        let actual =
+            host_value(json!({ "id": 1, "name": "Foo", "tags": ["a", "b"], "extra": true }));
+
+        assert!(structural_match(&json!({ "id": 1.0, "name": "Foo" }), &actual, "$").is_ok());
+        assert!(structural_match(&json!({ "missing": null }), &actual, "$").is_ok());
+        assert_eq!(
+            structural_match(&json!({ "tags": ["a"] }), &actual, "$"),
+            Err("$.tags: expected 1 items, found 2".to_string())
+        );
+        assert_eq!(
+            structural_match(&json!({ "name": "Bar" }), &actual, "$"),
+            Err("$.name: expected \"Bar\", found \"Foo\"".to_string())
+        );
+        assert_eq!(
+            structural_match(&json!({ "missing": 1 }), &actual, "$"),
+            Err("$.missing: field is missing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_profile_examples() {
+        let examples = profile_examples(
+            r#"name = "scope/example"
+version = "1.0.0"
+
+usecase Foo {
+  example success {
+    input {
+      f = "hello"
+    }
+    result 5
+  }
+  example {
+    error "FORBIDDEN_WORD"
+  }
+}
+
+usecase Bar {
+  example {
+    input {}
+  }
+}
+"#,
+            Some("Foo"),
+        );
+
+        assert_eq!(examples.len(), 2);
+        assert_eq!(examples[0].usecase, "Foo");
+        assert_eq!(examples[0].name.as_deref(), Some("success"));
+        assert!(matches!(
+            examples[0].input,
+            Ok((ref input, Expected::Result(ref result))) if input == &json!({ "f": "hello" }) && result == &json!(5)
+        ));
+        assert!(matches!(
+            examples[1].input,
+            Ok((_, Expected::Error(ref error))) if error == &json!("FORBIDDEN_WORD")
+        ));
+    }
+}
@@ -1,6 +1,7 @@
 use sf_std::unstable::exception::{PerformException, PerformExceptionErrorCode};
 
 use super::{cache::DocumentCacheError, json_schema_validator::JsonSchemaValidatorError};
+use crate::observability::PanicReport;
 
 impl<PostProcessError: std::error::Error> From<DocumentCacheError<PostProcessError>>
     for PerformException
@@ -36,3 +37,22 @@ impl FromJsonSchemaValidationError for PerformException {
         }
     }
 }
+
+/// Exception of performs cancelled by the host.
+pub fn perform_cancelled() -> PerformException {
+    PerformException {
+        error_code: PerformExceptionErrorCode::Cancelled,
+        message: "Perform was cancelled".to_string(),
+    }
+}
+
+/// Exception of performs which panicked, with the message and location of the panic if it was reported.
+pub fn perform_panicked(report: Option<PanicReport>) -> PerformException {
+    PerformException {
+        error_code: PerformExceptionErrorCode::Panic,
+        message: match report {
+            Some(report) => format!("Perform panicked: {}", report),
+            None => "Perform panicked".to_string(),
+        },
+    }
+}
@@ -63,7 +63,13 @@ impl JsonSchemaValidator {
 
     pub fn validate(&self, instance: &HostValue) -> Result<(), JsonSchemaValidatorError> {
         let instance = serde_json::to_value(instance).unwrap();
-        let result = self.compiled.validate(&instance);
+
+        self.validate_value(&instance)
+    }
+
+    /// Validates a JSON value directly, such as a document loaded from a file.
+    pub fn validate_value(&self, instance: &Value) -> Result<(), JsonSchemaValidatorError> {
+        let result = self.compiled.validate(instance);
 
         if let Err(errors) = result {
             return Err(JsonSchemaValidatorError::ValidationErrors(
@@ -0,0 +1,421 @@
+//! Record and replay of outbound HTTP calls.
+//!
+//! Interactions are stored in a JSON cassette file so that maps can be tested offline and deterministically.
+//! Requests are recorded before security is resolved, so secrets never end up in the cassette.
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sf_std::{HeadersMultiMap, MultiMap};
+use url::Url;
+
+use crate::sf_core::{CoreConfiguration, Fs, HttpCassetteMode};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpCassetteError {
+    #[error("Failed to read cassette \"{0}\": {1}")]
+    Read(String, std::io::Error),
+    #[error("Failed to parse cassette \"{0}\": {1}")]
+    Parse(String, serde_json::Error),
+    #[error("Failed to write cassette \"{0}\": {1}")]
+    Write(String, std::io::Error),
+}
+
+/// Body stored as text when it is valid UTF-8, otherwise as base64.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CassetteBody {
+    Text(String),
+    Base64(String),
+}
+impl CassetteBody {
+    pub fn new(data: &[u8]) -> Self {
+        match std::str::from_utf8(data) {
+            Ok(text) => Self::Text(text.to_string()),
+            Err(_) => Self::Base64(base64::engine::general_purpose::STANDARD.encode(data)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.as_bytes().to_vec(),
+            Self::Base64(data) => base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Headers with lowercase names, ordered so that the cassette is stable.
+type CassetteHeaders = BTreeMap<String, Vec<String>>;
+
+fn cassette_headers(headers: &HeadersMultiMap) -> CassetteHeaders {
+    let mut result = CassetteHeaders::new();
+    for (name, values) in headers {
+        result
+            .entry(name.lowercase_chars().collect())
+            .or_default()
+            .extend(values.iter().cloned());
+    }
+
+    result
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteRequest {
+    pub method: String,
+    /// Url including the query.
+    pub url: String,
+    pub headers: CassetteHeaders,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<CassetteBody>,
+}
+impl CassetteRequest {
+    pub fn new(
+        method: &str,
+        url: &str,
+        headers: &HeadersMultiMap,
+        query: &MultiMap,
+        body: Option<&[u8]>,
+    ) -> Self {
+        // same as the url which is sent out, unparseable urls fail to be sent and are kept as-is
+        let url = match Url::parse(url) {
+            Ok(mut url) => {
+                let mut query: Vec<_> = query
+                    .iter()
+                    .flat_map(|(key, values)| values.iter().map(move |value| (key, value)))
+                    .collect();
+                // query is a hash map, sort it so that recordings are stable
+                query.sort();
+                if !query.is_empty() {
+                    url.query_pairs_mut().extend_pairs(query);
+                }
+
+                url.to_string()
+            }
+            Err(_) => url.to_string(),
+        };
+
+        Self {
+            method: method.to_uppercase(),
+            url,
+            headers: cassette_headers(headers),
+            body: body.map(CassetteBody::new),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteResponse {
+    pub status: u16,
+    pub headers: CassetteHeaders,
+    pub body: CassetteBody,
+}
+impl CassetteResponse {
+    pub fn new(status: u16, headers: &HeadersMultiMap, body: &[u8]) -> Self {
+        Self {
+            status,
+            headers: cassette_headers(headers),
+            body: CassetteBody::new(body),
+        }
+    }
+
+    pub fn headers_multimap(&self) -> HeadersMultiMap {
+        self.headers
+            .iter()
+            .map(|(name, values)| (name.as_str().into(), values.clone()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CassetteInteraction {
+    request: CassetteRequest,
+    response: CassetteResponse,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<CassetteInteraction>,
+}
+
+/// Configures which requests are considered equal in replay mode.
+#[derive(Debug, Clone)]
+pub struct CassetteMatching {
+    /// Lowercase names of ignored request headers, `*` ignores all headers.
+    pub ignore_headers: Vec<String>,
+    /// Whether to ignore the order of query parameters.
+    pub normalize_query: bool,
+}
+impl CassetteMatching {
+    fn normalize_url(&self, url: &str) -> String {
+        if !self.normalize_query {
+            return url.to_string();
+        }
+
+        match Url::parse(url) {
+            Ok(mut url) => {
+                let mut query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+                if query.is_empty() {
+                    return url.to_string();
+                }
+
+                query.sort();
+                url.query_pairs_mut().clear().extend_pairs(query);
+                url.to_string()
+            }
+            Err(_) => url.to_string(),
+        }
+    }
+
+    fn filter_headers<'a>(
+        &'a self,
+        headers: &'a CassetteHeaders,
+    ) -> impl Iterator<Item = (&'a String, &'a Vec<String>)> + 'a {
+        headers
+            .iter()
+            .filter(|(name, _)| !self.ignore_headers.iter().any(|ignored| ignored == *name))
+    }
+
+    pub fn matches(&self, recorded: &CassetteRequest, request: &CassetteRequest) -> bool {
+        if recorded.method != request.method || recorded.body != request.body {
+            return false;
+        }
+        if self.normalize_url(&recorded.url) != self.normalize_url(&request.url) {
+            return false;
+        }
+        if self.ignore_headers.iter().any(|ignored| ignored == "*") {
+            return true;
+        }
+
+        self.filter_headers(&recorded.headers)
+            .eq(self.filter_headers(&request.headers))
+    }
+}
+
+#[derive(Debug)]
+pub struct HttpCassette {
+    path: String,
+    replay: bool,
+    matching: CassetteMatching,
+    interactions: Vec<CassetteInteraction>,
+    /// Whether each interaction has been replayed already
+    replayed: Vec<bool>,
+    /// Whether a perform is writing the cassette file right now
+    saving: bool,
+    /// Whether interactions were recorded while the cassette file was being written
+    unsaved: bool,
+}
+impl HttpCassette {
+    /// Creates the cassette according to configuration, loading recorded interactions in replay mode.
+    pub fn from_config(config: &CoreConfiguration) -> Result<Option<Self>, HttpCassetteError> {
+        let replay = match config.http_cassette_mode {
+            HttpCassetteMode::Off => return Ok(None),
+            HttpCassetteMode::Record => false,
+            HttpCassetteMode::Replay => true,
+        };
+        let path = config.http_cassette_path.clone();
+
+        let interactions = if replay {
+            let data = Fs::read(&path).map_err(|err| HttpCassetteError::Read(path.clone(), err))?;
+            serde_json::from_slice::<CassetteFile>(&data)
+                .map_err(|err| HttpCassetteError::Parse(path.clone(), err))?
+                .interactions
+        } else {
+            Vec::new()
+        };
+
+        Ok(Some(Self::new(
+            path,
+            replay,
+            CassetteMatching {
+                ignore_headers: config.http_cassette_ignore_headers.clone(),
+                normalize_query: config.http_cassette_normalize_query,
+            },
+            interactions,
+        )))
+    }
+
+    fn new(
+        path: String,
+        replay: bool,
+        matching: CassetteMatching,
+        interactions: Vec<CassetteInteraction>,
+    ) -> Self {
+        Self {
+            path,
+            replay,
+            matching,
+            replayed: vec![false; interactions.len()],
+            interactions,
+            saving: false,
+            unsaved: false,
+        }
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.replay
+    }
+
+    /// Finds the response to `request`.
+    ///
+    /// Matching interactions are replayed in the order they were recorded. Once all of them have been replayed
+    /// the last one is repeated, so the same perform can be run multiple times.
+    pub fn replay(&mut self, request: &CassetteRequest) -> Option<CassetteResponse> {
+        let mut last_match = None;
+        for (index, interaction) in self.interactions.iter().enumerate() {
+            if !self.matching.matches(&interaction.request, request) {
+                continue;
+            }
+
+            if !self.replayed[index] {
+                self.replayed[index] = true;
+                return Some(interaction.response.clone());
+            }
+            last_match = Some(index);
+        }
+
+        last_match.map(|index| self.interactions[index].response.clone())
+    }
+
+    pub fn record(&mut self, request: CassetteRequest, response: CassetteResponse) {
+        self.interactions
+            .push(CassetteInteraction { request, response });
+        self.replayed.push(false);
+    }
+
+    /// Writes recorded interactions into the cassette file, does nothing in replay mode.
+    ///
+    /// The cassette is shared by concurrent performs, so it is only locked while serializing and not while writing.
+    /// If another perform is already writing the file, it writes the file again once done instead.
+    pub fn save(cassette: &Mutex<Self>) -> Result<(), HttpCassetteError> {
+        loop {
+            let (path, data) = {
+                let mut cassette = cassette.lock().unwrap();
+                if cassette.replay {
+                    return Ok(());
+                }
+                if cassette.saving {
+                    cassette.unsaved = true;
+                    return Ok(());
+                }
+                cassette.saving = true;
+                cassette.unsaved = false;
+
+                let file = serde_json::json!({ "interactions": &cassette.interactions });
+                let data = serde_json::to_vec_pretty(&file).expect("Cassette is serializable");
+                (cassette.path.clone(), data)
+            };
+
+            let result = Fs::write(&path, &data).map_err(|err| HttpCassetteError::Write(path, err));
+
+            let mut cassette = cassette.lock().unwrap();
+            cassette.saving = false;
+            if result.is_err() || !cassette.unsaved {
+                return result;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(url: &str, query: &[(&str, &str)], headers: &[(&str, &str)]) -> CassetteRequest {
+        let mut query_map = MultiMap::new();
+        for (key, value) in query {
+            query_map
+                .entry(key.to_string())
+                .or_insert_with(Vec::new)
+                .push(value.to_string());
+        }
+        let mut header_map = HeadersMultiMap::new();
+        for (name, value) in headers {
+            header_map.insert((*name).into(), vec![value.to_string()]);
+        }
+
+        CassetteRequest::new("get", url, &header_map, &query_map, None)
+    }
+
+    fn response(body: &str) -> CassetteResponse {
+        CassetteResponse::new(200, &HeadersMultiMap::new(), body.as_bytes())
+    }
+
+    fn matching(ignore_headers: &[&str], normalize_query: bool) -> CassetteMatching {
+        CassetteMatching {
+            ignore_headers: ignore_headers.iter().map(|h| h.to_string()).collect(),
+            normalize_query,
+        }
+    }
+
+    #[test]
+    fn test_cassette_request() {
+        let request = request(
+            "https://example.com/path?a=1",
+            &[("c", "3"), ("b", "2")],
+            &[("Accept", "application/json")],
+        );
+
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.url, "https://example.com/path?a=1&b=2&c=3");
+        assert_eq!(request.headers["accept"], vec!["application/json"]);
+    }
+
+    #[test]
+    fn test_cassette_body() {
+        assert_eq!(
+            CassetteBody::new(b"text"),
+            CassetteBody::Text("text".into())
+        );
+
+        let binary = CassetteBody::new(&[0xff, 0x00]);
+        assert_eq!(binary, CassetteBody::Base64("/wA=".into()));
+        assert_eq!(binary.to_bytes(), vec![0xff, 0x00]);
+    }
+
+    #[test]
+    fn test_cassette_matching() {
+        let recorded = request("https://example.com/?b=2&a=1", &[], &[("x-id", "1")]);
+        let reordered = request("https://example.com/?a=1&b=2", &[], &[("X-Id", "1")]);
+        let other_header = request("https://example.com/?a=1&b=2", &[], &[("x-id", "2")]);
+
+        assert!(matching(&[], true).matches(&recorded, &reordered));
+        assert!(!matching(&[], false).matches(&recorded, &reordered));
+        assert!(!matching(&[], true).matches(&recorded, &other_header));
+        assert!(matching(&["x-id"], true).matches(&recorded, &other_header));
+        assert!(matching(&["*"], true).matches(&recorded, &other_header));
+        assert!(!matching(&["*"], true)
+            .matches(&recorded, &request("https://example.com/other", &[], &[])));
+    }
+
+    #[test]
+    fn test_cassette_replay() {
+        let mut cassette = HttpCassette::new(
+            "cassette.json".into(),
+            true,
+            matching(&[], true),
+            Vec::new(),
+        );
+        let first = request("https://example.com/", &[], &[]);
+        cassette.record(first.clone(), response("first"));
+        cassette.record(first.clone(), response("second"));
+
+        assert_eq!(
+            cassette.replay(&first).unwrap().body,
+            CassetteBody::Text("first".into())
+        );
+        assert_eq!(
+            cassette.replay(&first).unwrap().body,
+            CassetteBody::Text("second".into())
+        );
+        assert_eq!(
+            cassette.replay(&first).unwrap().body,
+            CassetteBody::Text("second".into())
+        );
+        assert!(cassette
+            .replay(&request("https://example.com/unknown", &[], &[]))
+            .is_none());
+    }
+}
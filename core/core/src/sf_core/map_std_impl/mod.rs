@@ -1,4 +1,13 @@
-use std::io::{Read, Write};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+pub use self::{cassette::HttpCassette, policy::HttpPolicy};
 
 use map_std::{
     handle_map::HandleMap,
@@ -13,13 +22,26 @@ use map_std::{
 use sf_std::{
     abi::Handle,
     fmt::{HttpRequestFmt, HttpResponseFmt},
+    unstable::{perform::TraceContext, secret::get_secret_in},
     HeaderName,
 };
 
-use self::stream::PeekableStream;
+use self::{
+    cassette::{CassetteRequest, CassetteResponse},
+    stream::StreamEntry,
+};
 
-use super::HttpRequest;
+use super::{redirect::RedirectableRequest, HttpRequest, HttpResponse};
+use crate::{
+    bindings::MessageExchangeFfi,
+    observability::{
+        metrics::HttpCallMetrics,
+        traces::{self, TraceParent},
+    },
+};
 
+mod cassette;
+mod policy;
 mod stream;
 
 #[derive(Debug, Clone)]
@@ -32,22 +54,66 @@ pub struct MapStdImplConfig {
     pub user_agent: String,
 }
 
+/// Trace context propagated into outbound HTTP calls.
+#[derive(Debug, Clone, Default)]
+pub struct TracePropagation {
+    /// Trace context of the caller passed with the perform input
+    pub context: Option<TraceContext>,
+    /// Resolved base urls of services which opted out of receiving trace context headers
+    pub excluded_base_urls: Vec<String>,
+}
+
+/// Http call sent to the host together with what is needed to follow its redirects.
+struct LiveHttpCall {
+    request: HttpRequest,
+    redirectable: RedirectableRequest,
+    /// Whether security values were attached to the request
+    with_security: bool,
+}
+
+/// Http call started by the map which hasn't received its response head yet.
+enum PendingHttpCall {
+    Live(LiveHttpCall),
+    /// Live call whose response is recorded into the cassette
+    Recording(LiveHttpCall, CassetteRequest),
+    Replayed(CassetteResponse),
+}
+
 pub struct MapStdImpl {
-    http_requests: HandleMap<HttpRequest>,
+    /// In-flight requests together with the span covering each call
+    http_requests: HandleMap<(PendingHttpCall, tracing::Span)>,
     streams: HandleMap<stream::StreamEntry>,
     security: Option<SecurityMap>,
+    /// Secrets referenced by security values, retrieved from the host when a request first needs them
+    secrets: HashMap<String, String>,
     map_context: Option<MapValue>,
     map_output: Option<Result<MapValue, MapValue>>,
+    http_metrics: HttpCallMetrics,
+    trace_propagation: TracePropagation,
+    /// Hosts HTTP calls are allowed to reach, none unless set for the perform
+    http_policy: HttpPolicy,
+    /// Cassette shared with other performs running concurrently
+    http_cassette: Option<Arc<Mutex<HttpCassette>>>,
+    /// Set when the host cancels the perform
+    cancelled: Arc<AtomicBool>,
     config: MapStdImplConfig,
 }
 impl MapStdImpl {
+    const CANCELLED_MESSAGE: &str = "Perform was cancelled";
+
     pub fn new(config: MapStdImplConfig) -> Self {
         Self {
             http_requests: HandleMap::new(),
             streams: HandleMap::new(),
             security: None,
+            secrets: HashMap::new(),
             map_context: None,
             map_output: None,
+            http_metrics: HttpCallMetrics::default(),
+            trace_propagation: TracePropagation::default(),
+            http_policy: HttpPolicy::default(),
+            http_cassette: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
             config,
         }
     }
@@ -60,9 +126,135 @@ impl MapStdImpl {
         self.security = security;
     }
 
+    pub fn set_trace_propagation(&mut self, trace_propagation: TracePropagation) {
+        self.trace_propagation = trace_propagation;
+    }
+
+    pub fn set_http_policy(&mut self, http_policy: HttpPolicy) {
+        self.http_policy = http_policy;
+    }
+
+    pub fn set_http_cassette(&mut self, http_cassette: Option<Arc<Mutex<HttpCassette>>>) {
+        self.http_cassette = http_cassette;
+    }
+
+    pub fn set_cancellation(&mut self, cancelled: Arc<AtomicBool>) {
+        self.cancelled = cancelled;
+    }
+
+    /// Returns whether the perform was cancelled, in which case outstanding HTTP requests and streams are closed.
+    fn is_cancelled(&mut self) -> bool {
+        if !self.cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        // dropping requests and streams closes them
+        self.http_requests = HandleMap::new();
+        self.streams = HandleMap::new();
+
+        true
+    }
+
+    /// Adds `traceparent` and `tracestate` headers to the request unless its service opted out.
+    ///
+    /// The `traceparent` identifies the current exported span if there is one, otherwise the caller trace context is forwarded.
+    fn inject_trace_context(&self, params: &mut MapHttpRequest) {
+        let excluded = self
+            .trace_propagation
+            .excluded_base_urls
+            .iter()
+            .any(|base_url| policy::is_under_base_url(&params.url, base_url));
+        if excluded {
+            return;
+        }
+
+        let remote = self.trace_propagation.context.as_ref().and_then(|context| {
+            context
+                .traceparent
+                .parse::<TraceParent>()
+                .ok()
+                .map(|parent| (parent, context.tracestate.as_ref()))
+        });
+        let traceparent = match traces::current_trace_parent().or(remote.map(|(p, _)| p)) {
+            Some(traceparent) => traceparent,
+            None => return,
+        };
+
+        params
+            .headers
+            .entry(HeaderName::from("traceparent"))
+            .or_insert(vec![traceparent.to_string()]);
+        // tracestate only belongs to the trace it was received with
+        if let Some((parent, Some(tracestate))) = remote {
+            if parent.trace_id == traceparent.trace_id {
+                params
+                    .headers
+                    .entry(HeaderName::from("tracestate"))
+                    .or_insert(vec![tracestate.clone()]);
+            }
+        }
+    }
+
+    /// Receives the response of a live call, following redirects to locations allowed by the HTTP policy.
+    ///
+    /// Redirects of requests with security have to stay within the services of the provider.
+    fn receive_response(
+        &mut self,
+        call: LiveHttpCall,
+    ) -> Result<HttpResponse, MapHttpCallHeadError> {
+        let LiveHttpCall {
+            mut request,
+            mut redirectable,
+            with_security,
+        } = call;
+
+        loop {
+            let response = request.into_response().map_err(|err| {
+                self.http_metrics.failed_calls += 1;
+                tracing::error!("HTTP call failed: {}", err);
+                MapHttpCallHeadError::from(err)
+            })?;
+
+            match redirectable.follow(response.status(), response.headers()) {
+                Ok(false) => return Ok(response),
+                Ok(true) => (),
+                Err(err) => {
+                    self.http_metrics.failed_calls += 1;
+                    tracing::error!("HTTP call failed: {}", err);
+                    return Err(MapHttpCallHeadError::Failed(err.to_string()));
+                }
+            }
+            if let Err(err) = self.http_policy.check(&redirectable.url, with_security) {
+                tracing::warn!(target: "@user", "Rejected HTTP redirect to {}: {}", redirectable.url, err);
+                self.http_metrics.failed_calls += 1;
+                return Err(MapHttpCallHeadError::Forbidden(err.to_string()));
+            }
+            tracing::debug!(status = response.status(), location = %redirectable.url, "Following redirect");
+            // dropping the response closes its body
+            drop(response);
+
+            request = HttpRequest::fetch(
+                &redirectable.method,
+                &redirectable.url,
+                &redirectable.headers,
+                &Default::default(),
+                redirectable.body.as_deref(),
+            )
+            .map_err(|err| {
+                self.http_metrics.failed_calls += 1;
+                tracing::error!("HTTP call failed: {}", err);
+                MapHttpCallHeadError::Failed(err.to_string())
+            })?;
+        }
+    }
+
     pub fn take_output(&mut self) -> Option<Result<MapValue, MapValue>> {
         self.map_output.take()
     }
+
+    pub fn http_metrics(&self) -> &HttpCallMetrics {
+        &self.http_metrics
+    }
 }
 impl MapStdUnstable for MapStdImpl {
     fn print(&mut self, message: &str) {
@@ -70,13 +262,33 @@ impl MapStdUnstable for MapStdImpl {
     }
 
     fn stream_read(&mut self, handle: Handle, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.is_cancelled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                Self::CANCELLED_MESSAGE,
+            ));
+        }
+
         match self.streams.get_mut(handle) {
             None => Err(std::io::ErrorKind::NotFound.into()),
-            Some(stream) => stream.read(buf),
+            Some(stream) => {
+                // all streams are currently http response bodies
+                let count = stream.read(buf)?;
+                self.http_metrics.bytes_received += count;
+
+                Ok(count)
+            }
         }
     }
 
     fn stream_write(&mut self, handle: Handle, buf: &[u8]) -> std::io::Result<usize> {
+        if self.is_cancelled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                Self::CANCELLED_MESSAGE,
+            ));
+        }
+
         match self.streams.get_mut(handle) {
             None => Err(std::io::ErrorKind::NotFound.into()),
             Some(stream) => stream.write(buf),
@@ -84,6 +296,13 @@ impl MapStdUnstable for MapStdImpl {
     }
 
     fn stream_close(&mut self, handle: Handle) -> std::io::Result<()> {
+        if self.is_cancelled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                Self::CANCELLED_MESSAGE,
+            ));
+        }
+
         match self.streams.try_remove(handle) {
             None => Err(std::io::ErrorKind::NotFound.into()),
             Some(_) => Ok(()), // drop cleans up
@@ -91,8 +310,52 @@ impl MapStdUnstable for MapStdImpl {
     }
 
     fn http_call(&mut self, mut params: MapHttpRequest) -> Result<Handle, MapHttpCallError> {
-        let security_map = self.security.as_ref().unwrap();
-        resolve_security(security_map, &mut params)?;
+        if self.is_cancelled() {
+            return Err(MapHttpCallError::Failed(
+                Self::CANCELLED_MESSAGE.to_string(),
+            ));
+        }
+
+        let with_security = params.security.is_some();
+        if let Err(err) = self.http_policy.check(&params.url, with_security) {
+            tracing::warn!(target: "@user", "Rejected HTTP call {} {}: {}", params.method, params.url, err);
+            self.http_metrics.calls += 1;
+            self.http_metrics.failed_calls += 1;
+            return Err(MapHttpCallError::Forbidden(err.to_string()));
+        }
+
+        // recorded before security is resolved so that secrets don't end up in the cassette
+        let cassette_request = self.http_cassette.as_ref().map(|_| {
+            CassetteRequest::new(
+                &params.method,
+                &params.url,
+                &params.headers,
+                &params.query,
+                params.body.as_deref(),
+            )
+        });
+
+        let replaying = self
+            .http_cassette
+            .as_ref()
+            .map(|c| c.lock().unwrap().is_replay())
+            .unwrap_or(false);
+        if !replaying {
+            let security_map = self.security.as_ref().unwrap();
+            let secrets = &mut self.secrets;
+            resolve_security(security_map, &mut params, |name| {
+                if let Some(value) = secrets.get(name) {
+                    return Ok(value.clone());
+                }
+
+                tracing::debug!(secret = name, "Retrieving secret from host");
+                let value = get_secret_in(name, MessageExchangeFfi)
+                    .map_err(|err| MapHttpCallError::MissingSecret(err.to_string()))?;
+                secrets.insert(name.to_string(), value.clone());
+
+                Ok(value)
+            })?;
+        }
 
         // IDEA: add profile, provider info as well?
         params
@@ -103,17 +366,67 @@ impl MapStdUnstable for MapStdImpl {
                 self.config.user_agent.clone()
             )]);
 
+        let span = tracing::debug_span!(
+            "HTTP call",
+            http.method = %params.method,
+            http.url = %params.url,
+            http.status_code = tracing::field::Empty
+        );
         // We want to log the transaction below together with the handle, but we want to log it even if it fails
         // in which case it doesn't get a handle, so we play around with a result here
-        let handle_result = HttpRequest::fetch(
-            &params.method,
-            &params.url,
-            &params.headers,
-            &params.query,
-            params.body.as_deref(),
-        )
-        .map(|request| self.http_requests.insert(request))
-        .map_err(MapHttpCallError::from);
+        let handle_result = span
+            .in_scope(|| {
+                self.inject_trace_context(&mut params);
+
+                match (replaying, cassette_request) {
+                    (true, Some(request)) => {
+                        let cassette = self.http_cassette.as_ref().unwrap();
+                        match cassette.lock().unwrap().replay(&request) {
+                            Some(response) => Ok(PendingHttpCall::Replayed(response)),
+                            None => Err(MapHttpCallError::Failed(format!(
+                                "No interaction in the cassette matches request {} {}",
+                                request.method, request.url
+                            ))),
+                        }
+                    }
+                    (_, cassette_request) => HttpRequest::fetch(
+                        &params.method,
+                        &params.url,
+                        &params.headers,
+                        &params.query,
+                        params.body.as_deref(),
+                    )
+                    .map(|request| {
+                        let call = LiveHttpCall {
+                            request,
+                            redirectable: RedirectableRequest::new(
+                                &params.method,
+                                &params.url,
+                                &params.headers,
+                                &params.query,
+                                params.body.as_deref(),
+                            ),
+                            with_security,
+                        };
+                        match cassette_request {
+                            Some(cassette_request) => {
+                                PendingHttpCall::Recording(call, cassette_request)
+                            }
+                            None => PendingHttpCall::Live(call),
+                        }
+                    })
+                    .map_err(MapHttpCallError::from),
+                }
+            })
+            .map(|call| self.http_requests.insert((call, span)));
+
+        self.http_metrics.calls += 1;
+        match handle_result {
+            Ok(_) => {
+                self.http_metrics.bytes_sent += params.body.as_ref().map(|b| b.len()).unwrap_or(0)
+            }
+            Err(_) => self.http_metrics.failed_calls += 1,
+        }
 
         // IDEA: mark this branch as unlikely?
         if self.config.log_http_transactions {
@@ -134,20 +447,60 @@ impl MapStdUnstable for MapStdImpl {
     }
 
     fn http_call_head(&mut self, handle: Handle) -> Result<MapHttpResponse, MapHttpCallHeadError> {
+        if self.is_cancelled() {
+            return Err(MapHttpCallHeadError::Failed(
+                Self::CANCELLED_MESSAGE.to_string(),
+            ));
+        }
+
         match self.http_requests.try_remove(handle) {
             None => Err(MapHttpCallHeadError::InvalidHandle),
-            Some(request) => {
-                let response = request.into_response()?;
-                let status = response.status();
-                let headers = response.headers().clone();
-                let body = response.into_body();
+            Some((call, span)) => {
+                let _entered = span.enter();
+
+                let (status, headers, body) = match call {
+                    PendingHttpCall::Replayed(response) => (
+                        response.status,
+                        response.headers_multimap(),
+                        StreamEntry::from(response.body.to_bytes()),
+                    ),
+                    PendingHttpCall::Live(request) => {
+                        let response = self.receive_response(request)?;
+                        let status = response.status();
+                        let headers = response.headers().clone();
+
+                        (status, headers, StreamEntry::from(response.into_body()))
+                    }
+                    PendingHttpCall::Recording(request, cassette_request) => {
+                        let response = self.receive_response(request)?;
+                        let status = response.status();
+                        let headers = response.headers().clone();
+
+                        let mut body = Vec::new();
+                        if let Err(err) = response.into_body().read_to_end(&mut body) {
+                            self.http_metrics.failed_calls += 1;
+                            tracing::error!("HTTP call failed: {}", err);
+                            return Err(MapHttpCallHeadError::Failed(err.to_string()));
+                        }
+                        if let Some(ref cassette) = self.http_cassette {
+                            cassette.lock().unwrap().record(
+                                cassette_request,
+                                CassetteResponse::new(status, &headers, &body),
+                            );
+                        }
+
+                        (status, headers, StreamEntry::from(body))
+                    }
+                };
+                span.record("http.status_code", status);
+                self.http_metrics.record_status(status);
 
                 // IDEA: mark this branch as unlikely?
                 let body_stream = if self.config.log_http_transactions {
                     let _span = tracing::debug_span!(target: "@user", "HTTP Response", id = handle)
                         .entered();
 
-                    let mut stream = PeekableStream::from(body);
+                    let mut stream = body.into_peekable();
 
                     tracing::debug!(
                         target: "@user",
@@ -158,9 +511,9 @@ impl MapStdUnstable for MapStdImpl {
                         }
                     );
 
-                    stream.into()
+                    stream
                 } else {
-                    body.into()
+                    body
                 };
 
                 Ok(MapHttpResponse {
@@ -0,0 +1,240 @@
+//! Restriction of outbound HTTP calls of maps.
+//!
+//! Maps may only call the services declared by the provider and hosts explicitly allowed in the configuration.
+//! Security values are only attached to requests to the declared services so that credentials cannot be sent elsewhere.
+
+use url::{Origin, Url};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpPolicyViolation {
+    #[error("Invalid url \"{0}\": {1}")]
+    InvalidUrl(String, url::ParseError),
+    #[error("\"{0}\" is neither a service of the provider nor an allowed host")]
+    UndeclaredHost(String),
+    #[error("Security can only be used with services of the provider, \"{0}\" is not one")]
+    SecurityNotAllowed(String),
+}
+
+/// Hosts outbound HTTP calls of a perform are allowed to reach.
+///
+/// The default policy doesn't allow any call.
+#[derive(Debug, Clone, Default)]
+pub struct HttpPolicy {
+    /// Origins of the resolved base urls of provider services
+    service_origins: Vec<Origin>,
+    /// Lowercase hosts from the configuration, `*.example.com` matches subdomains and `*` matches any host
+    allowed_hosts: Vec<String>,
+}
+impl HttpPolicy {
+    pub fn new<S: AsRef<str>>(
+        service_base_urls: impl IntoIterator<Item = S>,
+        allowed_hosts: Vec<String>,
+    ) -> Self {
+        let service_origins = service_base_urls
+            .into_iter()
+            .filter_map(|base_url| match Url::parse(base_url.as_ref()) {
+                Ok(url) => Some(url.origin()),
+                Err(err) => {
+                    tracing::warn!(
+                        "Ignoring service with invalid base url \"{}\": {}",
+                        base_url.as_ref(),
+                        err
+                    );
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            service_origins,
+            allowed_hosts,
+        }
+    }
+
+    /// Checks whether a request to `url` is allowed, `with_security` if security values are to be attached to it.
+    ///
+    /// Services are matched by scheme, host and port, allowed hosts only by host.
+    pub fn check(&self, url: &str, with_security: bool) -> Result<(), HttpPolicyViolation> {
+        let url =
+            Url::parse(url).map_err(|err| HttpPolicyViolation::InvalidUrl(url.to_string(), err))?;
+        let origin = url.origin();
+
+        // opaque origins never compare equal, so urls without a host can't match a service
+        if self.service_origins.contains(&origin) {
+            return Ok(());
+        }
+
+        if with_security {
+            return Err(HttpPolicyViolation::SecurityNotAllowed(
+                origin.ascii_serialization(),
+            ));
+        }
+
+        if let Some(host) = url.host_str() {
+            if self
+                .allowed_hosts
+                .iter()
+                .any(|allowed| Self::host_matches(allowed, host))
+            {
+                return Ok(());
+            }
+        }
+
+        Err(HttpPolicyViolation::UndeclaredHost(
+            origin.ascii_serialization(),
+        ))
+    }
+
+    fn host_matches(allowed: &str, host: &str) -> bool {
+        if allowed == "*" {
+            return true;
+        }
+
+        match allowed.strip_prefix("*.") {
+            Some(domain) => host
+                .strip_suffix(domain)
+                .map(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.'))
+                .unwrap_or(false),
+            None => allowed == host,
+        }
+    }
+}
+
+/// Whether `url` is `base_url` or below it, that is it has the same origin and the path of `base_url` is a prefix
+/// of its path in whole segments.
+pub fn is_under_base_url(url: &str, base_url: &str) -> bool {
+    let (url, base_url) = match (Url::parse(url), Url::parse(base_url)) {
+        (Ok(url), Ok(base_url)) => (url, base_url),
+        _ => return false,
+    };
+    // opaque origins never compare equal
+    if url.origin() != base_url.origin() {
+        return false;
+    }
+
+    let base_path = base_url.path().trim_end_matches('/');
+    match url.path().strip_prefix(base_path) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn policy(allowed_hosts: &[&str]) -> HttpPolicy {
+        HttpPolicy::new(
+            ["https://api.example.com", "http://localhost:8080/v1"],
+            allowed_hosts.iter().map(|h| h.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_services() {
+        let policy = policy(&[]);
+
+        policy
+            .check("https://api.example.com/users?page=2", true)
+            .unwrap();
+        policy.check("https://API.example.com:443/", true).unwrap();
+        policy.check("http://localhost:8080/other", true).unwrap();
+
+        for url in [
+            "http://api.example.com/users",
+            "https://api.example.com:8443/users",
+            "https://other.example.com/",
+            "http://localhost/",
+            "http://169.254.169.254/latest/meta-data/",
+            "https://api.example.com@169.254.169.254/",
+            "file:///etc/passwd",
+        ] {
+            assert!(
+                matches!(
+                    policy.check(url, false),
+                    Err(HttpPolicyViolation::UndeclaredHost(_))
+                ),
+                "{} should be rejected",
+                url
+            );
+        }
+
+        assert!(matches!(
+            policy.check("not a url", false),
+            Err(HttpPolicyViolation::InvalidUrl(..))
+        ));
+    }
+
+    #[test]
+    fn test_allowed_hosts() {
+        let policy = policy(&["files.example.org", "*.cdn.example.net"]);
+
+        policy.check("https://files.example.org/a", false).unwrap();
+        policy
+            .check("http://files.example.org:81/a", false)
+            .unwrap();
+        policy.check("https://eu.cdn.example.net/a", false).unwrap();
+        policy
+            .check("https://a.eu.cdn.example.net/a", false)
+            .unwrap();
+
+        for url in [
+            "https://cdn.example.net/a",
+            "https://evilcdn.example.net/a",
+            "https://other.example.org/a",
+        ] {
+            assert!(
+                policy.check(url, false).is_err(),
+                "{} should be rejected",
+                url
+            );
+        }
+
+        assert!(matches!(
+            policy.check("https://files.example.org/a", true),
+            Err(HttpPolicyViolation::SecurityNotAllowed(_))
+        ));
+
+        let any = HttpPolicy::new(Vec::<String>::new(), vec!["*".to_string()]);
+        any.check("http://169.254.169.254/", false).unwrap();
+        assert!(any.check("http://169.254.169.254/", true).is_err());
+    }
+
+    #[test]
+    fn test_under_base_url() {
+        assert!(is_under_base_url(
+            "https://api.example.com/users",
+            "https://api.example.com"
+        ));
+        assert!(is_under_base_url(
+            "https://API.example.com:443/v2/users?page=2",
+            "https://api.example.com/v2/"
+        ));
+        assert!(is_under_base_url(
+            "https://api.example.com/v2",
+            "https://api.example.com/v2"
+        ));
+
+        for url in [
+            "https://api.example.com.evil.com/users",
+            "https://api.example.com@evil.com/v2/users",
+            "http://api.example.com/v2/users",
+            "https://api.example.com/v2x/users",
+            "https://api.example.com/",
+            "not a url",
+        ] {
+            assert!(
+                !is_under_base_url(url, "https://api.example.com/v2"),
+                "{} should not match",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_denies() {
+        assert!(HttpPolicy::default()
+            .check("https://api.example.com/", false)
+            .is_err());
+    }
+}
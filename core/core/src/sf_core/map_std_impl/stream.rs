@@ -1,4 +1,4 @@
-use std::io::{Read, Write};
+use std::io::{Cursor, Read, Write};
 
 use crate::sf_core::{HttpResponse, IoStream};
 
@@ -6,6 +6,31 @@ pub enum StreamEntry {
     Io(IoStream),
     /// Buffered streams are currently implemented for body logging
     Peekable(PeekableStream),
+    /// Bodies recorded into or replayed from a cassette
+    Memory(Cursor<Vec<u8>>),
+}
+impl StreamEntry {
+    /// Wraps io streams so that their beginning can be peeked at.
+    pub fn into_peekable(self) -> Self {
+        match self {
+            Self::Io(i) => Self::Peekable(PeekableStream::from(i)),
+            other => other,
+        }
+    }
+
+    /// Returns up to `count` bytes from the start of the stream without consuming them.
+    ///
+    /// Io streams need to be converted using [into_peekable](Self::into_peekable) first.
+    pub fn peek(&mut self, count: usize) -> std::io::Result<&[u8]> {
+        match self {
+            Self::Io(_) => Err(std::io::ErrorKind::Unsupported.into()),
+            Self::Peekable(i) => i.peek(count),
+            Self::Memory(i) => {
+                let data = &i.get_ref()[i.position() as usize..];
+                Ok(&data[..count.min(data.len())])
+            }
+        }
+    }
 }
 impl From<IoStream> for StreamEntry {
     fn from(value: IoStream) -> Self {
@@ -17,6 +42,11 @@ impl From<PeekableStream> for StreamEntry {
         Self::Peekable(value)
     }
 }
+impl From<Vec<u8>> for StreamEntry {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Memory(Cursor::new(value))
+    }
+}
 impl From<HttpResponse> for StreamEntry {
     fn from(value: HttpResponse) -> Self {
         Self::Io(value.into_body())
@@ -27,6 +57,7 @@ impl Read for StreamEntry {
         match self {
             Self::Io(i) => i.read(buf),
             Self::Peekable(i) => i.read(buf),
+            Self::Memory(i) => i.read(buf),
         }
     }
 }
@@ -35,6 +66,7 @@ impl Write for StreamEntry {
         match self {
             Self::Io(i) => i.write(buf),
             Self::Peekable(i) => i.write(buf),
+            Self::Memory(i) => i.write(buf),
         }
     }
 
@@ -42,6 +74,7 @@ impl Write for StreamEntry {
         match self {
             Self::Io(i) => i.flush(),
             Self::Peekable(i) => i.flush(),
+            Self::Memory(i) => i.flush(),
         }
     }
 }
@@ -1,13 +1,17 @@
 use std::borrow::Cow;
 
+use crate::observability::metrics::{HttpCallMetrics, PerformTimings};
+
 /// For the purposes of metrics we are interested in some data which may or may not be parsed out of the profile, provider and map.
 ///
 /// If the perform ends successfully or with a maped error these fields will be available. If it ends with an exception some of these fields might not be available.
 #[derive(Debug, Default)]
 #[allow(dead_code)] // TODO: until we use these fields
 pub struct PerformMetricsData<'a> {
+    /// Id of the perform
+    pub perform_id: &'a str,
     /// Profile id in format `<scope>/<name>`
-    pub profile: Option<&'a str>,
+    pub profile: Option<String>,
     /// Profile url as passed into perform
     pub profile_url: &'a str,
     /// Profile version as parsed from the profile header
@@ -23,11 +27,15 @@ pub struct PerformMetricsData<'a> {
     /// Map version as parsed from map metadata
     pub map_version: Option<String>,
     pub map_content_hash: Option<&'a str>,
+    /// Wall times of perform phases which have finished
+    pub timings: PerformTimings,
+    /// Outbound HTTP calls made by the map
+    pub http: HttpCallMetrics,
 }
 impl<'a> PerformMetricsData<'a> {
     pub fn get_profile(&self) -> Cow<'_, str> {
         match self.profile {
-            Some(profile) => Cow::Borrowed(profile),
+            Some(ref profile) => Cow::Borrowed(profile),
             None => match self.profile_url.split('/').last() {
                 // treat anything from .profile until the end of the url as an extension
                 // this works for both .profile and .profile.ts extensions
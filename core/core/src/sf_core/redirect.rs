@@ -0,0 +1,248 @@
+//! Redirects of outbound HTTP calls.
+//!
+//! Hosts return redirect responses as they are and core follows them, so that each location can be checked
+//! against the [HttpPolicy](super::map_std_impl::HttpPolicy) before it is requested.
+
+use sf_std::{HeaderName, HeadersMultiMap, MultiMap};
+use url::Url;
+
+/// Headers which are not sent again when a redirect changes the origin.
+const CREDENTIAL_HEADERS: [&str; 3] = ["authorization", "proxy-authorization", "cookie"];
+/// Headers which are not sent again when a redirect drops the body.
+const BODY_HEADERS: [&str; 2] = ["content-type", "content-length"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum RedirectError {
+    #[error("Redirect to invalid location \"{0}\": {1}")]
+    InvalidLocation(String, url::ParseError),
+    #[error("Too many redirects, stopped after {0}")]
+    TooManyRedirects(usize),
+}
+
+/// Request which is updated to follow redirect responses.
+#[derive(Debug, Clone)]
+pub struct RedirectableRequest {
+    pub method: String,
+    /// Url including the query
+    pub url: String,
+    pub headers: HeadersMultiMap,
+    pub body: Option<Vec<u8>>,
+    redirects: usize,
+}
+impl RedirectableRequest {
+    pub const MAX_REDIRECTS: usize = 10;
+
+    /// Creates the request, `query` is merged into `url` the same way the host request does it.
+    pub fn new(
+        method: &str,
+        url: &str,
+        headers: &HeadersMultiMap,
+        query: &MultiMap,
+        body: Option<&[u8]>,
+    ) -> Self {
+        let url =
+            match Url::parse(url) {
+                Ok(mut url) => {
+                    if !query.is_empty() {
+                        url.query_pairs_mut().extend_pairs(query.iter().flat_map(
+                            |(key, values)| values.iter().map(move |value| (key, value)),
+                        ));
+                    }
+                    url.into()
+                }
+                // fails once requested
+                Err(_) => url.to_string(),
+            };
+
+        Self {
+            method: method.to_string(),
+            url,
+            headers: headers.clone(),
+            body: body.map(|b| b.to_vec()),
+            redirects: 0,
+        }
+    }
+
+    /// Updates the request to follow a response with `status` and lowercase `headers`.
+    ///
+    /// Returns `false` if the response is not a redirect and should be returned as is.
+    ///
+    /// Like in browsers, `303` and `POST` requests redirected by `301` or `302` turn into a `GET` without a body,
+    /// and credential headers are not sent to a different origin.
+    pub fn follow(
+        &mut self,
+        status: u16,
+        headers: &HeadersMultiMap,
+    ) -> Result<bool, RedirectError> {
+        if !matches!(status, 301 | 302 | 303 | 307 | 308) {
+            return Ok(false);
+        }
+        let location = match headers
+            .get(&HeaderName::from("location"))
+            .and_then(|values| values.first())
+        {
+            Some(location) => location,
+            None => return Ok(false),
+        };
+
+        if self.redirects >= Self::MAX_REDIRECTS {
+            return Err(RedirectError::TooManyRedirects(self.redirects));
+        }
+
+        let current = Url::parse(&self.url)
+            .map_err(|err| RedirectError::InvalidLocation(self.url.clone(), err))?;
+        let next = current
+            .join(location)
+            .map_err(|err| RedirectError::InvalidLocation(location.clone(), err))?;
+
+        let method = self.method.to_ascii_uppercase();
+        if (status == 303 && method != "GET" && method != "HEAD")
+            || (matches!(status, 301 | 302) && method == "POST")
+        {
+            self.method = "GET".to_string();
+            self.body = None;
+            Self::remove_headers(&mut self.headers, &BODY_HEADERS);
+        }
+        if next.origin() != current.origin() {
+            Self::remove_headers(&mut self.headers, &CREDENTIAL_HEADERS);
+        }
+
+        self.url = next.into();
+        self.redirects += 1;
+
+        Ok(true)
+    }
+
+    fn remove_headers(headers: &mut HeadersMultiMap, names: &[&str]) {
+        headers.retain(|name, _| {
+            let name = name.lowercase_chars().collect::<String>();
+            !names.contains(&name.as_str())
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sf_core::map_std_impl::HttpPolicy;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeadersMultiMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (HeaderName::from(*name), vec![value.to_string()]))
+            .collect()
+    }
+
+    // headers are compared case-insensitively, but not hashed that way
+    fn has_header(request: &RedirectableRequest, name: &str) -> bool {
+        request
+            .headers
+            .keys()
+            .any(|key| key == &HeaderName::from(name))
+    }
+
+    fn new_request(method: &str) -> RedirectableRequest {
+        RedirectableRequest::new(
+            method,
+            "https://api.example.com/v1/users",
+            &headers(&[
+                ("Authorization", "Bearer secret"),
+                ("Content-Type", "application/json"),
+                ("Accept", "application/json"),
+            ]),
+            &MultiMap::from([("page".to_string(), vec!["2".to_string()])]),
+            Some(b"{}"),
+        )
+    }
+
+    #[test]
+    fn test_not_redirect() {
+        let mut request = new_request("GET");
+
+        assert!(!request.follow(200, &headers(&[])).unwrap());
+        assert!(!request
+            .follow(304, &headers(&[("location", "/other")]))
+            .unwrap());
+        // redirect without location is returned to the map
+        assert!(!request.follow(302, &headers(&[])).unwrap());
+        assert_eq!(request.url, "https://api.example.com/v1/users?page=2");
+    }
+
+    #[test]
+    fn test_follow_same_origin() {
+        let mut request = new_request("PUT");
+
+        assert!(request
+            .follow(307, &headers(&[("location", "accounts")]))
+            .unwrap());
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.url, "https://api.example.com/v1/accounts");
+        assert_eq!(request.body.as_deref(), Some(b"{}".as_slice()));
+        assert_eq!(request.headers.len(), 3);
+
+        assert!(request
+            .follow(303, &headers(&[("location", "/status")]))
+            .unwrap());
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.url, "https://api.example.com/status");
+        assert_eq!(request.body, None);
+        assert!(has_header(&request, "authorization"));
+        assert!(!has_header(&request, "content-type"));
+    }
+
+    #[test]
+    fn test_follow_post() {
+        let mut request = new_request("POST");
+        assert!(request
+            .follow(302, &headers(&[("location", "/created")]))
+            .unwrap());
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.body, None);
+
+        let mut request = new_request("POST");
+        assert!(request
+            .follow(308, &headers(&[("location", "/moved")]))
+            .unwrap());
+        assert_eq!(request.method, "POST");
+        assert!(request.body.is_some());
+    }
+
+    #[test]
+    fn test_follow_cross_origin() {
+        let mut request = new_request("GET");
+
+        assert!(request
+            .follow(
+                301,
+                &headers(&[("location", "http://169.254.169.254/latest/meta-data/")])
+            )
+            .unwrap());
+        assert_eq!(request.url, "http://169.254.169.254/latest/meta-data/");
+        assert!(!has_header(&request, "authorization"));
+        assert!(has_header(&request, "accept"));
+
+        // the location is then rejected by the policy of the perform
+        let policy = HttpPolicy::new(["https://api.example.com"], Vec::new());
+        assert!(policy.check(&request.url, false).is_err());
+    }
+
+    #[test]
+    fn test_redirect_errors() {
+        let mut request = new_request("GET");
+        assert!(matches!(
+            request.follow(302, &headers(&[("location", "http://[::1")])),
+            Err(RedirectError::InvalidLocation(..))
+        ));
+
+        let mut request = new_request("GET");
+        for _ in 0..RedirectableRequest::MAX_REDIRECTS {
+            assert!(request
+                .follow(302, &headers(&[("location", "/again")]))
+                .unwrap());
+        }
+        assert!(matches!(
+            request.follow(302, &headers(&[("location", "/again")])),
+            Err(RedirectError::TooManyRedirects(_))
+        ));
+    }
+}
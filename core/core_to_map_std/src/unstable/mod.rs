@@ -205,6 +205,8 @@ pub enum ErrorCode {
     NetworkInvalidHandle,
     #[serde(rename = "network:invalid_url")]
     NetworkInvalidUrl,
+    #[serde(rename = "network:forbidden")]
+    NetworkForbidden,
 
     #[serde(rename = "security:misssing_secret")]
     SecurityMissingSecret,
@@ -225,6 +227,8 @@ pub enum HttpCallError {
     InvalidSecurityConfiguration(String),
     #[error("Missing secret value: {0}")]
     MissingSecret(String),
+    #[error("Request is not allowed: {0}")]
+    Forbidden(String),
 }
 impl From<HostHttpCallError> for HttpCallError {
     fn from(value: HostHttpCallError) -> Self {
@@ -251,6 +255,8 @@ pub enum HttpCallHeadError {
     ConnectionRefused(String),
     #[error("Host was not found: {0}")]
     HostNotFound(String),
+    #[error("Redirect is not allowed: {0}")]
+    Forbidden(String),
 
     #[error("Handle does not belong to an active http request")]
     InvalidHandle,
@@ -343,6 +349,7 @@ define_exchange_map_to_core! {
                     HttpCallError::Failed(message) => Response::Err { error_code: ErrorCode::NetworkError, message },
                     HttpCallError::MissingSecret(message) => Response::Err { error_code: ErrorCode::SecurityMissingSecret, message },
                     HttpCallError::InvalidSecurityConfiguration(message) => Response::Err { error_code: ErrorCode::SecurityInvalidConfiguration, message },
+                    HttpCallError::Forbidden(message) => Response::Err { error_code: ErrorCode::NetworkForbidden, message },
                 }
             }
         },
@@ -369,6 +376,7 @@ define_exchange_map_to_core! {
                 HttpCallHeadError::Failed(message) => Response::Err { error_code: ErrorCode::NetworkError, message },
                 HttpCallHeadError::ConnectionRefused(message) => Response::Err { error_code: ErrorCode::ConnectionRefused, message },
                 HttpCallHeadError::HostNotFound(message) => Response::Err { error_code: ErrorCode::HostNotFound, message },
+                HttpCallHeadError::Forbidden(message) => Response::Err { error_code: ErrorCode::NetworkForbidden, message },
             }
         },
         // input and output
@@ -56,14 +56,55 @@ impl From<sf_std::unstable::provider::HttpScheme> for HttpScheme {
     }
 }
 
+/// Value of a security field, either given directly or referencing a secret by name.
+///
+/// Secrets are retrieved from the host only when a request needs them, so they don't have to be part of the perform input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityField {
+    Plain(String),
+    Secret(String),
+}
+impl SecurityField {
+    /// Key of the object referencing a secret, e.g. `{ "$secret": "twilio/auth" }`.
+    pub const SECRET_KEY: &'static str = "$secret";
+
+    fn from_host_value(value: Option<&HostValue>, field: &str) -> Self {
+        match value {
+            Some(HostValue::String(value)) => Self::Plain(value.to_owned()),
+            Some(HostValue::Object(obj)) => match obj.get(Self::SECRET_KEY) {
+                Some(HostValue::String(name)) => Self::Secret(name.to_owned()),
+                _ => unreachable!(
+                    "Schema validation ensures secret reference in {} field has a String name.",
+                    field
+                ),
+            },
+            _ => unreachable!(
+                "Schema validation ensures there is String or secret reference value for {} field.",
+                field
+            ),
+        }
+    }
+
+    /// Returns the value of the field, retrieving it with `get_secret` if it references a secret.
+    pub fn reveal(
+        &self,
+        get_secret: &mut impl FnMut(&str) -> Result<String, HttpCallError>,
+    ) -> Result<String, HttpCallError> {
+        match self {
+            Self::Plain(value) => Ok(value.clone()),
+            Self::Secret(name) => get_secret(name),
+        }
+    }
+}
+
 pub enum HttpSecurity {
     Basic {
-        username: String,
-        password: String,
+        username: SecurityField,
+        password: SecurityField,
     },
     Bearer {
         bearer_format: Option<String>,
-        token: String,
+        token: SecurityField,
     },
 }
 
@@ -71,7 +112,7 @@ pub enum Security {
     ApiKey {
         r#in: ApiKeyPlacement,
         name: String,
-        apikey: String,
+        apikey: SecurityField,
         body_type: Option<ApiKeyBodyType>,
     },
     Http(HttpSecurity),
@@ -85,9 +126,16 @@ pub enum SecurityMapValue {
 pub type SecurityMap = HashMap<SecurityMapKey, SecurityMapValue>;
 
 pub enum SecurityValue {
-    ApiKey { apikey: String },
-    Basic { username: String, password: String },
-    Bearer { token: String },
+    ApiKey {
+        apikey: SecurityField,
+    },
+    Basic {
+        username: SecurityField,
+        password: SecurityField,
+    },
+    Bearer {
+        token: SecurityField,
+    },
 }
 pub type SecurityValuesMap = HashMap<String, SecurityValue>;
 
@@ -146,44 +194,22 @@ pub fn prepare_security_map(
 
                     if obj.contains_key("apikey") {
                         security_value = SecurityValue::ApiKey {
-                            apikey: match obj.get("apikey") {
-                                Some(HostValue::String(str)) => str.to_owned(),
-                                _ => {
-                                    unreachable!(
-                                        "Schema validation should ensure there is String value for apikey field."
-                                    );
-                                }
-                            },
+                            apikey: SecurityField::from_host_value(obj.get("apikey"), "apikey"),
                         }
                     } else if obj.contains_key("username") {
                         security_value = SecurityValue::Basic {
-                            username: match obj.get("username") {
-                                Some(HostValue::String(str)) => str.to_owned(),
-                                _ => {
-                                    unreachable!(
-                                        "Schema validation ensures there is String value for username field."
-                                    );
-                                }
-                            },
-                            password: match obj.get("password") {
-                                Some(HostValue::String(str)) => str.to_owned(),
-                                _ => {
-                                    unreachable!(
-                                        "Schema validation ensures there is String value for password field."
-                                    );
-                                }
-                            },
+                            username: SecurityField::from_host_value(
+                                obj.get("username"),
+                                "username",
+                            ),
+                            password: SecurityField::from_host_value(
+                                obj.get("password"),
+                                "password",
+                            ),
                         }
                     } else if obj.contains_key("token") {
                         security_value = SecurityValue::Bearer {
-                            token: match obj.get("token") {
-                                Some(HostValue::String(str)) => str.to_owned(),
-                                _ => {
-                                    unreachable!(
-                                        "Schema validation ensures there is String value for token field."
-                                    );
-                                }
-                            },
+                            token: SecurityField::from_host_value(obj.get("token"), "token"),
                         }
                     } else {
                         unreachable!("Schema validation ensures value is one of the types above.");
@@ -301,7 +327,7 @@ pub fn prepare_security_map(
                 security_map.insert(
                     id.to_string(),
                     SecurityMapValue::Security(Security::Http(HttpSecurity::Bearer {
-                        token: token.to_string(),
+                        token: token.to_owned(),
                         bearer_format: bearer_format.to_owned(),
                     })),
                 );
@@ -316,9 +342,11 @@ pub fn prepare_security_map(
     Ok(security_map)
 }
 
+/// Applies security required by the request, retrieving referenced secrets with `get_secret`.
 pub fn resolve_security(
     security_map: &SecurityMap,
     params: &mut HttpRequest,
+    mut get_secret: impl FnMut(&str) -> Result<String, HttpCallError>,
 ) -> Result<(), HttpCallError> {
     let security = match params.security {
         None => return Ok(()),
@@ -343,6 +371,8 @@ pub fn resolve_security(
             username,
             password,
         }))) => {
+            let username = username.reveal(&mut get_secret)?;
+            let password = password.reveal(&mut get_secret)?;
             let encoded_crendentials = base64::engine::general_purpose::STANDARD
                 .encode(format!("{}:{}", username, password).as_bytes());
             let basic_auth = vec![format!("Basic {}", encoded_crendentials)];
@@ -355,7 +385,7 @@ pub fn resolve_security(
             bearer_format: _,
             token,
         }))) => {
-            let digest_auth = vec![format!("Bearer {}", token)];
+            let digest_auth = vec![format!("Bearer {}", token.reveal(&mut get_secret)?)];
 
             params
                 .headers
@@ -368,17 +398,20 @@ pub fn resolve_security(
             body_type,
         })) => match (r#in, body_type) {
             (ApiKeyPlacement::Header, _) => {
-                params
-                    .headers
-                    .insert(HeaderName::from(name.as_str()), vec![apikey.to_string()]);
+                params.headers.insert(
+                    HeaderName::from(name.as_str()),
+                    vec![apikey.reveal(&mut get_secret)?],
+                );
             }
             (ApiKeyPlacement::Path, _) => {
-                params.url = params.url.replace(&format!("{{{}}}", name), apikey);
+                params.url = params
+                    .url
+                    .replace(&format!("{{{}}}", name), &apikey.reveal(&mut get_secret)?);
             }
             (ApiKeyPlacement::Query, _) => {
                 params
                     .query
-                    .insert(name.to_string(), vec![apikey.to_string()]);
+                    .insert(name.to_string(), vec![apikey.reveal(&mut get_secret)?]);
             }
             (ApiKeyPlacement::Body, Some(ApiKeyBodyType::Json)) => {
                 if let Some(body) = &params.body {
@@ -419,7 +452,8 @@ pub fn resolve_security(
                         key_idx += 1;
                     }
 
-                    nested[keys[key_idx]] = serde_json::Value::from(apikey.to_string());
+                    nested[keys[key_idx]] =
+                        serde_json::Value::from(apikey.reveal(&mut get_secret)?);
 
                     params.body = Some(serde_json::to_vec(&body).map_err(|e| {
                         HttpCallError::InvalidSecurityConfiguration(format!(
@@ -457,3 +491,82 @@ pub fn prepare_provider_parameters(provider_json: &ProviderJson) -> MapValueObje
             }))
         });
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn test_secret_references() {
+        let provider_json: ProviderJson = serde_json::from_value(serde_json::json!({
+            "name": "example",
+            "services": [{ "id": "default", "baseUrl": "https://example.com" }],
+            "defaultService": "default",
+            "securitySchemes": [
+                { "id": "apikey_query", "type": "apiKey", "in": "query", "name": "key" },
+                { "id": "basic_auth", "type": "http", "scheme": "basic" }
+            ]
+        }))
+        .unwrap();
+        let secret = |name: &str| {
+            HostValue::Object(BTreeMap::from([(
+                SecurityField::SECRET_KEY.to_string(),
+                HostValue::String(name.to_string()),
+            )]))
+        };
+        let map_security = HostValue::Object(BTreeMap::from([
+            (
+                "apikey_query".to_string(),
+                HostValue::Object(BTreeMap::from([(
+                    "apikey".to_string(),
+                    secret("example/key"),
+                )])),
+            ),
+            (
+                "basic_auth".to_string(),
+                HostValue::Object(BTreeMap::from([
+                    (
+                        "username".to_string(),
+                        HostValue::String("user".to_string()),
+                    ),
+                    ("password".to_string(), secret("example/password")),
+                ])),
+            ),
+        ]));
+        let security_map = prepare_security_map(&provider_json, &map_security).unwrap();
+
+        let request = |security: &str| HttpRequest {
+            method: "GET".to_string(),
+            url: "https://example.com".to_string(),
+            headers: Default::default(),
+            query: Default::default(),
+            body: None,
+            security: Some(security.to_string()),
+        };
+        let mut requested = Vec::new();
+        let mut get_secret = |name: &str| {
+            requested.push(name.to_string());
+            match name {
+                "example/key" => Ok("secret key".to_string()),
+                _ => Err(HttpCallError::MissingSecret(name.to_string())),
+            }
+        };
+
+        let mut params = request("apikey_query");
+        resolve_security(&security_map, &mut params, &mut get_secret).unwrap();
+        assert_eq!(params.query["key"], vec!["secret key".to_string()]);
+
+        let mut params = request("basic_auth");
+        assert!(matches!(
+            resolve_security(&security_map, &mut params, &mut get_secret),
+            Err(HttpCallError::MissingSecret(name)) if name == "example/password"
+        ));
+        assert!(!params
+            .headers
+            .contains_key(&HeaderName::from("Authorization")));
+
+        assert_eq!(requested, vec!["example/key", "example/password"]);
+    }
+}
@@ -1,47 +1,180 @@
+use std::fmt::Write;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use regex::Regex;
-use sf_std::unstable::provider::ProviderJson;
+use sf_std::unstable::{
+    exception::{PerformException, PerformExceptionErrorCode},
+    provider::ProviderJson,
+};
 
 use super::{MapValue, MapValueObject};
 
-pub fn prepare_services_map(provider_json: &ProviderJson, parameters: &MapValueObject) -> MapValue {
+/// Characters which are encoded when a parameter value is substituted into the query or fragment of a service url.
+///
+/// Everything except the unreserved characters from RFC 3986.
+const PARAMETER_VALUE_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+/// Characters which are encoded when a parameter value is substituted into the path of a service url.
+///
+/// Same as [PARAMETER_VALUE_ENCODE_SET] but keeps `/` and `:` so that a parameter can hold a path prefix.
+const PATH_PARAMETER_VALUE_ENCODE_SET: &AsciiSet =
+    &PARAMETER_VALUE_ENCODE_SET.remove(b'/').remove(b':');
+
+#[derive(Debug, thiserror::Error)]
+pub enum PrepareServicesMapError {
+    #[error("Integration parameters are misconfigured:\n{}", ServiceParameterError::format_errors(.0.as_slice()))]
+    ParametersMisconfigured(Vec<ServiceParameterError>),
+}
+impl From<PrepareServicesMapError> for PerformException {
+    fn from(value: PrepareServicesMapError) -> Self {
+        PerformException {
+            error_code: PerformExceptionErrorCode::ParametersFormatError,
+            message: value.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ServiceParameterError {
+    pub service: String,
+    pub parameter: String,
+    pub reason: String,
+}
+impl ServiceParameterError {
+    pub fn format_errors(errors: &[ServiceParameterError]) -> String {
+        let mut res = String::new();
+
+        for err in errors {
+            writeln!(
+                &mut res,
+                "Parameter {} used in service {} {}",
+                err.parameter, err.service, err.reason
+            )
+            .unwrap();
+        }
+
+        res
+    }
+}
+
+pub fn prepare_services_map(
+    provider_json: &ProviderJson,
+    parameters: &MapValueObject,
+) -> Result<MapValue, PrepareServicesMapError> {
     let mut services_map = MapValueObject::new();
+    let mut errors = Vec::new();
 
     for service in &provider_json.services {
-        let service_url = replace_parameters(service.base_url.clone(), parameters);
-        services_map.insert(service.id.to_string(), MapValue::String(service_url));
+        match replace_parameters(&service.base_url, parameters) {
+            Ok(service_url) => {
+                services_map.insert(service.id.to_string(), MapValue::String(service_url));
+            }
+            Err(invalid) => errors.extend(invalid.into_iter().map(|(parameter, reason)| {
+                ServiceParameterError {
+                    service: service.id.clone(),
+                    parameter,
+                    reason,
+                }
+            })),
+        }
     }
 
-    MapValue::Object(services_map)
+    if !errors.is_empty() {
+        return Err(PrepareServicesMapError::ParametersMisconfigured(errors));
+    }
+
+    Ok(MapValue::Object(services_map))
 }
 
-fn replace_parameters(url: String, parameters: &MapValueObject) -> String {
+/// Replaces `{PARAM}` placeholders in `url` with parameter values, encoded depending on the part of the url they are in.
+///
+/// A placeholder holding the whole base url, that is at the start of `url` and followed by nothing or a path, is substituted as it is.
+/// Values in the scheme and authority are substituted as they are but may only contain unreserved characters and `:`,
+/// so that they can't change the host. Values in the path are url-encoded except for `/` and `:`,
+/// values in the query or fragment are fully url-encoded.
+///
+/// Returns the name of each placeholder which couldn't be replaced together with the reason.
+fn replace_parameters(
+    url: &str,
+    parameters: &MapValueObject,
+) -> Result<String, Vec<(String, String)>> {
     let re = Regex::new(r"\{\s*([^}\s]*)\s*\}").unwrap();
 
-    let mut result_url = url.clone();
+    let mut result_url = String::with_capacity(url.len());
+    let mut errors = Vec::new();
 
-    for cap in re.captures_iter(url.as_str()) {
+    let mut last_end = 0;
+    for cap in re.captures_iter(url) {
+        let placeholder = cap.get(0).unwrap();
         let param_name = &cap[1];
-        let param_value = parameters.get(param_name).unwrap();
 
-        match param_value {
-            MapValue::String(val) => {
-                result_url = result_url.as_str().replace(&cap[0], val);
+        result_url.push_str(&url[last_end..placeholder.start()]);
+        last_end = placeholder.end();
+
+        match parameters.get(param_name) {
+            Some(MapValue::String(val)) => {
+                let prefix = &url[..placeholder.start()];
+                if prefix.contains(['?', '#']) {
+                    result_url.extend(utf8_percent_encode(val, PARAMETER_VALUE_ENCODE_SET));
+                } else if is_in_path(prefix) {
+                    result_url.extend(utf8_percent_encode(val, PATH_PARAMETER_VALUE_ENCODE_SET));
+                } else if prefix.is_empty()
+                    && (placeholder.end() == url.len() || url[placeholder.end()..].starts_with('/'))
+                {
+                    result_url.push_str(val);
+                } else if is_authority_safe(val) {
+                    result_url.push_str(val);
+                } else {
+                    errors.push((
+                        param_name.to_string(),
+                        "contains characters not allowed in the scheme or host".to_string(),
+                    ));
+                }
             }
-            _ => panic!("Invalid parameter value"),
+            Some(_) => errors.push((param_name.to_string(), "must be a string".to_string())),
+            None => errors.push((param_name.to_string(), "is missing".to_string())),
         }
     }
+    result_url.push_str(&url[last_end..]);
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(result_url)
+}
+
+/// Whether `value` only contains unreserved characters from RFC 3986 and `:` for a port.
+///
+/// Other characters, such as `/`, `@` or `#`, could change the host of the url.
+fn is_authority_safe(value: &str) -> bool {
+    value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~' | ':'))
+}
 
-    result_url
+/// Whether a placeholder following `prefix` is in the path of the url, that is after the authority.
+fn is_in_path(prefix: &str) -> bool {
+    match prefix.split_once("://") {
+        Some((_, rest)) => rest.contains('/'),
+        // a prefix without a scheme is either a placeholder holding the scheme or a relative url
+        None => prefix.contains('/'),
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use sf_std::unstable::provider::ProviderService;
+
     use super::*;
 
     #[test]
     fn test_params_replacing() {
         let result_url = replace_parameters(
-            "http://{ONE}.localhost/{ TWO}/{THREE }/{ FOUR }".to_string(),
+            "http://{ONE}.localhost/{ TWO}/{THREE }/{ FOUR }",
             &MapValueObject::from([
                 ("ONE".to_string(), MapValue::String("first".to_string())),
                 ("TWO".to_string(), MapValue::String("second".to_string())),
@@ -52,7 +185,143 @@ mod test {
 
         assert_eq!(
             result_url,
-            "http://first.localhost/second/third/fourth".to_string()
+            Ok("http://first.localhost/second/third/fourth".to_string())
+        );
+    }
+
+    #[test]
+    fn test_params_encoding() {
+        let result_url = replace_parameters(
+            "http://localhost/{PATH}?q={QUERY}#{FRAGMENT}",
+            &MapValueObject::from([
+                ("PATH".to_string(), MapValue::String("a/b c?".to_string())),
+                ("QUERY".to_string(), MapValue::String("x&y=z~/".to_string())),
+                ("FRAGMENT".to_string(), MapValue::String("a b".to_string())),
+            ]),
+        );
+
+        assert_eq!(
+            result_url,
+            Ok("http://localhost/a/b%20c%3F?q=x%26y%3Dz~%2F#a%20b".to_string())
+        );
+    }
+
+    #[test]
+    fn test_params_base_url() {
+        let parameters = MapValueObject::from([
+            (
+                "BASE_URL".to_string(),
+                MapValue::String("https://api.example.com:8443/v2".to_string()),
+            ),
+            (
+                "HOST".to_string(),
+                MapValue::String("localhost:8080".to_string()),
+            ),
+            (
+                "PREFIX".to_string(),
+                MapValue::String("tenants/acme corp".to_string()),
+            ),
+        ]);
+
+        assert_eq!(
+            replace_parameters("{BASE_URL}", &parameters),
+            Ok("https://api.example.com:8443/v2".to_string())
+        );
+        assert_eq!(
+            replace_parameters("{BASE_URL}/users", &parameters),
+            Ok("https://api.example.com:8443/v2/users".to_string())
+        );
+        assert_eq!(
+            replace_parameters("http://{HOST}/api", &parameters),
+            Ok("http://localhost:8080/api".to_string())
+        );
+        assert_eq!(
+            replace_parameters("https://api.example.com/{PREFIX}/users", &parameters),
+            Ok("https://api.example.com/tenants/acme%20corp/users".to_string())
+        );
+    }
+
+    #[test]
+    fn test_params_host_injection() {
+        for region in ["evil.com/", "x@evil.com", "evil.com#", "evil.com?"] {
+            let parameters = MapValueObject::from([(
+                "REGION".to_string(),
+                MapValue::String(region.to_string()),
+            )]);
+
+            assert_eq!(
+                replace_parameters("https://{REGION}.example.com/api", &parameters),
+                Err(vec![(
+                    "REGION".to_string(),
+                    "contains characters not allowed in the scheme or host".to_string()
+                )]),
+                "{} should be rejected",
+                region
+            );
+        }
+
+        // a whole base url can't be followed by more of the host
+        let parameters = MapValueObject::from([(
+            "BASE".to_string(),
+            MapValue::String("https://evil.com/".to_string()),
+        )]);
+        assert!(replace_parameters("{BASE}.example.com", &parameters).is_err());
+
+        let parameters = MapValueObject::from([(
+            "REGION".to_string(),
+            MapValue::String("eu-west-1".to_string()),
+        )]);
+        assert_eq!(
+            replace_parameters("https://{REGION}.example.com/api", &parameters),
+            Ok("https://eu-west-1.example.com/api".to_string())
+        );
+    }
+
+    #[test]
+    fn test_params_errors() {
+        let provider_json = ProviderJson {
+            name: "test".to_string(),
+            services: vec![
+                ProviderService {
+                    id: "default".to_string(),
+                    base_url: "http://{ONE}.localhost/{TWO}".to_string(),
+                    propagate_trace_context: None,
+                },
+                ProviderService {
+                    id: "other".to_string(),
+                    base_url: "http://localhost/{THREE}".to_string(),
+                    propagate_trace_context: None,
+                },
+                ProviderService {
+                    id: "valid".to_string(),
+                    base_url: "http://localhost/{FOUR}".to_string(),
+                    propagate_trace_context: None,
+                },
+            ],
+            security_schemes: None,
+            parameters: None,
+            default_service: Some("default".to_string()),
+        };
+        let parameters = MapValueObject::from([
+            ("TWO".to_string(), MapValue::Number(2.into())),
+            ("FOUR".to_string(), MapValue::String("four".to_string())),
+        ]);
+
+        let result = prepare_services_map(&provider_json, &parameters);
+        let errors = match result {
+            Err(PrepareServicesMapError::ParametersMisconfigured(errors)) => errors,
+            Ok(_) => panic!("expected error"),
+        };
+        assert_eq!(
+            errors
+                .iter()
+                .map(|e| (e.service.as_str(), e.parameter.as_str(), e.reason.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("default", "ONE", "is missing"),
+                ("default", "TWO", "must be a string"),
+                ("other", "THREE", "is missing"),
+            ]
         );
     }
 }
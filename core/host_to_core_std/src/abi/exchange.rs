@@ -7,12 +7,47 @@ use thiserror::Error;
 
 use super::{AbiResult, AbiResultRepr, Handle, Ptr, Size};
 
+/// Error of encoding or decoding a message in either [MessageEncoding].
 #[derive(Debug, Error)]
-pub enum JsonMessageError {
+pub enum MessageError {
     #[error("Failed to serialize message: {0}")]
     SerializeFailed(serde_json::Error),
     #[error("Failed to deserialize message: {0}")]
     DeserializeFailed(serde_json::Error),
+    #[error("Failed to encode MessagePack message: {0}")]
+    EncodeFailed(rmp_serde::encode::Error),
+    #[error("Failed to decode MessagePack message: {0}")]
+    DecodeFailed(rmp_serde::decode::Error),
+}
+#[deprecated(note = "messages are not only JSON, use `MessageError`")]
+pub type JsonMessageError = MessageError;
+
+/// Encoding of messages sent over [MessageExchange].
+///
+/// Messages are JSON unless both sides advertise the `message_pack` feature during the `host-info` exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageEncoding {
+    #[default]
+    Json,
+    /// MessagePack with structs encoded as maps, bytes are encoded natively.
+    MessagePack,
+}
+impl MessageEncoding {
+    pub fn encode<M: Serialize>(self, message: &M) -> Result<Vec<u8>, MessageError> {
+        match self {
+            Self::Json => serde_json::to_vec(message).map_err(MessageError::SerializeFailed),
+            Self::MessagePack => {
+                rmp_serde::to_vec_named(message).map_err(MessageError::EncodeFailed)
+            }
+        }
+    }
+
+    pub fn decode<R: DeserializeOwned>(self, message: &[u8]) -> Result<R, MessageError> {
+        match self {
+            Self::Json => serde_json::from_slice(message).map_err(MessageError::DeserializeFailed),
+            Self::MessagePack => rmp_serde::from_slice(message).map_err(MessageError::DecodeFailed),
+        }
+    }
 }
 
 /// Implementation of a channel over which messages can be exchanged.
@@ -20,15 +55,40 @@ pub trait MessageExchange {
     /// Invoke exchange by sending `message` and retrieving response.
     fn invoke(&self, message: &[u8]) -> Vec<u8>;
 
+    /// Encoding used by [invoke_message](Self::invoke_message).
+    fn message_encoding(&self) -> MessageEncoding {
+        MessageEncoding::Json
+    }
+
+    /// Sends a message using [invoke](Self::invoke) encoded according to [message_encoding](Self::message_encoding).
+    fn invoke_message<M: Serialize, R: DeserializeOwned>(
+        &self,
+        message: &M,
+    ) -> Result<R, MessageError> {
+        let encoding = self.message_encoding();
+        if encoding == MessageEncoding::Json {
+            return self.invoke_json(message);
+        }
+
+        let _span = tracing::trace_span!("host/MessageExchange::invoke_message").entered();
+
+        let message = encoding.encode(message)?;
+        tracing::trace!(?encoding, request_len = message.len());
+
+        let response = self.invoke(message.as_slice());
+        tracing::trace!(?encoding, response_len = response.len());
+
+        encoding.decode(response.as_slice())
+    }
+
     /// Sends a message using [invoke](Self::invoke) by serializing and deserializing JSON.
     fn invoke_json<M: Serialize, R: DeserializeOwned>(
         &self,
         message: &M,
-    ) -> Result<R, JsonMessageError> {
+    ) -> Result<R, MessageError> {
         let _span = tracing::trace_span!("host/MessageExchange::invoke_json").entered();
 
-        let json_message =
-            serde_json::to_string(message).map_err(JsonMessageError::SerializeFailed)?;
+        let json_message = serde_json::to_string(message).map_err(MessageError::SerializeFailed)?;
 
         tracing::trace!(request = %json_message);
 
@@ -36,8 +96,8 @@ pub trait MessageExchange {
 
         tracing::trace!(response = %std::str::from_utf8(response.as_slice()).unwrap());
 
-        let response = serde_json::from_slice(response.as_slice())
-            .map_err(JsonMessageError::DeserializeFailed)?;
+        let response =
+            serde_json::from_slice(response.as_slice()).map_err(MessageError::DeserializeFailed)?;
 
         Ok(response)
     }
@@ -46,6 +106,10 @@ impl<E: MessageExchange + ?Sized> MessageExchange for &E {
     fn invoke(&self, message: &[u8]) -> Vec<u8> {
         (**self).invoke(message)
     }
+
+    fn message_encoding(&self) -> MessageEncoding {
+        (**self).message_encoding()
+    }
 }
 /// Static message exchange is a trait for `MessageExchange`s which can be accessed in static context.
 pub trait StaticMessageExchange: MessageExchange + Sized {
@@ -416,6 +480,39 @@ mod test {
         assert_eq!(response.f2, "true");
     }
 
+    struct MessagePackEcho;
+    impl MessageExchange for MessagePackEcho {
+        fn invoke(&self, message: &[u8]) -> Vec<u8> {
+            // not valid JSON, so the message must have been sent as MessagePack
+            assert!(serde_json::from_slice::<serde_json::Value>(message).is_err());
+            message.to_vec()
+        }
+
+        fn message_encoding(&self) -> MessageEncoding {
+            MessageEncoding::MessagePack
+        }
+    }
+
+    #[test]
+    fn test_invoke_message_message_pack() {
+        let message = TestMsg {
+            f1: 1,
+            f2: "true".to_string(),
+        };
+        let response = MessagePackEcho
+            .invoke_message::<TestMsg, TestMsg>(&message)
+            .unwrap();
+
+        assert_eq!(response.f1, 1);
+        assert_eq!(response.f2, "true");
+
+        // JSON exchanges are unaffected
+        let response = MESSAGE_FN
+            .invoke_message::<TestMsg, TestMsg>(&message)
+            .unwrap();
+        assert_eq!(response.f2, "true");
+    }
+
     #[test]
     fn test_invoke_message_roundtrip_toobig() {
         let long_string = {
@@ -13,7 +13,7 @@
 //! This handle can be used to retrieve the message with a second call. By selecting a reasonable buffer size, the caller can avoid
 //! most secondary calls.
 //!
-//! The format of messages is UTF-8 JSON.
+//! The format of messages is UTF-8 JSON, unless MessagePack is negotiated during setup (see [MessageEncoding]).
 //!
 //! ## Streams
 //!
@@ -24,11 +24,13 @@ mod bits;
 mod exchange;
 mod result;
 
+#[allow(deprecated)]
+pub use self::exchange::JsonMessageError;
 pub use self::{
     bits::{AbiPair, AbiPairRepr, Handle, Ptr, Size},
     exchange::{
-        JsonMessageError, MessageExchange, MessageExchangeFfiFn, StaticMessageExchange,
-        StaticStreamExchange, StreamExchange, StreamExchangeFfiFn,
+        MessageEncoding, MessageError, MessageExchange, MessageExchangeFfiFn,
+        StaticMessageExchange, StaticStreamExchange, StreamExchange, StreamExchangeFfiFn,
     },
     result::{err_from_wasi_errno, AbiResult, AbiResultRepr},
 };
@@ -8,7 +8,7 @@
 /// the struct and its fields. Defines a response enum with newtype or struct variants and kebab-case kind.
 ///
 /// Defines a `new` method which automatically fills `kind` field.
-/// Implements trait `MessageExchange`.
+/// Defines a `send_json_in` method which sends the message in the encoding of the given `MessageExchange`, JSON by default.
 ///
 /// For example, the following defines `InMessage` with kind `my-kind` and two fields and defines a response `OutMessage` enum with three
 /// variants with kinds `ok`, `try-again`, and `err`, each with different number of fields.
@@ -76,8 +76,8 @@ macro_rules! define_exchange_core_to_host {
                 }
             }
 
-            pub fn send_json_in<E: $crate::abi::MessageExchange>(&self, message_exchange: E) -> Result<$response_name, $crate::abi::JsonMessageError> {
-                message_exchange.invoke_json(self)
+            pub fn send_json_in<E: $crate::abi::MessageExchange>(&self, message_exchange: E) -> Result<$response_name, $crate::abi::MessageError> {
+                message_exchange.invoke_message(self)
             }
         }
 
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+
+use super::ErrorCode;
+use crate::abi::{MessageError, MessageExchange};
+
+define_exchange_core_to_host! {
+    struct ConfigureInputRequest {
+        kind: "configure-input"
+    } -> enum ConfigureInputResponse {
+        Ok {
+            /// Settings to change, settings which are not present are left unchanged.
+            configuration: ConfigurationUpdate,
+            /// Whether documents cached so far are kept, otherwise all document caches are cleared.
+            keep_caches: bool
+        },
+        Err {
+            error_code: ErrorCode,
+            message: String,
+        }
+    }
+}
+
+define_exchange_core_to_host! {
+    struct ConfigureOutputRequest<'a> {
+        kind: "configure-output",
+        /// Why the configuration was rejected, it is left unchanged in that case.
+        error: Option<&'a str>
+    } -> enum ConfigureOutputResponse {
+        Ok,
+        Err {
+            error_code: ErrorCode,
+            message: String
+        }
+    }
+}
+
+/// Settings of core which can be changed after setup.
+///
+/// Names and units are the same as in `CoreConfiguration`, durations are in seconds. Unknown settings are rejected,
+/// settings which can only be set through the environment before setup are unknown here.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigurationUpdate {
+    /// Duration to cache documents for, in seconds.
+    #[serde(default)]
+    pub cache_duration: Option<u64>,
+    /// URL to document registry from which to download documents.
+    #[serde(default)]
+    pub registry_url: Option<String>,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub user_log: Option<bool>,
+    #[serde(default)]
+    pub user_log_http_body_max_size: Option<usize>,
+    /// Filter directives of the developer log, in the same format as `ONESDK_DEV_LOG`.
+    #[serde(default)]
+    pub developer_log: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TakeConfigureInputError {
+    #[error("Invalid input format: {0}")]
+    InvalidFormat(MessageError),
+    #[error("Unknown configure input error: {0}")]
+    Unknown(String),
+}
+
+pub struct ConfigureInput {
+    pub configuration: ConfigurationUpdate,
+    pub keep_caches: bool,
+}
+impl ConfigureInput {
+    pub fn take_in<E: MessageExchange>(
+        message_exchange: E,
+    ) -> Result<ConfigureInput, TakeConfigureInputError> {
+        let response = match ConfigureInputRequest::new().send_json_in(message_exchange) {
+            Err(err) => {
+                tracing::error!("Failed to receive configure_input response: {:#}", err);
+                return Err(TakeConfigureInputError::InvalidFormat(err));
+            }
+            Ok(r) => r,
+        };
+
+        match response {
+            ConfigureInputResponse::Ok {
+                configuration,
+                keep_caches,
+            } => Ok(ConfigureInput {
+                configuration,
+                keep_caches,
+            }),
+            ConfigureInputResponse::Err {
+                error_code,
+                message,
+            } => Err(TakeConfigureInputError::Unknown(format!(
+                "{:?} {}",
+                error_code, message
+            ))),
+        }
+    }
+}
+
+pub fn set_configure_output_in<E: MessageExchange>(error: Option<&str>, message_exchange: E) {
+    let response = ConfigureOutputRequest::new(error)
+        .send_json_in(message_exchange)
+        .unwrap();
+
+    match response {
+        ConfigureOutputResponse::Ok => (),
+        ConfigureOutputResponse::Err {
+            error_code,
+            message,
+        } => panic!("configure-output error: {:?}: {}", error_code, message),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_message_out_configure_input() {
+        let actual = json!({
+            "kind": "ok",
+            "configuration": { "developer_log": "debug", "user_log": true },
+            "keep_caches": true
+        });
+
+        match serde_json::from_value::<ConfigureInputResponse>(actual).unwrap() {
+            ConfigureInputResponse::Ok {
+                configuration,
+                keep_caches,
+            } => {
+                assert_eq!(
+                    configuration,
+                    ConfigurationUpdate {
+                        user_log: Some(true),
+                        developer_log: Some("debug".to_string()),
+                        ..Default::default()
+                    }
+                );
+                assert!(keep_caches);
+            }
+            ConfigureInputResponse::Err { .. } => panic!("Expected ok"),
+        }
+    }
+
+    #[test]
+    fn test_message_out_configure_input_unknown_setting() {
+        let actual = json!({
+            "kind": "ok",
+            "configuration": { "metrics_buffer_size": 1024 },
+            "keep_caches": true
+        });
+
+        assert!(serde_json::from_value::<ConfigureInputResponse>(actual).is_err());
+    }
+
+    #[test]
+    fn test_message_in_configure_output() {
+        assert_eq!(
+            serde_json::to_value(ConfigureOutputRequest::new(Some("Invalid registry url")))
+                .unwrap(),
+            json!({
+                "kind": "configure-output",
+                "error": "Invalid registry url"
+            })
+        );
+    }
+}
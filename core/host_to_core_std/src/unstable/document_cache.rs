@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+use super::ErrorCode;
+use crate::abi::{MessageError, MessageExchange};
+
+define_exchange_core_to_host! {
+    struct PreloadInputRequest {
+        kind: "preload-input"
+    } -> enum PreloadInputResponse {
+        Ok {
+            /// Urls of profiles to load into the profile cache.
+            #[serde(default)]
+            profile_urls: Vec<String>,
+            /// Urls of providers to load into the provider cache.
+            #[serde(default)]
+            provider_urls: Vec<String>,
+            /// Urls of maps to load into the map cache.
+            #[serde(default)]
+            map_urls: Vec<String>
+        },
+        Err {
+            error_code: ErrorCode,
+            message: String,
+        }
+    }
+}
+
+define_exchange_core_to_host! {
+    struct PreloadOutputRequest {
+        kind: "preload-output",
+        /// Outcome of each preloaded document, profiles first, then providers and maps.
+        documents: Vec<PreloadedDocument>
+    } -> enum PreloadOutputResponse {
+        Ok,
+        Err {
+            error_code: ErrorCode,
+            message: String
+        }
+    }
+}
+
+define_exchange_core_to_host! {
+    struct InvalidateCacheInputRequest {
+        kind: "invalidate-cache-input"
+    } -> enum InvalidateCacheInputResponse {
+        Ok {
+            /// Url of the document to drop from all caches, all documents are dropped if not present.
+            #[serde(default)]
+            url: Option<String>
+        },
+        Err {
+            error_code: ErrorCode,
+            message: String,
+        }
+    }
+}
+
+define_exchange_core_to_host! {
+    struct ListCacheOutputRequest {
+        kind: "list-cache-output",
+        /// Documents which are currently cached.
+        documents: Vec<CachedDocument>
+    } -> enum ListCacheOutputResponse {
+        Ok,
+        Err {
+            error_code: ErrorCode,
+            message: String
+        }
+    }
+}
+
+/// Cache a document belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    Profile,
+    Provider,
+    Map,
+}
+
+/// Outcome of loading one document during preload.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PreloadedDocument {
+    pub kind: DocumentKind,
+    pub url: String,
+    /// Hash of the loaded document, see [CachedDocument::content_hash].
+    pub content_hash: Option<String>,
+    /// Why the document could not be loaded.
+    pub error: Option<String>,
+}
+
+/// Document stored in one of the document caches.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedDocument {
+    pub kind: DocumentKind,
+    pub url: String,
+    /// Hash of the document content, the same one reported in metrics.
+    pub content_hash: String,
+    /// Time since the document was loaded, in milliseconds.
+    pub age: u64,
+    /// Whether the document is older than the cache duration and will be loaded again when next used.
+    pub expired: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TakeDocumentCacheInputError {
+    #[error("Invalid input format: {0}")]
+    InvalidFormat(MessageError),
+    #[error("Unknown document cache input error: {0}")]
+    Unknown(String),
+}
+
+pub struct PreloadInput {
+    pub profile_urls: Vec<String>,
+    pub provider_urls: Vec<String>,
+    pub map_urls: Vec<String>,
+}
+impl PreloadInput {
+    pub fn take_in<E: MessageExchange>(
+        message_exchange: E,
+    ) -> Result<PreloadInput, TakeDocumentCacheInputError> {
+        let response = match PreloadInputRequest::new().send_json_in(message_exchange) {
+            Err(err) => {
+                tracing::error!("Failed to receive preload_input response: {:#}", err);
+                return Err(TakeDocumentCacheInputError::InvalidFormat(err));
+            }
+            Ok(r) => r,
+        };
+
+        match response {
+            PreloadInputResponse::Ok {
+                profile_urls,
+                provider_urls,
+                map_urls,
+            } => Ok(PreloadInput {
+                profile_urls,
+                provider_urls,
+                map_urls,
+            }),
+            PreloadInputResponse::Err {
+                error_code,
+                message,
+            } => Err(TakeDocumentCacheInputError::Unknown(format!(
+                "{:?} {}",
+                error_code, message
+            ))),
+        }
+    }
+}
+
+pub struct InvalidateCacheInput {
+    pub url: Option<String>,
+}
+impl InvalidateCacheInput {
+    pub fn take_in<E: MessageExchange>(
+        message_exchange: E,
+    ) -> Result<InvalidateCacheInput, TakeDocumentCacheInputError> {
+        let response = match InvalidateCacheInputRequest::new().send_json_in(message_exchange) {
+            Err(err) => {
+                tracing::error!(
+                    "Failed to receive invalidate_cache_input response: {:#}",
+                    err
+                );
+                return Err(TakeDocumentCacheInputError::InvalidFormat(err));
+            }
+            Ok(r) => r,
+        };
+
+        match response {
+            InvalidateCacheInputResponse::Ok { url } => Ok(InvalidateCacheInput { url }),
+            InvalidateCacheInputResponse::Err {
+                error_code,
+                message,
+            } => Err(TakeDocumentCacheInputError::Unknown(format!(
+                "{:?} {}",
+                error_code, message
+            ))),
+        }
+    }
+}
+
+pub fn set_preload_output_in<E: MessageExchange>(
+    documents: Vec<PreloadedDocument>,
+    message_exchange: E,
+) {
+    let response = PreloadOutputRequest::new(documents)
+        .send_json_in(message_exchange)
+        .unwrap();
+
+    match response {
+        PreloadOutputResponse::Ok => (),
+        PreloadOutputResponse::Err {
+            error_code,
+            message,
+        } => panic!("preload-output error: {:?}: {}", error_code, message),
+    }
+}
+
+pub fn set_list_cache_output_in<E: MessageExchange>(
+    documents: Vec<CachedDocument>,
+    message_exchange: E,
+) {
+    let response = ListCacheOutputRequest::new(documents)
+        .send_json_in(message_exchange)
+        .unwrap();
+
+    match response {
+        ListCacheOutputResponse::Ok => (),
+        ListCacheOutputResponse::Err {
+            error_code,
+            message,
+        } => panic!("list-cache-output error: {:?}: {}", error_code, message),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_message_out_preload_input() {
+        let actual = json!({
+            "kind": "ok",
+            "profile_urls": ["file://wasm-sdk.example.profile"]
+        });
+
+        match serde_json::from_value::<PreloadInputResponse>(actual).unwrap() {
+            PreloadInputResponse::Ok {
+                profile_urls,
+                provider_urls,
+                map_urls,
+            } => {
+                assert_eq!(profile_urls, vec!["file://wasm-sdk.example.profile"]);
+                assert!(provider_urls.is_empty());
+                assert!(map_urls.is_empty());
+            }
+            PreloadInputResponse::Err { .. } => panic!("Expected ok"),
+        }
+    }
+
+    #[test]
+    fn test_message_in_list_cache_output() {
+        let request = ListCacheOutputRequest::new(vec![CachedDocument {
+            kind: DocumentKind::Provider,
+            url: "file://localhost.provider.json".to_string(),
+            content_hash: "abc".to_string(),
+            age: 1500,
+            expired: false,
+        }]);
+
+        assert_eq!(
+            serde_json::to_value(request).unwrap(),
+            json!({
+                "kind": "list-cache-output",
+                "documents": [{
+                    "kind": "provider",
+                    "url": "file://localhost.provider.json",
+                    "content_hash": "abc",
+                    "age": 1500,
+                    "expired": false
+                }]
+            })
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+
+use super::{exception::PerformException, ErrorCode, HostValue};
+use crate::abi::{MessageError, MessageExchange};
+
+define_exchange_core_to_host! {
+    struct ExamplesInputRequest {
+        kind: "examples-input"
+    } -> enum ExamplesInputResponse {
+        Ok {
+            /// Url of the profile whose examples are run.
+            profile_url: String,
+            /// Url of the provider.
+            provider_url: String,
+            /// Url of the map.
+            map_url: String,
+            /// Only run examples of this usecase, examples of all usecases are run otherwise.
+            #[serde(default)]
+            usecase: Option<String>,
+            /// Integrations parameters.
+            map_parameters: HostValue,
+            /// Security values
+            map_security: HostValue,
+            /// Id the examples are performed under, which the host uses to cancel them. Generated for each example if not present.
+            #[serde(default)]
+            perform_id: Option<String>
+        },
+        Err {
+            error_code: ErrorCode,
+            message: String,
+        }
+    }
+}
+
+define_exchange_core_to_host! {
+    struct ExamplesOutputRequest {
+        kind: "examples-output",
+        /// Outcome of all examples.
+        report: ExamplesReport
+    } -> enum ExamplesOutputResponse {
+        Ok,
+        Err {
+            error_code: ErrorCode,
+            message: String
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TakeExamplesInputError {
+    #[error("Invalid input format: {0}")]
+    InvalidFormat(MessageError),
+    #[error("Unknown examples input error: {0}")]
+    Unknown(String),
+}
+impl From<TakeExamplesInputError> for PerformException {
+    fn from(value: TakeExamplesInputError) -> Self {
+        PerformException {
+            error_code: super::exception::PerformExceptionErrorCode::TakeInputError,
+            message: value.to_string(),
+        }
+    }
+}
+
+pub struct ExamplesInput {
+    pub profile_url: String,
+    pub provider_url: String,
+    pub map_url: String,
+    pub usecase: Option<String>,
+    pub map_parameters: HostValue,
+    pub map_security: HostValue,
+    pub perform_id: Option<String>,
+}
+impl ExamplesInput {
+    pub fn take_in<E: MessageExchange>(
+        message_exchange: E,
+    ) -> Result<ExamplesInput, TakeExamplesInputError> {
+        let response = match ExamplesInputRequest::new().send_json_in(message_exchange) {
+            Err(err) => {
+                tracing::error!("Failed to receive examples_input response: {:#}", err);
+                return Err(TakeExamplesInputError::InvalidFormat(err));
+            }
+            Ok(r) => r,
+        };
+
+        match response {
+            ExamplesInputResponse::Ok {
+                profile_url,
+                provider_url,
+                map_url,
+                usecase,
+                map_parameters,
+                map_security,
+                perform_id,
+            } => Ok(ExamplesInput {
+                profile_url,
+                provider_url,
+                map_url,
+                usecase,
+                map_parameters,
+                map_security,
+                perform_id,
+            }),
+            ExamplesInputResponse::Err {
+                error_code,
+                message,
+            } => Err(TakeExamplesInputError::Unknown(format!(
+                "{:?} {}",
+                error_code, message
+            ))),
+        }
+    }
+}
+
+/// Outcome of running profile examples.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ExamplesReport {
+    /// Number of examples which passed.
+    pub passed: usize,
+    /// Number of examples which failed.
+    pub failed: usize,
+    pub examples: Vec<ExampleReport>,
+    /// Set when examples could not be run at all, for example because documents failed to load.
+    pub exception: Option<PerformException>,
+}
+
+/// Outcome of one example.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExampleReport {
+    pub usecase: String,
+    /// Name of the example, if it has one.
+    pub name: Option<String>,
+    pub passed: bool,
+    /// Why the example failed.
+    pub message: Option<String>,
+    /// Result or error declared in the example.
+    pub expected: Option<ExampleOutcome>,
+    /// Result, error or exception returned by the map.
+    pub actual: Option<ExampleOutcome>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExampleOutcome {
+    Result(HostValue),
+    Error(HostValue),
+    Exception(PerformException),
+}
+
+pub fn set_examples_output_in<E: MessageExchange>(report: ExamplesReport, message_exchange: E) {
+    let response = ExamplesOutputRequest::new(report)
+        .send_json_in(message_exchange)
+        .unwrap();
+
+    match response {
+        ExamplesOutputResponse::Ok => (),
+        ExamplesOutputResponse::Err {
+            error_code,
+            message,
+        } => panic!("examples-output error: {:?}: {}", error_code, message),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_message_in_examples_output() {
+        let actual = serde_json::to_value(ExamplesOutputRequest::new(ExamplesReport {
+            passed: 1,
+            failed: 0,
+            examples: vec![ExampleReport {
+                usecase: "Foo".into(),
+                name: Some("success_example".into()),
+                passed: true,
+                message: None,
+                expected: Some(ExampleOutcome::Result(HostValue::Bool(true))),
+                actual: Some(ExampleOutcome::Result(HostValue::Bool(true))),
+            }],
+            exception: None,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            actual,
+            json!({
+                "kind": "examples-output",
+                "report": {
+                    "passed": 1,
+                    "failed": 0,
+                    "examples": [{
+                        "usecase": "Foo",
+                        "name": "success_example",
+                        "passed": true,
+                        "message": null,
+                        "expected": { "result": true },
+                        "actual": { "result": true }
+                    }],
+                    "exception": null
+                }
+            })
+        )
+    }
+}
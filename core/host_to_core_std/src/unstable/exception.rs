@@ -2,24 +2,34 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum PerformExceptionErrorCode {
+    Cancelled,
     DocumentCacheError,
     InputValidationError,
     JsInterpreterError,
+    MapManifestMismatch,
+    Panic,
     ParametersFormatError,
     PrepareSecurityMapError,
+    ProfileVersionMismatch,
     ReplacementStdlibError,
     TakeInputError,
 }
 impl std::fmt::Display for PerformExceptionErrorCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            PerformExceptionErrorCode::Cancelled => write!(f, "Cancelled"),
             PerformExceptionErrorCode::DocumentCacheError => write!(f, "DocumentCacheError"),
             PerformExceptionErrorCode::InputValidationError => write!(f, "InputValidationError"),
             PerformExceptionErrorCode::JsInterpreterError => write!(f, "JsInterpreterError"),
+            PerformExceptionErrorCode::MapManifestMismatch => write!(f, "MapManifestMismatch"),
+            PerformExceptionErrorCode::Panic => write!(f, "Panic"),
             PerformExceptionErrorCode::ParametersFormatError => write!(f, "ParametersFormatError"),
             PerformExceptionErrorCode::PrepareSecurityMapError => {
                 write!(f, "PrepareSecurityMapError")
             }
+            PerformExceptionErrorCode::ProfileVersionMismatch => {
+                write!(f, "ProfileVersionMismatch")
+            }
             PerformExceptionErrorCode::ReplacementStdlibError => {
                 write!(f, "ReplacementStdlibError")
             }
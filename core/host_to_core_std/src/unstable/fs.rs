@@ -1,4 +1,4 @@
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 
 use serde::{Deserialize, Serialize};
 
@@ -151,4 +151,16 @@ impl<Me: StaticMessageExchange, Se: StaticStreamExchange> FsConvenience<Me, Se>
 
         Ok(data)
     }
+
+    /// Like [std::fs::write].
+    pub fn write(path: &str, data: &[u8]) -> Result<(), io::Error> {
+        let mut file = OpenOptions::new()
+            .read(false)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open_in(path.as_ref(), Me::instance(), Se::instance())?;
+
+        file.write_all(data)
+    }
 }
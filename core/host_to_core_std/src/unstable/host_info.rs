@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+
+use super::ErrorCode;
+use crate::abi::{MessageError, MessageExchange};
+
+/// Revision of the host to core ABI, incremented on breaking changes.
+///
+/// Additions which hosts may not support are advertised as features instead.
+pub const ABI_VERSION: u32 = 1;
+
+/// Kinds of messages core may send to the host.
+pub const MESSAGE_KINDS: &[&str] = &[
+    "host-info",
+    "perform-input",
+    "perform-output-result",
+    "perform-output-error",
+    "perform-output-exception",
+    "cancel-input",
+    "recover-input",
+    "configure-input",
+    "configure-output",
+    "preload-input",
+    "preload-output",
+    "invalidate-cache-input",
+    "list-cache-output",
+    "examples-input",
+    "examples-output",
+    "file-open",
+    "http-call",
+    "http-call-head",
+    "secret-get",
+];
+
+/// Description of core returned by the `oneclient_core_abi_info` export.
+#[derive(Debug, Serialize)]
+pub struct AbiInfo<'a> {
+    pub abi_version: u32,
+    pub core_version: &'a str,
+    pub message_kinds: &'a [&'a str],
+    /// Optional features supported by core.
+    pub features: &'a [&'a str],
+}
+
+define_exchange_core_to_host! {
+    struct HostInfoRequest<'a> {
+        kind: "host-info",
+        /// ABI version implemented by core.
+        abi_version: u32,
+        core_version: &'a str,
+        /// Optional features supported by core.
+        features: &'a [&'a str]
+    } -> enum HostInfoResponse {
+        Ok {
+            /// ABI version implemented by the host.
+            abi_version: u32,
+            /// Optional features supported by the host.
+            #[serde(default)]
+            features: Vec<String>
+        },
+        Err {
+            error_code: ErrorCode,
+            message: String
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HostInfoError {
+    #[error("Host does not support the host-info message: {0}")]
+    Unsupported(MessageError),
+    #[error("Host failed to respond to host-info: {0:?} {1}")]
+    Failed(ErrorCode, String),
+    #[error("Host implements ABI version {host}, but core implements ABI version {core}")]
+    IncompatibleAbi { host: u32, core: u32 },
+}
+
+/// Host information received during setup.
+#[derive(Debug, Clone, Default)]
+pub struct HostInfo {
+    /// ABI version implemented by the host, `None` if the host predates the handshake.
+    pub abi_version: Option<u32>,
+    /// Optional features supported by the host.
+    pub features: Vec<String>,
+}
+impl HostInfo {
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+
+    /// Sends information about core to the host and receives information about the host.
+    pub fn exchange_in<E: MessageExchange>(
+        core: &AbiInfo,
+        message_exchange: E,
+    ) -> Result<HostInfo, HostInfoError> {
+        let response = HostInfoRequest::new(core.abi_version, core.core_version, core.features)
+            .send_json_in(message_exchange)
+            .map_err(HostInfoError::Unsupported)?;
+
+        match response {
+            HostInfoResponse::Ok {
+                abi_version,
+                features,
+            } => {
+                if abi_version != core.abi_version {
+                    return Err(HostInfoError::IncompatibleAbi {
+                        host: abi_version,
+                        core: core.abi_version,
+                    });
+                }
+
+                Ok(HostInfo {
+                    abi_version: Some(abi_version),
+                    features,
+                })
+            }
+            HostInfoResponse::Err {
+                error_code,
+                message,
+            } => Err(HostInfoError::Failed(error_code, message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    struct TestExchange(serde_json::Value);
+    impl MessageExchange for TestExchange {
+        fn invoke(&self, _message: &[u8]) -> Vec<u8> {
+            serde_json::to_vec(&self.0).unwrap()
+        }
+    }
+
+    const CORE: AbiInfo = AbiInfo {
+        abi_version: ABI_VERSION,
+        core_version: "0.1.0",
+        message_kinds: MESSAGE_KINDS,
+        features: &["trace_context"],
+    };
+
+    #[test]
+    fn test_message_in_host_info() {
+        let actual = serde_json::to_value(HostInfoRequest::new(
+            CORE.abi_version,
+            CORE.core_version,
+            CORE.features,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            actual,
+            json!({
+                "kind": "host-info",
+                "abi_version": ABI_VERSION,
+                "core_version": "0.1.0",
+                "features": ["trace_context"]
+            })
+        )
+    }
+
+    #[test]
+    fn test_host_info_exchange() {
+        let info = HostInfo::exchange_in(
+            &CORE,
+            TestExchange(
+                json!({ "kind": "ok", "abi_version": ABI_VERSION, "features": ["timeouts"] }),
+            ),
+        )
+        .unwrap();
+        assert_eq!(info.abi_version, Some(ABI_VERSION));
+        assert!(info.supports("timeouts"));
+        assert!(!info.supports("trace_context"));
+
+        // hosts which predate the handshake respond with an unknown message error
+        assert!(matches!(
+            HostInfo::exchange_in(
+                &CORE,
+                TestExchange(json!({ "kind": "err", "error": "Unknown message host-info" }))
+            ),
+            Err(HostInfoError::Unsupported(_))
+        ));
+        assert!(matches!(
+            HostInfo::exchange_in(
+                &CORE,
+                TestExchange(json!({ "kind": "ok", "abi_version": ABI_VERSION + 1 }))
+            ),
+            Err(HostInfoError::IncompatibleAbi { .. })
+        ));
+    }
+}
@@ -25,6 +25,9 @@ define_exchange_core_to_host! {
         /// Multiple values for one key will not be joined.
         headers: &'a HeadersMultiMap,
         /// Body bytes to be sent.
+        ///
+        /// Serialized as an array of numbers in JSON and natively in MessagePack.
+        #[serde(serialize_with = "serde_bytes::serialize")]
         body: Option<&'a [u8]>
     } -> enum HttpCallResponse {
         Ok {
@@ -183,7 +186,10 @@ impl<Me: MessageExchange, Se: StreamExchange> HttpRequest<Me, Se> {
             ErrorCode::NetworkInvalidUrl => HttpCallError::InvalidUrl(message),
             ErrorCode::NetworkConnectionRefused => HttpCallError::ConnectionRefused(message),
             ErrorCode::NetworkHostNotFound => HttpCallError::HostNotFound(message),
-            ErrorCode::NetworkError | ErrorCode::NetworkInvalidHandle => {
+            ErrorCode::NetworkError
+            | ErrorCode::NetworkInvalidHandle
+            | ErrorCode::SecretNotFound
+            | ErrorCode::SecretError => {
                 HttpCallError::Unknown(format!("{:?}: {}", error_code, message))
             }
         }
@@ -1,10 +1,15 @@
 //! Unstable functions provide no stability guarantees
 
+pub mod configure;
+pub mod document_cache;
+pub mod examples;
 pub mod exception;
 pub mod fs;
+pub mod host_info;
 pub mod http;
 pub mod perform;
 pub mod provider;
+pub mod secret;
 
 mod value;
 use serde::Deserialize;
@@ -26,6 +31,10 @@ pub enum ErrorCode {
     NetworkInvalidUrl,
     #[serde(rename = "network:invalid_handle")]
     NetworkInvalidHandle,
+    #[serde(rename = "secret:not_found")]
+    SecretNotFound,
+    #[serde(rename = "secret:error")]
+    SecretError,
 }
 
 #[cfg(test)]
@@ -98,4 +107,26 @@ mod test {
         let actual_json_again = serde_json::to_value(actual).unwrap();
         assert_eq!(actual_json_again, actual_json);
     }
+
+    #[test]
+    fn test_bytes() {
+        let value = host_value_object! {
+            "bytes" => HostValue::Bytes(vec![0, 159, 146, 150])
+        };
+
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(
+            json,
+            json!({ "bytes": { "$HostValue::Bytes": "AJ+Slg==" } })
+        );
+        assert_eq!(serde_json::from_value::<HostValue>(json).unwrap(), value);
+
+        // bytes are native in MessagePack
+        let message_pack = rmp_serde::to_vec_named(&value).unwrap();
+        assert!(message_pack.ends_with(&[0xc4, 4, 0, 159, 146, 150]));
+        assert_eq!(
+            rmp_serde::from_slice::<HostValue>(&message_pack).unwrap(),
+            value
+        );
+    }
 }
@@ -1,27 +1,16 @@
 use serde::{Deserialize, Serialize};
 
 use super::{exception::PerformException, ErrorCode, HostValue};
-use crate::abi::{JsonMessageError, MessageExchange};
+use crate::abi::{MessageError, MessageExchange};
 
 define_exchange_core_to_host! {
     struct PerformInputRequest {
         kind: "perform-input"
     } -> enum PerformInputResponse {
         Ok {
-            /// Url of the profile.
-            profile_url: String,
-            /// Url of the provider.
-            provider_url: String,
-            /// Url of the map (e.g. `file://<path>`).
-            map_url: String,
-            /// Usecase defined in the profile.
-            usecase: String,
-            /// Input passed into the map.
-            map_input: HostValue,
-            /// Integrations parameters.
-            map_parameters: HostValue,
-            /// Security values
-            map_security: HostValue
+            /// Boxed because the input is much larger than the error.
+            #[serde(flatten)]
+            input: Box<PerformInput>
         },
         Err {
             error_code: ErrorCode,
@@ -62,7 +51,9 @@ define_exchange_core_to_host! {
     struct PerformOutputExceptionRequest {
         kind: "perform-output-exception",
         /// All other unexpected errors are returned here.
-        exception: PerformException
+        exception: PerformException,
+        /// Id of the failed perform, unless the exception happened before it was assigned.
+        perform_id: Option<String>
     } -> enum PerformOutputExceptionResponse {
         Ok,
         Err {
@@ -72,10 +63,41 @@ define_exchange_core_to_host! {
     }
 }
 
+define_exchange_core_to_host! {
+    struct CancelInputRequest {
+        kind: "cancel-input"
+    } -> enum CancelInputResponse {
+        Ok {
+            /// Id of the perform to cancel, as passed in the perform input.
+            perform_id: String
+        },
+        Err {
+            error_code: ErrorCode,
+            message: String,
+        }
+    }
+}
+
+define_exchange_core_to_host! {
+    struct RecoverInputRequest {
+        kind: "recover-input"
+    } -> enum RecoverInputResponse {
+        Ok {
+            /// Id of the perform which trapped, if the host passed one in its perform input.
+            #[serde(default)]
+            perform_id: Option<String>
+        },
+        Err {
+            error_code: ErrorCode,
+            message: String,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum TakePerformInputError {
     #[error("Invalid input format: {0}")]
-    InvalidFormat(JsonMessageError),
+    InvalidFormat(MessageError),
     #[error("Unknown perform input error: {0}")]
     Unknown(String),
 }
@@ -88,14 +110,38 @@ impl From<TakePerformInputError> for PerformException {
     }
 }
 
+/// W3C trace context passed from the host, see <https://www.w3.org/TR/trace-context/>.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceContext {
+    /// Value of the `traceparent` header.
+    pub traceparent: String,
+    /// Value of the `tracestate` header.
+    #[serde(default)]
+    pub tracestate: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
 pub struct PerformInput {
+    /// Url of the profile.
     pub profile_url: String,
+    /// Url of the provider.
     pub provider_url: String,
+    /// Url of the map (e.g. `file://<path>`).
     pub map_url: String,
+    /// Usecase defined in the profile.
     pub usecase: String,
+    /// Input passed into the map.
     pub map_input: HostValue,
+    /// Integrations parameters.
     pub map_parameters: HostValue,
+    /// Security values
     pub map_security: HostValue,
+    /// W3C trace context of the caller, if any.
+    #[serde(default)]
+    pub trace_context: Option<TraceContext>,
+    /// Id of the perform used to correlate logs, metrics and dumps, generated by core if not provided.
+    #[serde(default)]
+    pub perform_id: Option<String>,
 }
 impl PerformInput {
     pub fn take_in<E: MessageExchange>(
@@ -110,23 +156,7 @@ impl PerformInput {
         };
 
         match response {
-            PerformInputResponse::Ok {
-                profile_url,
-                provider_url,
-                map_url,
-                usecase,
-                map_input,
-                map_parameters,
-                map_security,
-            } => Ok(PerformInput {
-                profile_url,
-                provider_url,
-                map_url,
-                usecase,
-                map_input,
-                map_parameters,
-                map_security,
-            }),
+            PerformInputResponse::Ok { input } => Ok(*input),
             PerformInputResponse::Err {
                 error_code,
                 message,
@@ -138,6 +168,62 @@ impl PerformInput {
     }
 }
 
+pub struct CancelInput {
+    pub perform_id: String,
+}
+impl CancelInput {
+    pub fn take_in<E: MessageExchange>(
+        message_exchange: E,
+    ) -> Result<CancelInput, TakePerformInputError> {
+        let response = match CancelInputRequest::new().send_json_in(message_exchange) {
+            Err(err) => {
+                tracing::error!("Failed to receive cancel_input response: {:#}", err);
+                return Err(TakePerformInputError::InvalidFormat(err));
+            }
+            Ok(r) => r,
+        };
+
+        match response {
+            CancelInputResponse::Ok { perform_id } => Ok(CancelInput { perform_id }),
+            CancelInputResponse::Err {
+                error_code,
+                message,
+            } => Err(TakePerformInputError::Unknown(format!(
+                "{:?} {}",
+                error_code, message
+            ))),
+        }
+    }
+}
+
+pub struct RecoverInput {
+    pub perform_id: Option<String>,
+}
+impl RecoverInput {
+    pub fn take_in<E: MessageExchange>(
+        message_exchange: E,
+    ) -> Result<RecoverInput, TakePerformInputError> {
+        let response = match RecoverInputRequest::new().send_json_in(message_exchange) {
+            Err(err) => {
+                tracing::error!("Failed to receive recover_input response: {:#}", err);
+                return Err(TakePerformInputError::InvalidFormat(err));
+            }
+            Ok(r) => r,
+        };
+
+        match response {
+            RecoverInputResponse::Ok { perform_id } => Ok(RecoverInput { perform_id }),
+            RecoverInputResponse::Err {
+                error_code,
+                message,
+            } => Err(TakePerformInputError::Unknown(format!(
+                "{:?} {}",
+                error_code, message
+            ))),
+        }
+    }
+}
+
 pub fn set_perform_output_result_in<E: MessageExchange>(result: HostValue, message_exchange: E) {
     let response = PerformOutputResultRequest::new(result)
         .send_json_in(message_exchange)
@@ -168,9 +254,10 @@ pub fn set_perform_output_error_in<E: MessageExchange>(error: HostValue, message
 
 pub fn set_perform_output_exception_in<E: MessageExchange>(
     exception: PerformException,
+    perform_id: Option<String>,
     message_exchange: E,
 ) {
-    let response = PerformOutputExceptionRequest::new(exception)
+    let response = PerformOutputExceptionRequest::new(exception, perform_id)
         .send_json_in(message_exchange)
         .unwrap();
 
@@ -193,6 +280,7 @@ mod test {
     use serde_json::json;
 
     use super::*;
+    use crate::abi::MessageEncoding;
 
     #[test]
     fn test_message_in_perform_input() {
@@ -228,15 +316,19 @@ mod test {
         });
 
         match serde_json::from_value::<PerformInputResponse>(actual).unwrap() {
-            PerformInputResponse::Ok {
-                profile_url,
-                provider_url,
-                map_url,
-                usecase,
-                map_input,
-                map_parameters,
-                map_security,
-            } => {
+            PerformInputResponse::Ok { input } => {
+                let PerformInput {
+                    profile_url,
+                    provider_url,
+                    map_url,
+                    usecase,
+                    map_input,
+                    map_parameters,
+                    map_security,
+                    trace_context,
+                    perform_id,
+                } = *input;
+
                 assert_eq!(profile_url, "quz");
                 assert_eq!(provider_url, "baz");
                 assert_eq!(map_url, "foo");
@@ -257,6 +349,64 @@ mod test {
                 let mut security = BTreeMap::new();
                 security.insert("basic".to_string(), HostValue::Object(basic));
                 assert_eq!(map_security, HostValue::Object(security));
+                assert!(trace_context.is_none());
+                assert!(perform_id.is_none());
+            }
+            PerformInputResponse::Err { .. } => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_message_out_perform_input_trace_context() {
+        let actual = json!({
+            "kind": "ok",
+            "profile_url": "quz",
+            "provider_url": "baz",
+            "map_url": "foo",
+            "usecase": "bar",
+            "map_input": null,
+            "map_parameters": null,
+            "map_security": null,
+            "trace_context": {
+                "traceparent": "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+                "tracestate": "vendor=value"
+            }
+        });
+
+        match serde_json::from_value::<PerformInputResponse>(actual).unwrap() {
+            PerformInputResponse::Ok { input } => {
+                let trace_context = input.trace_context.unwrap();
+                assert_eq!(
+                    trace_context.traceparent,
+                    "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                );
+                assert_eq!(trace_context.tracestate.as_deref(), Some("vendor=value"));
+            }
+            PerformInputResponse::Err { .. } => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_message_out_perform_input_message_pack() {
+        let string = |s: &str| HostValue::String(s.to_string());
+        let actual = HostValue::Object(BTreeMap::from([
+            ("kind".to_string(), string("ok")),
+            ("profile_url".to_string(), string("quz")),
+            ("provider_url".to_string(), string("baz")),
+            ("map_url".to_string(), string("foo")),
+            ("usecase".to_string(), string("bar")),
+            ("map_input".to_string(), HostValue::Bytes(vec![1, 2, 3])),
+            ("map_parameters".to_string(), HostValue::None),
+            ("map_security".to_string(), HostValue::None),
+        ]));
+        let actual = MessageEncoding::MessagePack.encode(&actual).unwrap();
+
+        match MessageEncoding::MessagePack
+            .decode::<PerformInputResponse>(&actual)
+            .unwrap()
+        {
+            PerformInputResponse::Ok { input } => {
+                assert_eq!(input.map_input, HostValue::Bytes(vec![1, 2, 3]));
             }
             PerformInputResponse::Err { .. } => unreachable!(),
         }
@@ -307,4 +457,53 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_message_in_perform_output_exception() {
+        let actual = serde_json::to_value(PerformOutputExceptionRequest {
+            kind: PerformOutputExceptionRequest::KIND,
+            exception: PerformException {
+                error_code:
+                    crate::unstable::exception::PerformExceptionErrorCode::JsInterpreterError,
+                message: "Failed".into(),
+            },
+            perform_id: Some("4bf92f3577b34da6a3ce929d0e0e4736".into()),
+        })
+        .unwrap();
+
+        assert_eq!(
+            actual,
+            json!({
+                "kind": "perform-output-exception",
+                "exception": {
+                    "error_code": "JsInterpreterError",
+                    "message": "Failed"
+                },
+                "perform_id": "4bf92f3577b34da6a3ce929d0e0e4736"
+            })
+        )
+    }
+
+    #[test]
+    fn test_message_out_cancel_input() {
+        let actual = json!({
+            "kind": "ok",
+            "perform_id": "4bf92f3577b34da6a3ce929d0e0e4736"
+        });
+
+        match serde_json::from_value::<CancelInputResponse>(actual).unwrap() {
+            CancelInputResponse::Ok { perform_id } => {
+                assert_eq!(perform_id, "4bf92f3577b34da6a3ce929d0e0e4736")
+            }
+            CancelInputResponse::Err { .. } => panic!("Expected ok"),
+        }
+    }
+
+    #[test]
+    fn test_message_out_recover_input() {
+        match serde_json::from_value::<RecoverInputResponse>(json!({ "kind": "ok" })).unwrap() {
+            RecoverInputResponse::Ok { perform_id } => assert_eq!(perform_id, None),
+            RecoverInputResponse::Err { .. } => panic!("Expected ok"),
+        }
+    }
 }
@@ -16,6 +16,13 @@ pub struct ProviderService {
     pub id: String,
     #[serde(rename = "baseUrl")]
     pub base_url: String,
+    /// Whether W3C trace context headers are sent to this service, defaults to `true`.
+    #[serde(
+        rename = "propagateTraceContext",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub propagate_trace_context: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use super::ErrorCode;
+use crate::abi::{MessageError, MessageExchange};
+
+define_exchange_core_to_host! {
+    struct SecretGetRequest<'a> {
+        kind: "secret-get",
+        /// Name of the secret as referenced in security values, e.g. `{ "$secret": "<name>" }`.
+        name: &'a str
+    } -> enum SecretGetResponse {
+        Ok {
+            value: String
+        },
+        Err {
+            error_code: ErrorCode,
+            message: String
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretGetError {
+    #[error("Invalid secret-get response: {0}")]
+    InvalidFormat(MessageError),
+    #[error("Secret \"{0}\" was not found: {1}")]
+    NotFound(String, String),
+    #[error("Failed to get secret \"{0}\": {1}")]
+    Unknown(String, String),
+}
+
+/// Retrieves the value of the secret `name` from the host.
+pub fn get_secret_in<E: MessageExchange>(
+    name: &str,
+    message_exchange: E,
+) -> Result<String, SecretGetError> {
+    let response = SecretGetRequest::new(name)
+        .send_json_in(message_exchange)
+        .map_err(SecretGetError::InvalidFormat)?;
+
+    match response {
+        SecretGetResponse::Ok { value } => Ok(value),
+        SecretGetResponse::Err {
+            error_code: ErrorCode::SecretNotFound,
+            message,
+        } => Err(SecretGetError::NotFound(name.to_string(), message)),
+        SecretGetResponse::Err {
+            error_code,
+            message,
+        } => Err(SecretGetError::Unknown(
+            name.to_string(),
+            format!("{:?} {}", error_code, message),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_message_in_secret_get() {
+        assert_eq!(
+            serde_json::to_value(SecretGetRequest::new("twilio/auth")).unwrap(),
+            json!({
+                "kind": "secret-get",
+                "name": "twilio/auth"
+            })
+        );
+    }
+
+    #[test]
+    fn test_message_out_secret_get() {
+        let actual = json!({
+            "kind": "err",
+            "error_code": "secret:not_found",
+            "message": "No such secret in vault"
+        });
+
+        assert!(matches!(
+            serde_json::from_value::<SecretGetResponse>(actual).unwrap(),
+            SecretGetResponse::Err {
+                error_code: ErrorCode::SecretNotFound,
+                ..
+            }
+        ));
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 
 use super::IoStreamHandle;
@@ -9,10 +10,14 @@ use super::IoStreamHandle;
 /// In addition to variants in [serde_json::Value] we also define our custom types, such as streams.
 ///
 /// Our custom types are always objects with one field: `{ "$HostValue::<type>": <type_serialized> }`
+///
+/// Bytes are a custom type with base64 encoded value in human readable formats such as JSON and a native type in
+/// binary formats such as MessagePack.
 #[derive(Debug, PartialEq, Eq)]
 pub enum HostValue {
     // custom
     Stream(IoStreamHandle),
+    Bytes(Vec<u8>),
     // standard
     None,
     Bool(bool),
@@ -25,6 +30,7 @@ pub enum HostValue {
 }
 impl HostValue {
     const CUSTOM_TYPE_STREAM: &'static str = "$HostValue::Stream";
+    const CUSTOM_TYPE_BYTES: &'static str = "$HostValue::Bytes";
 }
 impl Serialize for HostValue {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
@@ -37,6 +43,15 @@ impl Serialize for HostValue {
                 map.serialize_entry(Self::CUSTOM_TYPE_STREAM, stream)?;
                 map.end()
             }
+            Self::Bytes(bytes) if serializer.is_human_readable() => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(
+                    Self::CUSTOM_TYPE_BYTES,
+                    &base64::engine::general_purpose::STANDARD.encode(bytes),
+                )?;
+                map.end()
+            }
+            Self::Bytes(bytes) => serializer.serialize_bytes(bytes),
             Self::None => serializer.serialize_unit(),
             Self::Bool(b) => serializer.serialize_bool(*b),
             Self::Number(n) => n.serialize(serializer),
@@ -94,6 +109,15 @@ impl<'de> Deserialize<'de> for HostValue {
                 Ok(HostValue::String(value))
             }
 
+            #[inline]
+            fn visit_bytes<E>(self, value: &[u8]) -> Result<HostValue, E> {
+                Ok(HostValue::Bytes(value.to_vec()))
+            }
+            #[inline]
+            fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<HostValue, E> {
+                Ok(HostValue::Bytes(value))
+            }
+
             #[inline]
             fn visit_none<E>(self) -> Result<HostValue, E> {
                 Ok(HostValue::None)
@@ -130,6 +154,14 @@ impl<'de> Deserialize<'de> for HostValue {
 
                         return Ok(HostValue::Stream(stream));
                     }
+                    Some(key) if key == HostValue::CUSTOM_TYPE_BYTES => {
+                        let bytes: String = visitor.next_value()?;
+                        let bytes = base64::engine::general_purpose::STANDARD
+                            .decode(bytes)
+                            .map_err(serde::de::Error::custom)?;
+
+                        return Ok(HostValue::Bytes(bytes));
+                    }
                     Some(first_key) => {
                         let mut values = BTreeMap::new();
 
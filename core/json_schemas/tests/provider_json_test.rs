@@ -0,0 +1,142 @@
+use serde_json::json;
+
+#[macro_use]
+mod common;
+
+#[test]
+fn test_provider_json() {
+    let schema = json_schema!("../src/schemas/provider_json.yaml");
+
+    let instance = json!({
+      "name": "localhost",
+      "services": [
+        {
+          "id": "default",
+          "baseUrl": "http://localhost:8000/{PARAM}",
+          "propagateTraceContext": false
+        }
+      ],
+      "defaultService": "default",
+      "parameters": [
+        {
+          "name": "PARAM",
+          "default": "default param value"
+        }
+      ],
+      "securitySchemes": [
+        {
+          "id": "basic_auth",
+          "type": "http",
+          "scheme": "basic"
+        },
+        {
+          "id": "api_key",
+          "type": "apiKey",
+          "in": "header",
+          "name": "X-API-KEY"
+        }
+      ]
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_ok());
+
+    let instance = json!({
+      "name": "empty",
+      "services": [
+        {
+          "id": "default",
+          "baseUrl": "http://localhost:8000"
+        }
+      ],
+      "parameters": [],
+      "securitySchemes": []
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_ok());
+
+    let instance = json!({
+      "name": "no-services",
+      "services": []
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_err());
+
+    let instance = json!({
+      "name": "missing-base-url",
+      "services": [
+        {
+          "id": "default"
+        }
+      ]
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_err());
+
+    let instance = json!({
+      "name": "unknown-scheme",
+      "services": [
+        {
+          "id": "default",
+          "baseUrl": "http://localhost:8000"
+        }
+      ],
+      "securitySchemes": [
+        {
+          "id": "digest",
+          "type": "http",
+          "scheme": "digest"
+        }
+      ]
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_err());
+
+    let instance = json!({
+      "name": "incomplete-api-key",
+      "services": [
+        {
+          "id": "default",
+          "baseUrl": "http://localhost:8000"
+        }
+      ],
+      "securitySchemes": [
+        {
+          "id": "api_key",
+          "type": "apiKey",
+          "in": "header"
+        }
+      ]
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_err());
+
+    let instance = json!({
+      "name": "invalid-trace-context-flag",
+      "services": [
+        {
+          "id": "default",
+          "baseUrl": "http://localhost:8000",
+          "propagateTraceContext": "no"
+        }
+      ]
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_err());
+
+    let instance = json!({
+      "name": "parameter-without-name",
+      "services": [
+        {
+          "id": "default",
+          "baseUrl": "http://localhost:8000"
+        }
+      ],
+      "parameters": [
+        {
+          "default": "value"
+        }
+      ]
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_err());
+}
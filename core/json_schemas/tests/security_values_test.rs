@@ -22,6 +22,26 @@ fn test_security_values() {
     let result = schema.validate(&instance);
     assert!(result.is_ok());
 
+    let instance = json!({
+        "my_basic": {
+            "username": "username",
+            "password": { "$secret": "example/password" }
+        },
+        "my_api_key": {
+            "apikey": { "$secret": "example/apikey" }
+        }
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_ok());
+
+    let instance = json!({
+        "my_token": {
+            "token": { "$secret": "example/token", "value": "token" }
+        }
+    });
+    let result = schema.validate(&instance);
+    assert!(result.is_err());
+
     let instance = json!({
         "security_config": {
             "unknown": "so invalid"
@@ -1,6 +1,11 @@
 /// <reference types="@superface/map-std" />
 // @ts-check
 
+const manifest = {
+  profile: 'communication/send-email@2.1',
+  provider: 'mailchimp'
+};
+
 /** @type {Usecase<{ safety: 'unsafe', input: { from: AnyValue, to: AnyValue, subject: AnyValue, text?: AnyValue, html?: AnyValue, attachments?: [{ content: AnyValue, type: AnyValue, filename?: AnyValue }] }, result: { messageId: AnyValue }, error: { title: string, detail: string } }>} */
 var SendEmail = ({ input, services }) => {
   const url = `${services.mandrill}/api/1.0/messages/send`;
@@ -1,6 +1,11 @@
 /// <reference types="@superface/map-std" />
 // @ts-check
 
+const manifest = {
+  profile: 'communication/send-sms@2.0',
+  provider: 'tyntec'
+};
+
 /** @type {Usecase<{ safety: 'unsafe', input: { to?: AnyValue, from?: AnyValue, text?: AnyValue }, result: { messageId: AnyValue }, error: { title: string, detail: string } }>} */
 var SendMessage = ({ input, parameters, services }) => {
   const url = `${services.default}/messaging/v1/sms`;
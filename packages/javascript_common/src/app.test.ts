@@ -75,11 +75,12 @@ describe('App', () => {
 
     await app.init(new WASI({ version: 'preview1' } as any));
 
+    const handleMessageOriginal = app.handleMessage.bind(app);
     handleMessage = jest.spyOn(app, 'handleMessage');
     handleMessage.mockImplementation(async (message) => {
       switch (message.kind) {
         case 'perform-input':
-          const performState = (app as any).performState;
+          const performState = (app as any).taskState();
 
           return {
             'kind': 'ok',
@@ -93,10 +94,13 @@ describe('App', () => {
           };
 
         case 'perform-output-result':
-          (app as any).performState.result = message.result;
+          (app as any).taskState().result = message.result;
           return {
             kind: 'ok'
           }
+
+        default:
+          return handleMessageOriginal(message);
       }
     });
   });
@@ -145,6 +149,57 @@ describe('App', () => {
     expect(result).toBe(true);
   });
 
+  test('perform after recovered panic', async () => {
+    await expect(app.perform(
+      '',
+      '',
+      '',
+      'CORE_PERFORM_PANIC',
+      null,
+      {},
+      {},
+    )).rejects.toMatchObject({ name: 'Panic' });
+
+    // core was recovered, not instantiated again
+    const result = await app.perform(
+      '',
+      '',
+      '',
+      'CORE_PERFORM_TRUE',
+      null,
+      {},
+      {},
+    );
+
+    expect(result).toBe(true);
+  });
+
+  test('concurrent performs', async () => {
+    const results = await Promise.all([
+      app.perform('', '', '', 'CORE_PERFORM_TRUE', null, {}, {}),
+      app.perform('', '', '', 'CORE_PERFORM_TRUE', null, {}, {}),
+    ]);
+
+    expect(results).toEqual([true, true]);
+  });
+
+  test('configure without teardown', async () => {
+    await expect(app.configure({ developer_log: 'debug' }, { keepCaches: true })).resolves.toBeUndefined();
+  });
+
+  test('document cache management', async () => {
+    await expect(app.preload({ profileUrls: ['file://example.profile'] })).resolves.toEqual([]);
+    await expect(app.invalidateCache()).resolves.toBeUndefined();
+    await expect(app.listCache()).resolves.toEqual([]);
+  });
+
+  test('secret-get without secrets', async () => {
+    await expect(app.handleMessage({ kind: 'secret-get', name: 'example/key' })).resolves.toMatchObject({
+      kind: 'err',
+      error_code: 'secret:not_found'
+    });
+  });
+
   test('invalid user input', async () => {
     handleMessage.mockRestore();
 
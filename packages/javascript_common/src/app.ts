@@ -1,6 +1,6 @@
 import type { SecurityValuesMap } from './security.js';
-import type { AppContext, FileSystem, Network, Persistence, TextCoder, Timers, WasiContext } from './interfaces.js';
-import { PerformError, UnexpectedError, UninitializedError, ValidationError, WasiErrno, WasiError } from './error.js';
+import type { AppContext, FileSystem, Network, Persistence, Secrets, TextCoder, Timers, WasiContext } from './interfaces.js';
+import { ErrorCode, PerformError, UnexpectedError, UninitializedError, ValidationError, WasiErrno, WasiError } from './error.js';
 import { AsyncMutex, Asyncify, HandleMap, ReadableStreamAdapter, Stream } from './lib/index.js';
 import * as sf_host from './sf_host.js';
 
@@ -19,14 +19,164 @@ function headersToMultimap(headers: Headers): Record<string, string[]> {
   return result;
 }
 
+/** W3C trace context of the caller, see https://www.w3.org/TR/trace-context/ */
+export type TraceContext = {
+  traceparent: string,
+  tracestate?: string
+};
+
+/** Outcome of one profile example, see `App.runExamples`. */
+export type ExampleReport = {
+  usecase: string,
+  name: string | null,
+  passed: boolean,
+  /** Why the example failed */
+  message: string | null,
+  expected: { result: unknown } | { error: unknown } | null,
+  actual: { result: unknown } | { error: unknown } | { exception: { error_code: string, message: string } } | null
+};
+export type ExamplesReport = {
+  passed: number,
+  failed: number,
+  examples: ExampleReport[],
+  /** Set when examples could not be run at all */
+  exception: { error_code: string, message: string } | null
+};
+
+/** Revision of the host to core ABI implemented by this host. */
+const ABI_VERSION = 1;
+
+/** Description of the ABI implemented by core, returned by `oneclient_core_abi_info`. */
+export type CoreAbiInfo = {
+  abi_version: number,
+  core_version: string,
+  message_kinds: string[],
+  features: string[]
+};
+
+/** Settings of core which can be changed after setup, see `App.configure`. Settings which are not present are left unchanged. */
+export type CoreConfigurationUpdate = {
+  /** Duration to cache documents for, in seconds */
+  cache_duration?: number,
+  registry_url?: string,
+  user_agent?: string,
+  user_log?: boolean,
+  user_log_http_body_max_size?: number,
+  /** Filter directives of the developer log, in the same format as `ONESDK_DEV_LOG` */
+  developer_log?: string
+};
+
+export type DocumentKind = 'profile' | 'provider' | 'map';
+
+/** Outcome of loading one document, see `App.preload`. */
+export type PreloadedDocument = {
+  kind: DocumentKind,
+  url: string,
+  content_hash: string | null,
+  /** Why the document could not be loaded */
+  error: string | null
+};
+
+/** Document stored in one of the core document caches, see `App.listCache`. */
+export type CachedDocument = {
+  kind: DocumentKind,
+  url: string,
+  content_hash: string,
+  /** Time since the document was loaded, in milliseconds */
+  age: number,
+  /** Whether the document is older than the cache duration and will be loaded again when next used */
+  expired: boolean
+};
+
+type PerformState = {
+  /** Only sent to core when the perform can be cancelled or recovered */
+  performId?: string,
+  profileUrl: string,
+  providerUrl: string,
+  mapUrl: string,
+  usecase: string,
+  input: unknown,
+  parameters: Record<string, string>,
+  security: SecurityValuesMap,
+  traceContext?: TraceContext,
+  result?: unknown,
+  error?: PerformError,
+  exception?: UnexpectedError
+};
+
+type ExamplesState = {
+  /** Only sent to core when the examples can be cancelled */
+  performId?: string,
+  profileUrl: string,
+  providerUrl: string,
+  mapUrl: string,
+  usecase?: string,
+  parameters: Record<string, string>,
+  security: SecurityValuesMap,
+  report?: ExamplesReport
+};
+
+type CancelState = {
+  performId: string
+};
+
+type ConfigureState = {
+  configuration: CoreConfigurationUpdate,
+  keepCaches: boolean,
+  /** Why core rejected the configuration */
+  error?: string
+};
+
+type PreloadState = {
+  profileUrls: string[],
+  providerUrls: string[],
+  mapUrls: string[],
+  documents?: PreloadedDocument[]
+};
+
+type InvalidateCacheState = {
+  url?: string
+};
+
+type ListCacheState = {
+  documents?: CachedDocument[]
+};
+
+/** Random id in the same format as ids generated by core. */
+function generatePerformId(): string {
+  let id = '';
+  for (let i = 0; i < 4; i += 1) {
+    id += Math.floor(Math.random() * 0x100000000).toString(16).padStart(8, '0');
+  }
+
+  return id;
+}
+
 type AppCore = {
   instance: WebAssembly.Instance;
   asyncify: Asyncify;
+  /** Performs can run concurrently, otherwise they must be called under the core lock */
+  concurrentPerforms: boolean;
   setupFn: () => Promise<void>;
   teardownFn: () => Promise<void>;
-  performFn: () => Promise<void>;
+  performFn: (state: PerformState) => Promise<void>;
+  runExamplesFn: (state: ExamplesState) => Promise<void>;
+  /** Only available if performs run concurrently, otherwise core cannot be called while a perform is running */
+  cancelFn: ((state: CancelState) => Promise<void>) | undefined;
+  /** Only available if performs run concurrently, so that the trapped perform has its own stacks to abandon */
+  recoverFn: ((state: PerformState) => Promise<void>) | undefined;
+  /** Only available if core has the `configure` feature */
+  configureFn: ((state: ConfigureState) => Promise<void>) | undefined;
+  /** Only available if core has the `document_cache` feature */
+  preloadFn: ((state: PreloadState) => Promise<void>) | undefined;
+  /** Only available if core has the `document_cache` feature */
+  invalidateCacheFn: ((state: InvalidateCacheState) => Promise<void>) | undefined;
+  /** Only available if core has the `document_cache` feature */
+  listCacheFn: ((state: ListCacheState) => Promise<void>) | undefined;
   getMetricsFn: () => Promise<number>;
   clearMetricsFn: () => Promise<void>;
+  getTracesFn: () => Promise<number>;
+  clearTracesFn: () => Promise<void>;
   getDeveloperDumpFn: () => Promise<number>;
 };
 export class App implements AppContext {
@@ -35,24 +185,15 @@ export class App implements AppContext {
   private readonly fileSystem: FileSystem;
   private readonly timers: Timers;
   private readonly persistence: Persistence;
+  private readonly secrets: Secrets | undefined;
 
   private readonly streams: HandleMap<Stream>;
   private readonly requests: HandleMap<Promise<Response>>;
 
   private module: WebAssembly.Module | undefined = undefined;
   private core: AsyncMutex<AppCore> | undefined = undefined;
-  private performState: {
-    profileUrl: string,
-    providerUrl: string,
-    mapUrl: string,
-    usecase: string,
-    input: unknown,
-    parameters: Record<string, string>,
-    security: SecurityValuesMap,
-    result?: unknown,
-    error?: PerformError,
-    exception?: UnexpectedError
-  } | undefined = undefined;
+  /** Performs and example runs which have not finished yet, awaited before the core is torn down */
+  private readonly inFlight: Set<Promise<void>> = new Set();
 
   // TODO: should the timer be part of the host platform instead?
   private metricsState: {
@@ -63,7 +204,7 @@ export class App implements AppContext {
   private readonly userAgent;
 
   constructor(
-    dependencies: { network: Network, fileSystem: FileSystem, textCoder: TextCoder, timers: Timers, persistence: Persistence },
+    dependencies: { network: Network, fileSystem: FileSystem, textCoder: TextCoder, timers: Timers, persistence: Persistence, secrets?: Secrets },
     options: { userAgent?: string, metricsTimeout?: number }
   ) {
     this.textCoder = dependencies.textCoder;
@@ -71,6 +212,7 @@ export class App implements AppContext {
     this.fileSystem = dependencies.fileSystem;
     this.timers = dependencies.timers;
     this.persistence = dependencies.persistence;
+    this.secrets = dependencies.secrets;
     this.streams = new HandleMap();
     this.requests = new HandleMap();
     this.userAgent = options?.userAgent;
@@ -111,16 +253,43 @@ export class App implements AppContext {
     if (this.core === undefined) {
       const [instance, asyncify] = await Asyncify.instantiate(this.module, (asyncify) => this.importObject(wasi, asyncify));
       wasi.initialize(instance);
+      const abiInfo = this.checkCoreAbi(instance);
+      const concurrentPerforms = asyncify.supportsTasks && (abiInfo?.features.includes('concurrent_performs') ?? false);
+      const recoverFn = concurrentPerforms && abiInfo!.features.includes('recover')
+        ? asyncify.wrapTaskExport(instance.exports['oneclient_core_recover'] as () => void)
+        : undefined;
+      const documentCache = abiInfo?.features.includes('document_cache') === true;
 
       this.core = new AsyncMutex({
         instance,
         asyncify,
+        concurrentPerforms,
         setupFn: this.wrapExport(asyncify.wrapExport(instance.exports['oneclient_core_setup'] as () => void)),
         teardownFn: this.wrapExport(asyncify.wrapExport(instance.exports['oneclient_core_teardown'] as () => void)),
-        performFn: this.wrapExport(asyncify.wrapExport(instance.exports['oneclient_core_perform'] as () => void)),
+        // a perform which traps is recovered from if possible, so that other performs and document caches survive
+        performFn: this.wrapExport(asyncify.wrapTaskExport(instance.exports['oneclient_core_perform'] as () => void), recoverFn),
+        runExamplesFn: this.wrapExport(asyncify.wrapTaskExport(instance.exports['oneclient_core_run_examples'] as () => void)),
+        cancelFn: concurrentPerforms && abiInfo!.features.includes('cancel')
+          ? this.wrapExport(asyncify.wrapTaskExport(instance.exports['oneclient_core_cancel'] as () => void))
+          : undefined,
+        recoverFn,
+        configureFn: abiInfo?.features.includes('configure') === true
+          ? this.wrapExport(asyncify.wrapTaskExport(instance.exports['oneclient_core_configure'] as () => void))
+          : undefined,
+        preloadFn: documentCache
+          ? this.wrapExport(asyncify.wrapTaskExport(instance.exports['oneclient_core_preload'] as () => void))
+          : undefined,
+        invalidateCacheFn: documentCache
+          ? this.wrapExport(asyncify.wrapTaskExport(instance.exports['oneclient_core_invalidate_cache'] as () => void))
+          : undefined,
+        listCacheFn: documentCache
+          ? this.wrapExport(asyncify.wrapTaskExport(instance.exports['oneclient_core_list_cache'] as () => void))
+          : undefined,
         // if we fail during getting metrics, we want to skip dumping metrics but still attempt to create developer dump
         getMetricsFn: this.wrapExport(instance.exports['oneclient_core_get_metrics'] as () => number),
         clearMetricsFn: this.wrapExport(instance.exports['oneclient_core_clear_metrics'] as () => void), // this is not called when dumping metrics, so we can wrap it as normal
+        getTracesFn: this.wrapExport(instance.exports['oneclient_core_get_traces'] as () => number),
+        clearTracesFn: this.wrapExport(instance.exports['oneclient_core_clear_traces'] as () => void),
         // if we fail during getting developer dump, we want to skip recursing, so we don't attempt to dump anything
         getDeveloperDumpFn: this.wrapExport(instance.exports['oneclient_core_get_developer_dump'] as () => number)
       });
@@ -129,8 +298,32 @@ export class App implements AppContext {
     }
   }
 
+  /** Cores which predate the `oneclient_core_abi_info` export are assumed to be compatible. */
+  private checkCoreAbi(instance: WebAssembly.Instance): CoreAbiInfo | undefined {
+    const abiInfoFn = instance.exports['oneclient_core_abi_info'] as (() => number) | undefined;
+    if (abiInfoFn === undefined) {
+      return undefined;
+    }
+
+    const memory = instance.exports.memory as WebAssembly.Memory;
+    const view = new DataView(memory.buffer);
+    const ptr = abiInfoFn();
+    const abiInfo: CoreAbiInfo = JSON.parse(this.textCoder.decodeUtf8(
+      new Uint8Array(memory.buffer, view.getUint32(ptr, true), view.getUint32(ptr + 4, true))
+    ));
+    if (abiInfo.abi_version !== ABI_VERSION) {
+      throw new UnexpectedError(
+        'CoreIncompatible',
+        `Core ${abiInfo.core_version} implements ABI version ${abiInfo.abi_version}, but host implements ABI version ${ABI_VERSION}`
+      );
+    }
+
+    return abiInfo;
+  }
+
   public async destroy(): Promise<void> {
     if (this.core !== undefined) {
+      await Promise.allSettled(this.inFlight);
       await this.sendMetrics();
       return this.core.withLock(core => core.teardownFn());
       // TODO: should there be a this.core = undefined here?
@@ -138,6 +331,8 @@ export class App implements AppContext {
   }
 
   /**
+   * Aborting `signal` cancels the perform if the core supports it, the perform then rejects with a `Cancelled` error.
+   *
    * @throws {PerformError | ValidationError | UnexpectedError}
    */
   public async perform(
@@ -148,56 +343,277 @@ export class App implements AppContext {
     input: unknown,
     parameters: Record<string, string>,
     security: SecurityValuesMap,
+    traceContext?: TraceContext,
+    signal?: AbortSignal,
   ): Promise<unknown> {
+    if (signal?.aborted === true) {
+      throw new UnexpectedError('Cancelled', 'Perform was cancelled');
+    }
     this.setSendMetricsTimeout();
 
-    return this.core!.withLock(
-      async (core) => {
-        this.performState = { profileUrl, providerUrl, mapUrl, usecase, input, parameters, security };
-        await core.performFn();
+    const state: PerformState = { profileUrl, providerUrl, mapUrl, usecase, input, parameters, security, traceContext };
+    const { cancelFn, recoverFn } = this.core!.unsafeValue;
+    // core needs the id to cancel the perform or to forget it after it traps
+    if (recoverFn !== undefined || (signal !== undefined && cancelFn !== undefined)) {
+      state.performId = generatePerformId();
+    }
+    let onAbort: (() => void) | undefined;
+    if (signal !== undefined && cancelFn !== undefined) {
+      const performId = state.performId!;
+      // failures are reported by the perform itself
+      onAbort = () => { cancelFn({ performId }).catch(() => undefined); };
+      signal.addEventListener('abort', onAbort, { once: true });
+    }
 
-        const state = this.performState;
-        this.performState = undefined;
-        if (state.exception !== undefined) {
-          throw state.exception;
-        }
+    try {
+      await this.runTask(core => core.performFn(state));
+    } finally {
+      if (onAbort !== undefined) {
+        signal!.removeEventListener('abort', onAbort);
+      }
+    }
 
-        if (state.error !== undefined) {
-          throw state.error;
-        }
+    if (state.exception !== undefined) {
+      throw state.exception;
+    }
+
+    if (state.error !== undefined) {
+      throw state.error;
+    }
+
+    return state.result;
+  }
+
+  /**
+   * Performs examples declared in the profile and compares map outputs to the example results and errors.
+   *
+   * Runs examples of all usecases unless `usecase` is given. Aborting `signal` cancels the running example, the remaining ones are not run.
+   */
+  public async runExamples(
+    profileUrl: string,
+    providerUrl: string,
+    mapUrl: string,
+    parameters: Record<string, string>,
+    security: SecurityValuesMap,
+    usecase?: string,
+    signal?: AbortSignal,
+  ): Promise<ExamplesReport> {
+    if (signal?.aborted === true) {
+      throw new UnexpectedError('Cancelled', 'Perform was cancelled');
+    }
+    this.setSendMetricsTimeout();
+
+    const state: ExamplesState = { profileUrl, providerUrl, mapUrl, usecase, parameters, security };
+    const { cancelFn } = this.core!.unsafeValue;
+    let onAbort: (() => void) | undefined;
+    if (signal !== undefined && cancelFn !== undefined) {
+      const performId = generatePerformId();
+      state.performId = performId;
+      // the cancellation is reported in the examples report
+      onAbort = () => { cancelFn({ performId }).catch(() => undefined); };
+      signal.addEventListener('abort', onAbort, { once: true });
+    }
 
-        return state.result;
+    try {
+      await this.runTask(core => core.runExamplesFn(state));
+    } finally {
+      if (onAbort !== undefined) {
+        signal!.removeEventListener('abort', onAbort);
       }
-    );
+    }
+
+    return state.report!;
+  }
+
+  /**
+   * Changes configuration of core without tearing it down, e.g. to enable developer log.
+   *
+   * Documents cached so far are dropped unless `keepCaches` is set, performs which are already running keep the documents they loaded.
+   *
+   * @throws {ValidationError} if core rejects the configuration, it is left unchanged then
+   * @throws {UnexpectedError} if core doesn't support configuration after setup
+   */
+  public async configure(configuration: CoreConfigurationUpdate, options: { keepCaches: boolean }): Promise<void> {
+    if (this.core!.unsafeValue.configureFn === undefined) {
+      throw new UnexpectedError('CoreIncompatible', 'Core does not support configuration after setup');
+    }
+
+    const state: ConfigureState = { configuration, keepCaches: options.keepCaches };
+    await this.runTask(core => core.configureFn!(state));
+
+    if (state.error !== undefined) {
+      throw new ValidationError(state.error);
+    }
+  }
+
+  /**
+   * Loads documents into the core document caches, so that the first perform using them doesn't have to.
+   *
+   * A document which fails to load doesn't prevent the others from loading, the outcome of each is returned in the order given.
+   *
+   * @throws {UnexpectedError} if core doesn't support document cache management
+   */
+  public async preload(urls: { profileUrls?: string[], providerUrls?: string[], mapUrls?: string[] }): Promise<PreloadedDocument[]> {
+    this.checkDocumentCacheSupport();
+
+    const state: PreloadState = {
+      profileUrls: urls.profileUrls ?? [],
+      providerUrls: urls.providerUrls ?? [],
+      mapUrls: urls.mapUrls ?? []
+    };
+    await this.runTask(core => core.preloadFn!(state));
+
+    if (state.documents === undefined) {
+      throw new UnexpectedError('UnexpectedError', 'Core did not report preloaded documents');
+    }
+
+    return state.documents;
+  }
+
+  /**
+   * Drops the document with `url` from the core document caches, or all documents if `url` is not given.
+   *
+   * Performs which are already running keep using the documents they loaded.
+   *
+   * @throws {UnexpectedError} if core doesn't support document cache management
+   */
+  public async invalidateCache(url?: string): Promise<void> {
+    this.checkDocumentCacheSupport();
+
+    await this.runTask(core => core.invalidateCacheFn!({ url }));
+  }
+
+  /**
+   * Lists documents in the core document caches, including expired ones which will be loaded again when next used.
+   *
+   * @throws {UnexpectedError} if core doesn't support document cache management
+   */
+  public async listCache(): Promise<CachedDocument[]> {
+    this.checkDocumentCacheSupport();
+
+    const state: ListCacheState = {};
+    await this.runTask(core => core.listCacheFn!(state));
+
+    return state.documents ?? [];
+  }
+
+  private checkDocumentCacheSupport() {
+    if (this.core!.unsafeValue.preloadFn === undefined) {
+      throw new UnexpectedError('CoreIncompatible', 'Core does not support document cache management');
+    }
+  }
+
+  /** Runs an export which receives its state through messages, concurrently with other ones if the core supports it. */
+  private async runTask(fn: (core: AppCore) => Promise<void>): Promise<void> {
+    const core = this.core!;
+    const task = core.unsafeValue.concurrentPerforms ? fn(core.unsafeValue) : core.withLock(fn);
+
+    this.inFlight.add(task);
+    try {
+      await task;
+    } finally {
+      this.inFlight.delete(task);
+    }
+  }
+
+  /**
+   * State of the perform or examples run which sent the message currently being handled.
+   *
+   * Must be called before the first `await` in `handleMessage`, after that another perform might be running.
+   */
+  private taskState<T extends PerformState | ExamplesState | CancelState | ConfigureState | PreloadState | InvalidateCacheState | ListCacheState>(): T {
+    return this.core!.unsafeValue.asyncify.currentContext as T;
   }
 
   public async handleMessage(message: any): Promise<any> {
     switch (message.kind) {
-      case 'perform-input':
+      // compatibility is checked against `oneclient_core_abi_info` in init
+      case 'host-info':
+        return { kind: 'ok', abi_version: ABI_VERSION, features: [] };
+
+      case 'examples-input': {
+        const examplesState = this.taskState<ExamplesState>();
+        return {
+          kind: 'ok',
+          profile_url: examplesState.profileUrl,
+          provider_url: examplesState.providerUrl,
+          map_url: examplesState.mapUrl,
+          usecase: examplesState.usecase,
+          map_parameters: examplesState.parameters,
+          map_security: examplesState.security,
+          perform_id: examplesState.performId,
+        };
+      }
+
+      case 'examples-output':
+        this.taskState<ExamplesState>().report = message.report;
+        return { kind: 'ok' };
+
+      case 'perform-input': {
+        const performState = this.taskState<PerformState>();
+        return {
+          kind: 'ok',
+          profile_url: performState.profileUrl,
+          provider_url: performState.providerUrl,
+          map_url: performState.mapUrl,
+          usecase: performState.usecase,
+          map_input: performState.input,
+          map_parameters: performState.parameters,
+          map_security: performState.security,
+          trace_context: performState.traceContext,
+          perform_id: performState.performId,
+        };
+      }
+
+      case 'cancel-input':
+        return { kind: 'ok', perform_id: this.taskState<CancelState>().performId };
+
+      case 'configure-input': {
+        const configureState = this.taskState<ConfigureState>();
+        return { kind: 'ok', configuration: configureState.configuration, keep_caches: configureState.keepCaches };
+      }
+
+      case 'configure-output':
+        this.taskState<ConfigureState>().error = message.error ?? undefined;
+        return { kind: 'ok' };
+
+      case 'preload-input': {
+        const preloadState = this.taskState<PreloadState>();
         return {
           kind: 'ok',
-          profile_url: this.performState!.profileUrl,
-          provider_url: this.performState!.providerUrl,
-          map_url: this.performState!.mapUrl,
-          usecase: this.performState!.usecase,
-          map_input: this.performState!.input,
-          map_parameters: this.performState!.parameters,
-          map_security: this.performState!.security,
+          profile_urls: preloadState.profileUrls,
+          provider_urls: preloadState.providerUrls,
+          map_urls: preloadState.mapUrls,
         };
+      }
+
+      case 'preload-output':
+        this.taskState<PreloadState>().documents = message.documents;
+        return { kind: 'ok' };
+
+      case 'invalidate-cache-input':
+        return { kind: 'ok', url: this.taskState<InvalidateCacheState>().url };
+
+      case 'list-cache-output':
+        this.taskState<ListCacheState>().documents = message.documents;
+        return { kind: 'ok' };
+
+      case 'recover-input':
+        return { kind: 'ok', perform_id: this.taskState<PerformState>().performId };
 
       case 'perform-output-result':
-        this.performState!.result = message.result;
+        this.taskState<PerformState>().result = message.result;
         return { kind: 'ok' };
 
       case 'perform-output-error':
-        this.performState!.error = new PerformError(message.error);
+        this.taskState<PerformState>().error = new PerformError(message.error);
         return { kind: 'ok' };
 
       case 'perform-output-exception':
         if (message.exception.error_code === "InputValidationError") {
-          this.performState!.exception = new ValidationError(message.exception.message);
+          this.taskState<PerformState>().exception = new ValidationError(message.exception.message, message.perform_id ?? undefined);
         } else {
-          this.performState!.exception = new UnexpectedError(message.exception.error_code, message.exception.message);
+          this.taskState<PerformState>().exception = new UnexpectedError(message.exception.error_code, message.exception.message, message.perform_id ?? undefined);
         }
         return { kind: 'ok' };
 
@@ -226,6 +642,8 @@ export class App implements AppContext {
         const requestInit: RequestInit = {
           method: message.method,
           headers: message.headers,
+          // core follows redirects itself so that it can check where they lead
+          redirect: 'manual',
         };
 
         if (message.body !== undefined && message.body !== null) {
@@ -250,6 +668,23 @@ export class App implements AppContext {
         }
       }
 
+      case 'secret-get': {
+        if (this.secrets === undefined) {
+          return { kind: 'err', error_code: ErrorCode.SecretNotFound, message: 'No secrets are configured' };
+        }
+
+        try {
+          const value = await this.secrets.get(message.name);
+          if (value === undefined) {
+            return { kind: 'err', error_code: ErrorCode.SecretNotFound, message: `Secret ${message.name} does not exist` };
+          }
+
+          return { kind: 'ok', value };
+        } catch (error: any) {
+          return { kind: 'err', error_code: ErrorCode.SecretError, message: error.message };
+        }
+      }
+
       default:
         return { 'kind': 'err', 'error': `Unknown message ${message['kind']}` }
     }
@@ -289,7 +724,14 @@ export class App implements AppContext {
     }
   }
 
-  private wrapExport<A extends unknown[], R>(fn: (...arg: A) => R): (...arg: A) => Promise<Awaited<R>> {
+  /**
+   * If `recover` is given, it is called with the same arguments after `fn` traps. If it succeeds, core is kept
+   * and `recover` is expected to have reported the failure through the arguments.
+   */
+  private wrapExport<A extends unknown[], R>(
+    fn: (...arg: A) => R,
+    recover?: (...arg: A) => Promise<void>
+  ): (...arg: A) => Promise<Awaited<R>> {
     return async (...args: A): Promise<Awaited<R>> => {
       try {
         return await fn(...args);
@@ -299,6 +741,25 @@ export class App implements AppContext {
         if (err instanceof WebAssembly.RuntimeError) {
           errName = 'WebAssemblyRuntimeError';
           errMessage = err.message;
+
+          if (recover !== undefined && this.core !== undefined) {
+            const core = this.core.unsafeValue;
+            let recovered = false;
+            try {
+              core.asyncify.recover();
+              await recover(...args);
+              recovered = true;
+            } catch (recoverErr: unknown) {
+              errMessage = `${errMessage}, recovery failed with ${recoverErr}`;
+            }
+
+            if (recovered) {
+              // metrics, including the panic, are sent as usual since core keeps running
+              await this.createDeveloperDump(core);
+              // only exports without a return value are recoverable
+              return undefined as Awaited<R>;
+            }
+          }
         }
 
         // in case we got here while already attempting to dump during an error, this condition prevents recursion
@@ -380,7 +841,27 @@ export class App implements AppContext {
     });
 
     if (events.length > 0) {
-      return this.persistence.persistMetrics(events);
+      await this.persistence.persistMetrics(events);
+    }
+
+    await this.sendTraces();
+  }
+
+  private async sendTraces(): Promise<void> {
+    if (this.core === undefined || this.persistence.persistTraces === undefined) {
+      return;
+    }
+
+    const spans = await this.core.withLock(async (core) => {
+      const arenaPointer = await core.getTracesFn();
+      const spans: string[] = this.getTracingEventsByArena(core.instance.exports.memory as WebAssembly.Memory, arenaPointer);
+      await core.clearTracesFn();
+
+      return spans;
+    });
+
+    if (spans.length > 0) {
+      return this.persistence.persistTraces(spans);
     }
   }
 
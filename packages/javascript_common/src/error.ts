@@ -12,13 +12,15 @@ export class PerformError extends BaseError {
 }
 
 export class ValidationError extends BaseError {
-  constructor(message: string) {
+  /** @param performId id of the failed perform, to look it up in logs and metrics */
+  constructor(message: string, public readonly performId?: string) {
     super(ValidationError.name, message);
   }
 }
 
 export class UnexpectedError extends BaseError {
-  constructor(name: string, message: string) {
+  /** @param performId id of the failed perform, to look it up in logs and metrics */
+  constructor(name: string, message: string, public readonly performId?: string) {
     super(name, message);
   }
 }
@@ -127,5 +129,7 @@ export enum ErrorCode {
   NetworkError = 'network:error', // generic network error
   NetworkConnectionRefused = 'network:ECONNREFUSED',
   NetworkHostNotFound = 'network:ENOTFOUND',
-  NetworkInvalidUrl = 'network:invalid_url'
+  NetworkInvalidUrl = 'network:invalid_url',
+  SecretNotFound = 'secret:not_found',
+  SecretError = 'secret:error'
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 export { App } from './app.js';
+export type { CachedDocument, CoreConfigurationUpdate, DocumentKind, ExampleReport, ExamplesReport, PreloadedDocument, TraceContext } from './app.js';
 export * from './error.js';
-export type { FileSystem, Network, Persistence, TextCoder, Timers, WasiContext } from './interfaces.js';
-export type { SecurityValuesMap } from './security.js';
+export type { FileSystem, Network, Persistence, Secrets, TextCoder, Timers, WasiContext } from './interfaces.js';
+export type { SecretReference, SecurityValuesMap } from './security.js';
 export { HandleMap, AsyncMutex, corePathURL } from './lib/index.js';
\ No newline at end of file
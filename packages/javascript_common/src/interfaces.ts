@@ -31,6 +31,10 @@ export interface Timers {
   setTimeout(callback: () => void, ms: number): number;
   clearTimeout(handle: number): void;
 }
+export interface Secrets {
+  /** Return value of the secret `name`, referenced in security values as `{ $secret: name }`, or `undefined` if there is no such secret. */
+  get(name: string): Promise<string | undefined>;
+}
 export interface WasiContext {
   wasiImport: WebAssembly.ModuleImports;
   initialize(instance: object): void;
@@ -44,6 +48,12 @@ export interface Persistence {
    * The `events` elements are guaranteed to be stringified JSON objects.
   */
   persistMetrics(events: string[]): Promise<void>;
+  /** Persist spans exported by the core when trace export is enabled.
+   * 
+   * The `spans` elements are guaranteed to be stringified OTLP JSON `Span` objects. It is up to the host platform to wrap them
+   * into `resourceSpans` and send them to a collector.
+   */
+  persistTraces?(spans: string[]): Promise<void>;
   /** Process developer dump after the core has panicked.
    * 
    * It is up to the host platform whether to write these in a file, or write to stderr, or store them somewhere else.
@@ -2,6 +2,7 @@
 //
 // We can affort to be less defensive against the code that uses this (since we control it), so we don't use Proxies nor WeakMaps.
 // We also add support for our extension to asyncify - unwind stack allocation with the help of the core.
+// If the core allows it, we also run multiple exports at the same time, each in its own task with its own unwind and shadow stack.
 
 export enum AsyncifyState {
   Normal = 0,
@@ -10,8 +11,12 @@ export enum AsyncifyState {
 }
 
 type AsyncifyExports = {
+  memory: WebAssembly.Memory;
   // our extension of asyncify
   asyncify_alloc_stack(dataAddress: number, size: number): void;
+  // optional, only exported by cores which support concurrent tasks
+  asyncify_alloc_task?: (unwindStackSize: number, shadowStackSize: number) => number;
+  __stack_pointer?: WebAssembly.Global;
   // default asyncify methods
   asyncify_start_rewind(dataAddress: number): void;
   asyncify_stop_rewind(): void;
@@ -22,21 +27,41 @@ type AsyncifyExports = {
 
 type Fn<A extends unknown[], R> = (...args: A) => R;
 
+/** One export call which can be suspended in an async import. */
+type AsyncifyTask = {
+  /** Address of the asyncify data struct of this task */
+  dataAddress: number;
+  /** Shadow stack pointer of this task while it is not running, `undefined` for tasks using the shared stack */
+  stackPointer: number | undefined;
+  // Promise from async import is stored here during unwind
+  // Resolved value from async import is stored here during rewind
+  storedValue: Promise<unknown> | unknown | undefined;
+  /** Context passed to the export, available to imports through `Asyncify.currentContext` */
+  context: unknown;
+};
+
 export class Asyncify {
   private static DEFAULT_DATA_ADDRESS: number = 16;
   private static DEFAULT_STACK_SIZE: number = 8 * 1024;
+  private static DEFAULT_SHADOW_STACK_SIZE: number = 1024 * 1024;
 
   static async instantiate(
     module: WebAssembly.Module,
     imports: (self: Asyncify) => WebAssembly.Imports,
     options?: {
       dataAddress?: number,
-      unwindStackSize?: number
+      unwindStackSize?: number,
+      /** Size of the shadow stack of each concurrent task */
+      shadowStackSize?: number
     }
   ): Promise<[WebAssembly.Instance, Asyncify]> {
-    const asyncify = new Asyncify(options?.dataAddress ?? Asyncify.DEFAULT_DATA_ADDRESS);
+    const asyncify = new Asyncify(
+      options?.dataAddress ?? Asyncify.DEFAULT_DATA_ADDRESS,
+      options?.unwindStackSize ?? Asyncify.DEFAULT_STACK_SIZE,
+      options?.shadowStackSize ?? Asyncify.DEFAULT_SHADOW_STACK_SIZE
+    );
     const instance = await WebAssembly.instantiate(module, imports(asyncify));
-    
+
     const exports = instance.exports;
     if (
       typeof exports.asyncify_alloc_stack !== 'function'
@@ -50,31 +75,55 @@ export class Asyncify {
     }
     asyncify.exports = exports as AsyncifyExports;
 
-    asyncify.exports.asyncify_alloc_stack(asyncify.dataAddress, options?.unwindStackSize ?? Asyncify.DEFAULT_STACK_SIZE);
+    asyncify.exports.asyncify_alloc_stack(asyncify.defaultTask.dataAddress, asyncify.unwindStackSize);
 
     return [instance, asyncify];
   }
-  
+
   private exports: AsyncifyExports | undefined;
-  private readonly dataAddress: number;
-  // Promise from async import is stored here during unwind
-  // Resolved value from async import is stored here during rewind
-  private storedValue: Promise<unknown> | unknown | undefined;
-  private constructor(dataAddress: number) {
+  private readonly unwindStackSize: number;
+  private readonly shadowStackSize: number;
+  /** Task using the shared stack, used by `wrapExport` and by `wrapTaskExport` if the core doesn't support tasks */
+  private readonly defaultTask: AsyncifyTask;
+  /** Task which is currently running synchronously, if any */
+  private currentTask: AsyncifyTask | undefined = undefined;
+  /** Finished tasks whose stacks can be reused */
+  private readonly freeTasks: AsyncifyTask[] = [];
+  /** Set when a task traps, suspended tasks must not be resumed afterwards */
+  private trapped: boolean = false;
+  private constructor(dataAddress: number, unwindStackSize: number, shadowStackSize: number) {
     // Put `__asyncify_data` somewhere, just like the original code.
-    this.dataAddress = dataAddress;
+    this.defaultTask = { dataAddress, stackPointer: undefined, storedValue: undefined, context: undefined };
+    this.unwindStackSize = unwindStackSize;
+    this.shadowStackSize = shadowStackSize;
+  }
+
+  /** Whether `wrapTaskExport` runs exports concurrently, otherwise the caller must not call them concurrently. */
+  public get supportsTasks(): boolean {
+    return typeof this.exports!.asyncify_alloc_task === 'function'
+      && this.exports!.__stack_pointer instanceof WebAssembly.Global;
+  }
+
+  /**
+   * Context of the task which is currently running.
+   *
+   * Async imports must read this before their first `await`, after that another task might be running.
+   */
+  public get currentContext(): unknown {
+    return this.currentTask?.context;
   }
 
   public wrapImport<A extends unknown[], R>(fn: Fn<A, Promise<R>>, dummyValue: R): Fn<A, R> {
     return (...args: A): R => {
+      const task = this.currentTask!;
       const state = this.getState();
       switch (state) {
         // -> export[rewinding] -> wasm[rewinding] -> import[rewinding]
         case AsyncifyState.Rewinding: {
           // we just rewound into this import, resolved value is ready and we will return it
           this.exports!.asyncify_stop_rewind();
-          const value = this.storedValue as R;
-          this.storedValue = undefined;
+          const value = task.storedValue as R;
+          task.storedValue = undefined;
           return value; // -> wasm[normal]
         };
 
@@ -82,8 +131,8 @@ export class Asyncify {
         case AsyncifyState.Normal: {
           // we entered this import from host call
           // we will store this value in `pendingValue` and unwind
-          this.storedValue = fn(...args);
-          this.exports!.asyncify_start_unwind(this.dataAddress);
+          task.storedValue = fn(...args);
+          this.exports!.asyncify_start_unwind(task.dataAddress);
 
           return dummyValue; // -> wasm[unwinding] -> export[unwinding]
         };
@@ -93,37 +142,117 @@ export class Asyncify {
     };
   }
 
+  /** Wraps an export which runs on the shared stack, the caller must not call it while another export is suspended. */
   public wrapExport<A extends unknown[], R>(fn: Fn<A, R>): Fn<A, Promise<R>> {
     return async (...args: A): Promise<R> => {
-      let state = this.getState();
-      if (state !== AsyncifyState.Normal) {
-        throw new Error(`Invalid asyncify state ${state} at the beginning of an export`);
+      this.defaultTask.context = undefined;
+
+      return this.runTask(this.defaultTask, fn, args);
+    }
+  }
+
+  /**
+   * Wraps an export which runs in its own task, so it can be called while other exports are suspended.
+   *
+   * Falls back to the shared stack if the core doesn't support tasks, see `supportsTasks`.
+   */
+  public wrapTaskExport<A extends unknown[], R>(fn: Fn<A, R>): (context: unknown, ...args: A) => Promise<R> {
+    return async (context: unknown, ...args: A): Promise<R> => {
+      if (!this.supportsTasks) {
+        this.defaultTask.context = context;
+
+        return this.runTask(this.defaultTask, fn, args);
+      }
+
+      const task = this.freeTasks.pop() ?? this.allocTask();
+      task.context = context;
+      const result = await this.runTask(task, fn, args);
+
+      // tasks which threw are not reused, their stacks might be in an inconsistent state
+      task.context = undefined;
+      this.freeTasks.push(task);
+
+      return result;
+    }
+  }
+
+  /**
+   * Allows suspended tasks to resume after the task which trapped has been recovered from.
+   *
+   * The stacks of the trapped task are abandoned, so this is only sound if it didn't run on the shared stack.
+   */
+  public recover(): void {
+    switch (this.getState()) {
+      case AsyncifyState.Unwinding: this.exports!.asyncify_stop_unwind(); break;
+      case AsyncifyState.Rewinding: this.exports!.asyncify_stop_rewind(); break;
+    }
+    this.trapped = false;
+  }
+
+  private allocTask(): AsyncifyTask {
+    const dataAddress = this.exports!.asyncify_alloc_task!(this.unwindStackSize, this.shadowStackSize);
+    // the data struct is followed by the initial shadow stack pointer
+    const stackPointer = new DataView(this.exports!.memory.buffer).getUint32(dataAddress + 8, true);
+
+    return { dataAddress, stackPointer, storedValue: undefined, context: undefined };
+  }
+
+  private async runTask<A extends unknown[], R>(task: AsyncifyTask, fn: Fn<A, R>, args: A): Promise<R> {
+    let state = this.getState();
+    if (state !== AsyncifyState.Normal) {
+      throw new Error(`Invalid asyncify state ${state} at the beginning of an export`);
+    }
+
+    let result = this.callInTask(task, fn, args);
+    while (true) {
+      state = this.getState();
+      switch (state) {
+        // -> export[normal] -> wasm[normal] -> import[normal] -> wasm[unwinding] -> export[unwinding]
+        case AsyncifyState.Unwinding: {
+          this.exports!.asyncify_stop_unwind(); // other tasks can run while we wait for this one
+          task.storedValue = await task.storedValue;
+
+          if (this.trapped) {
+            throw new Error('Cannot resume export after another export trapped');
+          }
+          state = this.getState();
+          if (state !== AsyncifyState.Normal) {
+            throw new Error(`Invalid state ${state} after awaiting value in export`);
+          }
+          this.exports!.asyncify_start_rewind(task.dataAddress);
+          result = this.callInTask(task, fn, args); // -> wasm[rewinding] -> import[rewinding]
+        } break;
+
+        // -> export[normal]
+        case AsyncifyState.Normal: {
+          return result as R; // -> wasm[normal]
+        };
+
+        case AsyncifyState.Rewinding: throw new Error('Invalid Rewinding state in an export');
       }
+    }
+  }
+
+  /** Calls `fn` synchronously with the shadow stack of `task`, restoring the shared stack afterwards. */
+  private callInTask<A extends unknown[], R>(task: AsyncifyTask, fn: Fn<A, R>, args: A): R {
+    const stackPointer = this.exports!.__stack_pointer;
+    let sharedStackPointer: unknown;
+    if (task.stackPointer !== undefined) {
+      sharedStackPointer = stackPointer!.value;
+      stackPointer!.value = task.stackPointer;
+    }
+    this.currentTask = task;
 
-      let result = fn(...args);
-      while (true) {
-        state = this.getState();
-        switch (state) {
-          // -> export[normal] -> wasm[normal] -> import[normal] -> wasm[unwinding] -> export[unwinding]
-          case AsyncifyState.Unwinding: {
-            this.exports!.asyncify_stop_unwind(); // in theory, another export could be called while wait for this one, but that requires careful synchronization
-            this.storedValue = await this.storedValue;
-
-            state = this.getState();
-            if (state !== AsyncifyState.Normal) {
-              throw new Error(`Invalid state ${state} after awaiting value in export`);
-            }
-            this.exports!.asyncify_start_rewind(this.dataAddress);
-            result = fn(...args); // -> wasm[rewinding] -> import[rewinding]
-          } break;
-
-          // -> export[normal]
-          case AsyncifyState.Normal: {
-            return result as R; // -> wasm[normal]
-          };
-
-          case AsyncifyState.Rewinding: throw new Error('Invalid Rewinding state in an export');
-        }
+    try {
+      return fn(...args);
+    } catch (err: unknown) {
+      this.trapped = true;
+      throw err;
+    } finally {
+      this.currentTask = undefined;
+      if (task.stackPointer !== undefined) {
+        task.stackPointer = stackPointer!.value;
+        stackPointer!.value = sharedStackPointer;
       }
     }
   }
@@ -1,18 +1,23 @@
+/** Reference to a secret which the host resolves through `Secrets` only when a request needs it. */
+export type SecretReference = {
+  $secret: string;
+};
+
 export type IdBase = {
   id: string;
 };
 
 export type ApiKeySecurityValues = IdBase & {
-  apikey: string;
+  apikey: string | SecretReference;
 };
 
 export type BasicAuthSecurityValues = IdBase & {
-  username: string;
-  password: string;
+  username: string | SecretReference;
+  password: string | SecretReference;
 };
 
 export type BearerTokenSecurityValues = IdBase & {
-  token: string;
+  token: string | SecretReference;
 };
 
 export type SecurityValues = ApiKeySecurityValues | BasicAuthSecurityValues | BearerTokenSecurityValues;
@@ -93,13 +93,20 @@ export function link(app: AppContext, textCoder: TextCoder, asyncify: Asyncify):
     return abi_ok(responseBytes.byteLength);
   }
 
+  // other performs may run and grow the memory while we wait, which detaches views into it, so we copy through a buffer
   async function __export_stream_read(handle: number, out_ptr: Ptr<8>, out_len: Size): Promise<AbiResult> {
-    const out = app.memoryBytes.subarray(out_ptr, out_ptr + out_len);
-
-    return app.readStream(handle, out).then(c => abi_ok(c), e => abi_err(e.errno));
+    const out = new Uint8Array(out_len);
+
+    return app.readStream(handle, out).then(
+      c => {
+        app.memoryBytes.set(out.subarray(0, c), out_ptr);
+        return abi_ok(c);
+      },
+      e => abi_err(e.errno)
+    );
   }
   async function __export_stream_write(handle: number, in_ptr: Ptr<8>, in_len: Size): Promise<AbiResult> {
-    const data = app.memoryBytes.subarray(in_ptr, in_ptr + in_len);
+    const data = app.memoryBytes.slice(in_ptr, in_ptr + in_len);
 
     return app.writeStream(handle, data).then(c => abi_ok(c), e => abi_err(e.errno));
   }
@@ -8,12 +8,17 @@ import { WASI } from 'node:wasi';
 import { AsyncMutex } from './common/lib/index.js';
 import {
   App,
+  CachedDocument,
+  CoreConfigurationUpdate,
   FileSystem,
   HandleMap,
   Network,
   Persistence,
+  PreloadedDocument,
+  Secrets,
   SecurityValuesMap,
   TextCoder,
+  TraceContext,
   Timers,
   UnexpectedError,
   WasiErrno,
@@ -25,6 +30,7 @@ import { fetchErrorToHostError, systemErrorToWasiError } from './error.js';
 const pkg = createRequire(import.meta.url)('../package.json');
 
 export { PerformError, UnexpectedError, ValidationError } from './common/index.js';
+export type { CachedDocument, CoreConfigurationUpdate, PreloadedDocument, SecretReference, Secrets } from './common/index.js';
 export { fetchErrorToHostError, systemErrorToWasiError } from './error.js';
 
 const ASSETS_FOLDER = 'superface';
@@ -219,12 +225,29 @@ export type ClientOptions = {
    * Default: `true`
    */
   onBeforeExitHook?: boolean;
+  /**
+   * Resolves secrets referenced in security values as `{ $secret: name }`, e.g. from a vault.
+   *
+   * Secrets are only retrieved when a request needs them, so they are never part of the perform input.
+   */
+  secrets?: Secrets;
 };
 
 export type ClientPerformOptions = {
   provider: string; // TODO: is there a way to make it optional?
   parameters?: Record<string, string>;
   security?: SecurityValuesMap;
+  /** W3C trace context of the caller, outbound provider calls are made as its children */
+  traceContext?: TraceContext;
+  /** Cancels the perform when aborted, it then rejects with an `UnexpectedError` named `Cancelled` */
+  signal?: AbortSignal;
+};
+
+/** Documents to load by `OneClient.preload`, named the same as when performing. */
+export type PreloadDocuments = {
+  profiles?: string[];
+  providers?: string[];
+  maps?: { profile: string; provider: string }[];
 };
 
 class InternalClient {
@@ -247,7 +270,8 @@ class InternalClient {
       fileSystem: this.fileSystem,
       textCoder: new NodeTextCoder(),
       timers: new NodeTimers(),
-      persistence: new NodePersistence(options.token, options.superfaceApiUrl, this.userAgent)
+      persistence: new NodePersistence(options.token, options.superfaceApiUrl, this.userAgent),
+      secrets: options.secrets
     }, { metricsTimeout: 1000, userAgent: this.userAgent });
 
     if (options.onBeforeExitHook !== false) {
@@ -294,7 +318,9 @@ class InternalClient {
     usecase: string,
     input?: unknown,
     parameters: Record<string, string> = {},
-    security: SecurityValuesMap = {}
+    security: SecurityValuesMap = {},
+    traceContext?: TraceContext,
+    signal?: AbortSignal
   ): Promise<unknown> {
     await this.init();
 
@@ -303,7 +329,7 @@ class InternalClient {
     const mapUrl = await this.resolveMapUrl(profile, provider);
 
     try {
-      return await this.app.perform(profileUrl, providerUrl, mapUrl, usecase, input, parameters, security);
+      return await this.app.perform(profileUrl, providerUrl, mapUrl, usecase, input, parameters, security, traceContext, signal);
     } catch (err: unknown) {
       if (err instanceof UnexpectedError && (err.name === 'WebAssemblyRuntimeError')) {
         await this.destroy();
@@ -314,6 +340,31 @@ class InternalClient {
     }
   }
 
+  public async configure(configuration: CoreConfigurationUpdate, keepCaches: boolean) {
+    await this.init();
+    await this.app.configure(configuration, { keepCaches });
+  }
+
+  public async preload(documents: PreloadDocuments): Promise<PreloadedDocument[]> {
+    await this.init();
+
+    const profileUrls = await Promise.all((documents.profiles ?? []).map(profile => this.resolveProfileUrl(profile)));
+    const providerUrls = await Promise.all((documents.providers ?? []).map(provider => this.resolveProviderUrl(provider)));
+    const mapUrls = await Promise.all((documents.maps ?? []).map(({ profile, provider }) => this.resolveMapUrl(profile, provider)));
+
+    return await this.app.preload({ profileUrls, providerUrls, mapUrls });
+  }
+
+  public async invalidateCache(url?: string) {
+    await this.init();
+    await this.app.invalidateCache(url);
+  }
+
+  public async listCache(): Promise<CachedDocument[]> {
+    await this.init();
+    return await this.app.listCache();
+  }
+
   public async resolveProfileUrl(profile: string): Promise<string> {
     const resolvedProfile = profile.replace(/\//g, '.');
     let path = resolvePath(this.assetsPath, `${resolvedProfile}.profile.ts`);
@@ -371,6 +422,38 @@ export class OneClient {
     await this.internal.destroy();
   }
 
+  /**
+   * Changes configuration of the running client, e.g. to enable the developer log, without restarting it.
+   *
+   * Documents cached so far are dropped unless `keepCaches` is set.
+   */
+  public async configure(configuration: CoreConfigurationUpdate, options: { keepCaches: boolean }) {
+    await this.internal.configure(configuration, options.keepCaches);
+  }
+
+  /**
+   * Loads profiles, providers and maps ahead of the first perform which uses them, e.g. during deployment warmup.
+   *
+   * Returns the outcome of each document, a document which fails to load doesn't prevent the others from loading.
+   */
+  public async preload(documents: PreloadDocuments): Promise<PreloadedDocument[]> {
+    return await this.internal.preload(documents);
+  }
+
+  /**
+   * Drops the document with `url`, as returned by `listCache`, from the caches, or all documents if `url` is not given.
+   *
+   * Dropped documents are loaded again by the next perform which uses them.
+   */
+  public async invalidateCache(url?: string) {
+    await this.internal.invalidateCache(url);
+  }
+
+  /** Lists cached documents with their content hash and age. */
+  public async listCache(): Promise<CachedDocument[]> {
+    return await this.internal.listCache();
+  }
+
   public async getProfile(name: string): Promise<Profile> {
     return await Profile.loadLocal(this.internal, name);
   }
@@ -405,6 +488,6 @@ export class UseCase {
    * @throws {PerformError | UnexpectedError}
    */
   public async perform<TInput = unknown, TResult = unknown>(input: TInput | undefined, options: ClientPerformOptions): Promise<TResult> {
-    return await this.internal.perform(this.profile.name, options.provider, this.name, input, options?.parameters, options?.security) as TResult;
+    return await this.internal.perform(this.profile.name, options.provider, this.name, input, options?.parameters, options?.security, options?.traceContext, options?.signal) as TResult;
   }
 }
@@ -9,6 +9,7 @@ import struct
 from types import SimpleNamespace
 from dataclasses import dataclass
 import functools
+import json
 import weakref
 
 from wasmtime import Engine, Instance, Memory, Store, Module, Linker, WasiConfig
@@ -22,6 +23,9 @@ from one_sdk.platform import PythonFilesystem, PythonNetwork, PythonPersistence,
 # TODO: TypeAlias - needs 3.10
 SecurityValuesMap = Mapping[str, Mapping[str, str]]
 
+# Revision of the host to core ABI implemented by this host
+ABI_VERSION = 1
+
 class WasiMemory:
 	"""Pointer to Wasi Memory - do not store this between calls to WASM as it might get invalidated"""
 	def __init__(self, data_ptr):
@@ -118,6 +122,10 @@ class WasiApp:
 		return self._memory_from_core(self._core)
 
 	def handle_message(self, message: Any) -> Any:
+		# sent during setup, compatibility is checked against `oneclient_core_abi_info` in init
+		if message["kind"] == "host-info":
+			return { "kind": "ok", "abi_version": ABI_VERSION, "features": [] }
+
 		if self._perform_state is None:
 			raise UnexpectedError("UnexpectedError", "Unexpected perform state")
 		
@@ -140,9 +148,9 @@ class WasiApp:
 			return { "kind": "ok" }
 		elif message["kind"] == "perform-output-exception":
 			if message["exception"]["error_code"] == "InputValidationError":
-				self._perform_state.exception = ValidationError(message["exception"]["message"])
+				self._perform_state.exception = ValidationError(message["exception"]["message"], message.get("perform_id"))
 			else:
-				self._perform_state.exception = UnexpectedError(message["exception"]["error_code"], message["exception"]["message"])
+				self._perform_state.exception = UnexpectedError(message["exception"]["error_code"], message["exception"]["message"], message.get("perform_id"))
 			return { "kind": "ok" }
 		elif message["kind"] == "file-open":
 			try:
@@ -245,9 +253,24 @@ class WasiApp:
 			self._wrap_export(exports["oneclient_core_clear_metrics"]),
 			self._wrap_export(exports["oneclient_core_get_developer_dump"])
 		)
+		self._check_core_abi(exports)
 
 		self._core.setup_fn()
 	
+	def _check_core_abi(self, exports: Any):
+		"""Cores which predate the `oneclient_core_abi_info` export are assumed to be compatible."""
+		try:
+			abi_info_fn = exports["oneclient_core_abi_info"]
+		except KeyError:
+			return
+
+		memory = self.memory
+		ptr = abi_info_fn(self._store)
+		abi_info = json.loads(memory.read_bytes(memory.read_i32(ptr), memory.read_i32(ptr + 4)).decode("utf-8"))
+		if abi_info["abi_version"] != ABI_VERSION:
+			self._core = None
+			raise UnexpectedError("CoreIncompatible", f"Core {abi_info['core_version']} implements ABI version {abi_info['abi_version']}, but host implements ABI version {ABI_VERSION}")
+
 	def destroy(self):
 		if self._core is not None:
 			self.send_metrics()
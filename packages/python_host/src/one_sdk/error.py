@@ -1,4 +1,4 @@
-from typing import Any
+from typing import Any, Optional
 
 from enum import IntEnum, Enum
 
@@ -16,12 +16,14 @@ class PerformError(BaseError):
 		self.error_result = error_result
 
 class ValidationError(BaseError):
-	def __init__(self, message: str):
+	def __init__(self, message: str, perform_id: Optional[str] = None):
 		super().__init__("ValidationError", message)
+		self.perform_id = perform_id
 
 class UnexpectedError(BaseError):
-	def __init__(self, name: str, message: str):
+	def __init__(self, name: str, message: str, perform_id: Optional[str] = None):
 		super().__init__(name, message)
+		self.perform_id = perform_id
 
 class UninitializedError(BaseError):
 	def __init__(self):
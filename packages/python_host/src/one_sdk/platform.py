@@ -136,7 +136,7 @@ class DeferredHttpResponse:
 class PythonNetwork:
 	def __init__(self):
 		self._manager = urllib3.PoolManager(num_pools = 3)
-		self._retries = urllib3.Retry(connect = 2, read = 2, redirect = 3)
+		self._retries = urllib3.Retry(connect = 2, read = 2, redirect = False)
 
 	def fetch(
 		self,
@@ -157,7 +157,7 @@ class PythonNetwork:
 			response = self._manager.urlopen(
 				method,
 				url,
-				True,
+				False, # core follows redirects itself so that it can check where they lead
 				body = body,
 				headers = headers_dict,
 				retries = None,
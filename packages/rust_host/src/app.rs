@@ -0,0 +1,903 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use wasmtime::{Engine, Linker, Memory, Module, Store, Trap, TypedFunc};
+use wasmtime_wasi::{preview1::WasiP1Ctx, WasiCtxBuilder};
+
+use crate::{
+    error::{
+        errno::{self, WasiErrno},
+        ErrorCode, OneSdkError,
+    },
+    handle_map::{Handle, HandleMap},
+    http::{HeadersMultiMap, HttpClient, HttpRequest, HttpResponse},
+    sf_host,
+};
+
+type Ptr = i32;
+
+/// Revision of the host to core ABI implemented by this host.
+pub(crate) const ABI_VERSION: u32 = 1;
+/// Optional features supported by this host, sent to core in the `host-info` response.
+const HOST_FEATURES: &[&str] = &["message_pack"];
+
+/// Encoding of messages exchanged with core.
+///
+/// Messages are JSON until both core and host advertise `message_pack` during `host-info`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MessageEncoding {
+    Json,
+    MessagePack,
+}
+impl MessageEncoding {
+    fn decode(self, message: &[u8]) -> anyhow::Result<CoreMessage> {
+        Ok(match self {
+            Self::Json => serde_json::from_slice(message)?,
+            Self::MessagePack => rmp_serde::from_slice(message)?,
+        })
+    }
+
+    fn encode(self, response: &JsonValue) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Self::Json => serde_json::to_vec(response)?,
+            // structs are encoded as maps so that core can read the `kind` tag
+            Self::MessagePack => rmp_serde::to_vec_named(response)?,
+        })
+    }
+}
+
+/// Stream readable or writable by core through `stream_*` functions.
+enum HostStream {
+    File(File),
+    HttpBody(Box<dyn Read + Send>),
+}
+
+#[derive(Debug, Deserialize)]
+struct PerformException {
+    error_code: String,
+    message: String,
+}
+
+/// Messages sent by core through `message_exchange`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum CoreMessage {
+    HostInfo {
+        /// Optional features supported by core.
+        #[serde(default)]
+        features: Vec<String>,
+    },
+    PerformInput,
+    PerformOutputResult {
+        result: JsonValue,
+    },
+    PerformOutputError {
+        error: JsonValue,
+    },
+    PerformOutputException {
+        exception: PerformException,
+        #[serde(default)]
+        perform_id: Option<String>,
+    },
+    FileOpen {
+        path: String,
+        read: bool,
+        write: bool,
+        append: bool,
+        truncate: bool,
+        create: bool,
+        create_new: bool,
+    },
+    HttpCall {
+        method: String,
+        url: String,
+        headers: HeadersMultiMap,
+        /// Array of numbers in JSON, binary in MessagePack.
+        #[serde(default, with = "serde_bytes")]
+        body: Option<Vec<u8>>,
+    },
+    HttpCallHead {
+        handle: Handle,
+    },
+}
+
+struct PerformState {
+    profile_url: String,
+    provider_url: String,
+    map_url: String,
+    usecase: String,
+    input: JsonValue,
+    parameters: JsonValue,
+    security: JsonValue,
+    output: Option<Result<JsonValue, OneSdkError>>,
+}
+
+/// State of the host exposed to core.
+pub(crate) struct AppState {
+    streams: HandleMap<HostStream>,
+    requests: HandleMap<HttpResponse>,
+    http_client: Arc<dyn HttpClient>,
+    perform_state: Option<PerformState>,
+    message_encoding: MessageEncoding,
+}
+impl AppState {
+    fn new(http_client: Arc<dyn HttpClient>) -> Self {
+        Self {
+            streams: HandleMap::new(),
+            requests: HandleMap::new(),
+            http_client,
+            perform_state: None,
+            message_encoding: MessageEncoding::Json,
+        }
+    }
+
+    /// Handles an encoded message and returns the encoded response.
+    pub(crate) fn handle_message(&mut self, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+        // the response is encoded the same way as the message, even if it changes the encoding
+        let encoding = self.message_encoding;
+        let response = match encoding.decode(message) {
+            Ok(message) => self.handle_core_message(message),
+            Err(err) => json!({ "kind": "err", "error": format!("Invalid message: {}", err) }),
+        };
+
+        encoding.encode(&response)
+    }
+
+    fn handle_core_message(&mut self, message: CoreMessage) -> JsonValue {
+        match message {
+            // compatibility is checked against `oneclient_core_abi_info` in init
+            CoreMessage::HostInfo { features } => {
+                if features.iter().any(|f| f == "message_pack") {
+                    self.message_encoding = MessageEncoding::MessagePack;
+                }
+
+                json!({
+                    "kind": "ok",
+                    "abi_version": ABI_VERSION,
+                    "features": HOST_FEATURES
+                })
+            }
+            CoreMessage::PerformInput => match self.perform_state {
+                None => json!({ "kind": "err", "error": "No perform in progress" }),
+                Some(ref state) => json!({
+                    "kind": "ok",
+                    "profile_url": state.profile_url,
+                    "provider_url": state.provider_url,
+                    "map_url": state.map_url,
+                    "usecase": state.usecase,
+                    "map_input": state.input,
+                    "map_parameters": state.parameters,
+                    "map_security": state.security,
+                }),
+            },
+            CoreMessage::PerformOutputResult { result } => self.set_output(Ok(result)),
+            CoreMessage::PerformOutputError { error } => {
+                self.set_output(Err(OneSdkError::PerformError(error)))
+            }
+            CoreMessage::PerformOutputException {
+                exception,
+                perform_id,
+            } => {
+                let err = if exception.error_code == "InputValidationError" {
+                    OneSdkError::ValidationError {
+                        message: exception.message,
+                        perform_id,
+                    }
+                } else {
+                    OneSdkError::UnexpectedError {
+                        name: exception.error_code,
+                        message: exception.message,
+                        perform_id,
+                    }
+                };
+
+                self.set_output(Err(err))
+            }
+            CoreMessage::FileOpen {
+                path,
+                read,
+                write,
+                append,
+                truncate,
+                create,
+                create_new,
+            } => {
+                let file = OpenOptions::new()
+                    .read(read)
+                    .write(write)
+                    .append(append)
+                    .truncate(truncate)
+                    .create(create)
+                    .create_new(create_new)
+                    .open(path);
+
+                match file {
+                    Ok(file) => {
+                        json!({ "kind": "ok", "stream": self.streams.insert(HostStream::File(file)) })
+                    }
+                    Err(err) => json!({ "kind": "err", "errno": errno::from_io_error(&err) }),
+                }
+            }
+            CoreMessage::HttpCall {
+                method,
+                url,
+                headers,
+                body,
+            } => {
+                let request = HttpRequest {
+                    method,
+                    url,
+                    headers,
+                    body,
+                };
+
+                match self.http_client.fetch(request) {
+                    Ok(response) => {
+                        json!({ "kind": "ok", "handle": self.requests.insert(response) })
+                    }
+                    Err(err) => {
+                        json!({ "kind": "err", "error_code": err.code, "message": err.message })
+                    }
+                }
+            }
+            CoreMessage::HttpCallHead { handle } => match self.requests.remove(handle) {
+                None => json!({
+                    "kind": "err",
+                    "error_code": ErrorCode::NetworkInvalidHandle,
+                    "message": "Invalid http call handle"
+                }),
+                Some(response) => json!({
+                    "kind": "ok",
+                    "status": response.status,
+                    "headers": response.headers,
+                    "body_stream": self.streams.insert(HostStream::HttpBody(response.body))
+                }),
+            },
+        }
+    }
+
+    fn set_output(&mut self, output: Result<JsonValue, OneSdkError>) -> JsonValue {
+        match self.perform_state {
+            None => json!({ "kind": "err", "error": "No perform in progress" }),
+            Some(ref mut state) => {
+                state.output = Some(output);
+                json!({ "kind": "ok" })
+            }
+        }
+    }
+
+    pub(crate) fn stream_read(
+        &mut self,
+        handle: Handle,
+        buf: &mut [u8],
+    ) -> Result<usize, WasiErrno> {
+        let result = match self.streams.get_mut(handle) {
+            None => return Err(errno::EBADF),
+            Some(HostStream::File(file)) => file.read(buf),
+            Some(HostStream::HttpBody(body)) => body.read(buf),
+        };
+
+        result.map_err(|err| errno::from_io_error(&err))
+    }
+
+    pub(crate) fn stream_write(&mut self, handle: Handle, buf: &[u8]) -> Result<usize, WasiErrno> {
+        match self.streams.get_mut(handle) {
+            None => Err(errno::EBADF),
+            Some(HostStream::File(file)) => {
+                file.write(buf).map_err(|err| errno::from_io_error(&err))
+            }
+            // response bodies are only readable
+            Some(HostStream::HttpBody(_)) => Err(errno::EBADF),
+        }
+    }
+
+    pub(crate) fn stream_close(&mut self, handle: Handle) -> Result<(), WasiErrno> {
+        match self.streams.remove(handle) {
+            None => Err(errno::EBADF),
+            Some(HostStream::File(mut file)) => {
+                file.flush().map_err(|err| errno::from_io_error(&err))
+            }
+            Some(HostStream::HttpBody(_)) => Ok(()), // drop cleans up
+        }
+    }
+}
+
+/// Data of the wasmtime store.
+pub(crate) struct HostState {
+    wasi: WasiP1Ctx,
+    pub(crate) app: AppState,
+    /// Responses of `message_exchange` which didn't fit into the buffer provided by core
+    pub(crate) message_store: HandleMap<Vec<u8>>,
+}
+
+/// Description of the ABI implemented by core, returned by `oneclient_core_abi_info`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoreAbiInfo {
+    pub abi_version: u32,
+    pub core_version: String,
+    pub message_kinds: Vec<String>,
+    /// Optional features supported by core.
+    pub features: Vec<String>,
+}
+
+struct AppCore {
+    store: Store<HostState>,
+    memory: Memory,
+    setup_fn: TypedFunc<(), ()>,
+    teardown_fn: TypedFunc<(), ()>,
+    perform_fn: TypedFunc<(), ()>,
+    get_metrics_fn: TypedFunc<(), Ptr>,
+    clear_metrics_fn: TypedFunc<(), ()>,
+    get_developer_dump_fn: TypedFunc<(), Ptr>,
+    /// `None` for cores which predate the ABI handshake.
+    abi_info: Option<CoreAbiInfo>,
+}
+impl AppCore {
+    fn read_i32(&self, ptr: Ptr) -> anyhow::Result<i32> {
+        let mut bytes = [0u8; 4];
+        self.memory
+            .read(&self.store, ptr as u32 as usize, &mut bytes)?;
+
+        Ok(i32::from_le_bytes(bytes))
+    }
+
+    fn read_bytes(&self, ptr: Ptr, len: i32) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = vec![0u8; len as u32 as usize];
+        self.memory
+            .read(&self.store, ptr as u32 as usize, &mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Reads events from an arena, which describes a ring buffer as two slices of null-separated events.
+    fn tracing_events_by_arena(&self, arena_ptr: Ptr) -> anyhow::Result<Vec<String>> {
+        let mut buffer =
+            self.read_bytes(self.read_i32(arena_ptr)?, self.read_i32(arena_ptr + 4)?)?;
+        buffer.extend(self.read_bytes(
+            self.read_i32(arena_ptr + 8)?,
+            self.read_i32(arena_ptr + 12)?,
+        )?);
+
+        let mut events = Vec::new();
+        let mut rest = buffer.as_slice();
+        while let Some(index) = rest.iter().position(|b| *b == 0) {
+            events.push(String::from_utf8_lossy(&rest[..index]).into_owned());
+            rest = &rest[index + 1..];
+        }
+
+        Ok(events)
+    }
+}
+
+/// Runs the core wasm module and handles its requests.
+pub struct WasiApp {
+    engine: Engine,
+    linker: Linker<HostState>,
+    module: Option<Module>,
+    core: Option<AppCore>,
+    http_client: Arc<dyn HttpClient>,
+    developer_dump_dir: PathBuf,
+}
+impl WasiApp {
+    pub fn new(http_client: Arc<dyn HttpClient>) -> Result<Self, OneSdkError> {
+        let engine = Engine::default();
+        let mut linker = Linker::new(&engine);
+
+        wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |state: &mut HostState| {
+            &mut state.wasi
+        })
+        .and_then(|_| sf_host::link(&mut linker))
+        .map_err(|err| OneSdkError::unexpected("UnexpectedError", format!("{:#}", err)))?;
+
+        Ok(Self {
+            engine,
+            linker,
+            module: None,
+            core: None,
+            http_client,
+            developer_dump_dir: PathBuf::from("."),
+        })
+    }
+
+    /// Sets the directory where developer dumps are written when core crashes.
+    pub fn set_developer_dump_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.developer_dump_dir = dir.into();
+    }
+
+    pub fn user_agent() -> String {
+        format!(
+            "one-sdk-rust/{} ({} {})",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        )
+    }
+
+    /// Compiles the core module, accepts both binary and text format.
+    pub fn load_core(&mut self, wasm: &[u8]) -> Result<(), OneSdkError> {
+        let module = Module::new(&self.engine, wasm)
+            .map_err(|err| OneSdkError::unexpected("CoreLoadError", format!("{:#}", err)))?;
+        self.module = Some(module);
+
+        Ok(())
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.core.is_some()
+    }
+
+    /// Returns the ABI information of the initialized core, `None` if not initialized or if core predates the handshake.
+    pub fn core_abi_info(&self) -> Option<&CoreAbiInfo> {
+        self.core.as_ref().and_then(|core| core.abi_info.as_ref())
+    }
+
+    /// Instantiates the loaded core and calls its setup.
+    ///
+    /// Each instance gets a fresh store, so the app can be initialized again after core crashed.
+    pub fn init(&mut self) -> Result<(), OneSdkError> {
+        let module = match self.module {
+            None => {
+                return Err(OneSdkError::unexpected(
+                    "CoreNotLoaded",
+                    "Call load_core first",
+                ))
+            }
+            Some(ref module) => module,
+        };
+        if self.core.is_some() {
+            return Ok(());
+        }
+
+        let wasi = WasiCtxBuilder::new()
+            .inherit_stdout()
+            .inherit_stderr()
+            .inherit_env()
+            .env("ONESDK_DEFAULT_USERAGENT", Self::user_agent())
+            .build_p1();
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                wasi,
+                app: AppState::new(self.http_client.clone()),
+                message_store: HandleMap::new(),
+            },
+        );
+
+        let core = (|| -> anyhow::Result<AppCore> {
+            let instance = self.linker.instantiate(&mut store, module)?;
+            let memory = instance
+                .get_memory(&mut store, "memory")
+                .ok_or_else(|| anyhow::anyhow!("Core does not export memory"))?;
+
+            let mut core = AppCore {
+                memory,
+                setup_fn: instance.get_typed_func(&mut store, "oneclient_core_setup")?,
+                teardown_fn: instance.get_typed_func(&mut store, "oneclient_core_teardown")?,
+                perform_fn: instance.get_typed_func(&mut store, "oneclient_core_perform")?,
+                get_metrics_fn: instance
+                    .get_typed_func(&mut store, "oneclient_core_get_metrics")?,
+                clear_metrics_fn: instance
+                    .get_typed_func(&mut store, "oneclient_core_clear_metrics")?,
+                get_developer_dump_fn: instance
+                    .get_typed_func(&mut store, "oneclient_core_get_developer_dump")?,
+                abi_info: None,
+                store,
+            };
+            if let Some(abi_info_fn) = instance.get_func(&mut core.store, "oneclient_core_abi_info")
+            {
+                let ptr = abi_info_fn
+                    .typed::<(), Ptr>(&core.store)?
+                    .call(&mut core.store, ())?;
+                let abi_info = core.read_bytes(core.read_i32(ptr)?, core.read_i32(ptr + 4)?)?;
+                core.abi_info = Some(serde_json::from_slice(&abi_info)?);
+            }
+
+            Ok(core)
+        })()
+        .map_err(|err| OneSdkError::unexpected("CoreLoadError", format!("{:#}", err)))?;
+
+        if let Some(ref abi_info) = core.abi_info {
+            if abi_info.abi_version != ABI_VERSION {
+                return Err(OneSdkError::unexpected(
+                    "CoreIncompatible",
+                    format!(
+                        "Core {} implements ABI version {}, but host implements ABI version {}",
+                        abi_info.core_version, abi_info.abi_version, ABI_VERSION
+                    ),
+                ));
+            }
+        }
+
+        self.core = Some(core);
+        self.call(|core| core.setup_fn.call(&mut core.store, ()))
+    }
+
+    pub fn destroy(&mut self) {
+        if self.core.is_some() {
+            // a failed teardown already dropped core
+            let _ = self.call(|core| core.teardown_fn.call(&mut core.store, ()));
+            self.core = None;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn perform(
+        &mut self,
+        profile_url: &str,
+        provider_url: &str,
+        map_url: &str,
+        usecase: &str,
+        input: JsonValue,
+        parameters: JsonValue,
+        security: JsonValue,
+    ) -> Result<JsonValue, OneSdkError> {
+        let core = self.core.as_mut().ok_or(OneSdkError::UninitializedError)?;
+        core.store.data_mut().app.perform_state = Some(PerformState {
+            profile_url: profile_url.to_string(),
+            provider_url: provider_url.to_string(),
+            map_url: map_url.to_string(),
+            usecase: usecase.to_string(),
+            input,
+            parameters,
+            security,
+            output: None,
+        });
+
+        self.call(|core| core.perform_fn.call(&mut core.store, ()))?;
+
+        let state = self
+            .core
+            .as_mut()
+            .and_then(|core| core.store.data_mut().app.perform_state.take());
+        match state.and_then(|state| state.output) {
+            Some(output) => output,
+            None => Err(OneSdkError::unexpected(
+                "UnexpectedError",
+                "Core did not set perform output",
+            )),
+        }
+    }
+
+    /// Takes metrics events collected by core since the last call.
+    pub fn take_metrics(&mut self) -> Result<Vec<String>, OneSdkError> {
+        if self.core.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let events = self.call(|core| {
+            let arena_ptr = core.get_metrics_fn.call(&mut core.store, ())?;
+            core.tracing_events_by_arena(arena_ptr)
+        })?;
+        self.call(|core| core.clear_metrics_fn.call(&mut core.store, ()))?;
+
+        Ok(events)
+    }
+
+    /// Calls into core, dropping core and writing a developer dump if it fails.
+    fn call<R>(
+        &mut self,
+        f: impl FnOnce(&mut AppCore) -> anyhow::Result<R>,
+    ) -> Result<R, OneSdkError> {
+        let core = self.core.as_mut().ok_or(OneSdkError::UninitializedError)?;
+
+        match f(core) {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                let name = if err.is::<Trap>() {
+                    "WebAssemblyRuntimeError"
+                } else {
+                    "UnexpectedError"
+                };
+
+                let mut core = self.core.take().unwrap();
+                let message = match self.create_developer_dump(&mut core) {
+                    Ok(()) => format!("Error while executing WebAssembly: {:#}", err),
+                    Err(dump_err) => format!(
+                        "Error while executing WebAssembly: {:#}, error during dumping: {:#}",
+                        err, dump_err
+                    ),
+                };
+
+                Err(OneSdkError::unexpected(name, message))
+            }
+        }
+    }
+
+    fn create_developer_dump(&self, core: &mut AppCore) -> anyhow::Result<()> {
+        let arena_ptr = core.get_developer_dump_fn.call(&mut core.store, ())?;
+        let events = core.tracing_events_by_arena(arena_ptr)?;
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = self
+            .developer_dump_dir
+            .join(format!("onesdk_devlog_dump_{}.txt", timestamp));
+        std::fs::write(path, events.concat())?;
+
+        Ok(())
+    }
+}
+impl Drop for WasiApp {
+    fn drop(&mut self) {
+        self.destroy();
+    }
+}
+
+#[cfg(all(test, feature = "ureq"))]
+mod test {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+    use crate::http::UreqHttpClient;
+
+    /// Serves one request and responds with its request line.
+    fn http_stub() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+            }
+
+            let body = request_line.trim();
+            write!(
+                stream,
+                "HTTP/1.1 201 Created\r\nContent-Type: text/plain\r\nX-Test: a\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+
+        format!("http://{}", address)
+    }
+
+    fn send(state: &mut AppState, message: JsonValue) -> JsonValue {
+        let encoding = state.message_encoding;
+        let response = state
+            .handle_message(&encoding.encode(&message).unwrap())
+            .unwrap();
+
+        match encoding {
+            MessageEncoding::Json => serde_json::from_slice(&response).unwrap(),
+            MessageEncoding::MessagePack => rmp_serde::from_slice(&response).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_http_call() {
+        let mut state = AppState::new(Arc::new(UreqHttpClient::new()));
+        let url = http_stub();
+
+        let response = send(
+            &mut state,
+            json!({
+                "kind": "http-call",
+                "method": "GET",
+                "url": format!("{}/api/1?q=2", url),
+                "headers": { "accept": ["text/plain"] },
+                "body": null
+            }),
+        );
+        assert_eq!(response["kind"], "ok");
+
+        let response = send(
+            &mut state,
+            json!({ "kind": "http-call-head", "handle": response["handle"] }),
+        );
+        assert_eq!(response["kind"], "ok");
+        assert_eq!(response["status"], 201);
+        assert_eq!(response["headers"]["x-test"], json!(["a"]));
+
+        let body_stream = response["body_stream"].as_u64().unwrap() as Handle;
+        let mut body = vec![0u8; 64];
+        let count = state.stream_read(body_stream, &mut body).unwrap();
+        assert_eq!(&body[..count], b"GET /api/1?q=2 HTTP/1.1");
+        assert_eq!(state.stream_write(body_stream, b"data"), Err(errno::EBADF));
+        assert_eq!(state.stream_close(body_stream), Ok(()));
+        assert_eq!(state.stream_close(body_stream), Err(errno::EBADF));
+    }
+
+    #[test]
+    fn test_http_call_connection_refused() {
+        let mut state = AppState::new(Arc::new(UreqHttpClient::new()));
+        // bind and drop to get a port nobody listens on
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let response = send(
+            &mut state,
+            json!({
+                "kind": "http-call",
+                "method": "GET",
+                "url": format!("http://{}/", address),
+                "headers": {},
+                "body": null
+            }),
+        );
+        assert_eq!(response["kind"], "err");
+        assert_eq!(response["error_code"], "network:ECONNREFUSED");
+
+        let response = send(&mut state, json!({ "kind": "http-call-head", "handle": 1 }));
+        assert_eq!(response["error_code"], "network:invalid_handle");
+    }
+
+    #[test]
+    fn test_file_open() {
+        let mut state = AppState::new(Arc::new(UreqHttpClient::new()));
+        let path = std::env::temp_dir().join(format!("one_sdk_test_{}.txt", std::process::id()));
+
+        let open = |state: &mut AppState, write: bool| {
+            send(
+                state,
+                json!({
+                    "kind": "file-open",
+                    "path": path.to_str().unwrap(),
+                    "read": !write,
+                    "write": write,
+                    "append": false,
+                    "truncate": write,
+                    "create": write,
+                    "create_new": false
+                }),
+            )
+        };
+
+        assert_eq!(open(&mut state, false)["errno"], errno::ENOENT);
+
+        let stream = open(&mut state, true)["stream"].as_u64().unwrap() as Handle;
+        assert_eq!(state.stream_write(stream, b"hello"), Ok(5));
+        assert_eq!(state.stream_close(stream), Ok(()));
+
+        let stream = open(&mut state, false)["stream"].as_u64().unwrap() as Handle;
+        let mut data = vec![0u8; 16];
+        assert_eq!(state.stream_read(stream, &mut data), Ok(5));
+        assert_eq!(&data[..5], b"hello");
+        assert_eq!(state.stream_close(stream), Ok(()));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_perform_messages() {
+        let mut state = AppState::new(Arc::new(UreqHttpClient::new()));
+
+        assert_eq!(
+            send(&mut state, json!({ "kind": "perform-input" }))["kind"],
+            "err"
+        );
+        assert_eq!(
+            send(&mut state, json!({ "kind": "unknown" }))["kind"],
+            "err"
+        );
+
+        state.perform_state = Some(PerformState {
+            profile_url: "file://profile".into(),
+            provider_url: "file://provider".into(),
+            map_url: "file://map".into(),
+            usecase: "Example".into(),
+            input: json!({ "id": 1 }),
+            parameters: json!({}),
+            security: json!({}),
+            output: None,
+        });
+        let input = send(&mut state, json!({ "kind": "perform-input" }));
+        assert_eq!(input["usecase"], "Example");
+        assert_eq!(input["map_input"], json!({ "id": 1 }));
+
+        send(
+            &mut state,
+            json!({
+                "kind": "perform-output-exception",
+                "exception": { "error_code": "InputValidationError", "message": "Invalid input" },
+                "perform_id": "abc"
+            }),
+        );
+        assert!(matches!(
+            state.perform_state.unwrap().output,
+            Some(Err(OneSdkError::ValidationError { perform_id: Some(ref id), .. })) if id == "abc"
+        ));
+    }
+
+    #[test]
+    fn test_host_info() {
+        let mut state = AppState::new(Arc::new(UreqHttpClient::new()));
+
+        let response = send(
+            &mut state,
+            json!({
+                "kind": "host-info",
+                "abi_version": ABI_VERSION,
+                "core_version": "0.1.0",
+                "features": ["trace_context"]
+            }),
+        );
+        assert_eq!(response["kind"], "ok");
+        assert_eq!(response["abi_version"], ABI_VERSION);
+        assert_eq!(state.message_encoding, MessageEncoding::Json);
+    }
+
+    #[test]
+    fn test_message_pack() {
+        let mut state = AppState::new(Arc::new(UreqHttpClient::new()));
+
+        // the host-info response itself is still JSON
+        let response = state
+            .handle_message(
+                &serde_json::to_vec(&json!({
+                    "kind": "host-info",
+                    "abi_version": ABI_VERSION,
+                    "core_version": "0.1.0",
+                    "features": ["message_pack"]
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        let response: JsonValue = serde_json::from_slice(&response).unwrap();
+        assert_eq!(response["features"], json!(["message_pack"]));
+        assert_eq!(state.message_encoding, MessageEncoding::MessagePack);
+
+        state.perform_state = Some(PerformState {
+            profile_url: "file://profile".into(),
+            provider_url: "file://provider".into(),
+            map_url: "file://map".into(),
+            usecase: "Example".into(),
+            input: json!({ "id": 1 }),
+            parameters: json!({}),
+            security: json!({}),
+            output: None,
+        });
+        let input = send(&mut state, json!({ "kind": "perform-input" }));
+        assert_eq!(input["map_input"], json!({ "id": 1 }));
+
+        #[derive(serde::Serialize)]
+        struct HttpCall<'a> {
+            kind: &'a str,
+            method: &'a str,
+            url: &'a str,
+            headers: HeadersMultiMap,
+            #[serde(with = "serde_bytes")]
+            body: Option<&'a [u8]>,
+        }
+        let message = rmp_serde::to_vec_named(&HttpCall {
+            kind: "http-call",
+            method: "POST",
+            url: "http://localhost",
+            headers: HeadersMultiMap::new(),
+            body: Some(&[0, 159, 146, 150]),
+        })
+        .unwrap();
+        match MessageEncoding::MessagePack.decode(&message).unwrap() {
+            CoreMessage::HttpCall { body, .. } => assert_eq!(body, Some(vec![0, 159, 146, 150])),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{app::WasiApp, error::OneSdkError, http::HttpClient};
+
+/// Security values of a provider, e.g. `{ "basic_auth": { "username": "..", "password": ".." } }`.
+pub type SecurityValuesMap = HashMap<String, HashMap<String, String>>;
+
+/// Path to the core, can be overridden by `ONESDK_CORE_PATH` at runtime.
+const DEFAULT_CORE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/core.wasm");
+
+/// Client performing use-cases using profiles, providers and maps from a local assets directory.
+pub struct OneClient {
+    assets_path: PathBuf,
+    core_path: PathBuf,
+    app: WasiApp,
+}
+impl OneClient {
+    /// Creates a client loading documents from `assets_path`.
+    #[cfg(feature = "ureq")]
+    pub fn new(assets_path: impl Into<PathBuf>) -> Result<Self, OneSdkError> {
+        Self::with_http_client(assets_path, crate::http::UreqHttpClient::new())
+    }
+
+    /// Creates a client which executes HTTP calls using `http_client`.
+    pub fn with_http_client(
+        assets_path: impl Into<PathBuf>,
+        http_client: impl HttpClient + 'static,
+    ) -> Result<Self, OneSdkError> {
+        let core_path = std::env::var_os("ONESDK_CORE_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CORE_PATH));
+
+        Ok(Self {
+            assets_path: assets_path.into(),
+            core_path,
+            app: WasiApp::new(Arc::new(http_client))?,
+        })
+    }
+
+    /// Sets the path of the core wasm module, takes effect on the next [init](Self::init).
+    pub fn set_core_path(&mut self, core_path: impl Into<PathBuf>) {
+        self.core_path = core_path.into();
+    }
+
+    /// Sets the directory where developer dumps are written when core crashes.
+    pub fn set_developer_dump_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.app.set_developer_dump_dir(dir);
+    }
+
+    fn file_url(path: &Path) -> String {
+        format!("file://{}", path.display())
+    }
+
+    fn resolve_profile_url(&self, profile: &str) -> String {
+        let resolved_profile = profile.replace('/', ".");
+        let path = self
+            .assets_path
+            .join(format!("{}.profile.ts", resolved_profile));
+        // migration from Comlink to TypeScript profiles
+        let path_comlink = self
+            .assets_path
+            .join(format!("{}.profile", resolved_profile));
+        if !path.exists() && path_comlink.exists() {
+            return Self::file_url(&path_comlink);
+        }
+
+        Self::file_url(&path)
+    }
+
+    fn resolve_map_url(&self, profile: &str, provider: &str) -> String {
+        let resolved_profile = profile.replace('/', ".");
+        Self::file_url(
+            &self
+                .assets_path
+                .join(format!("{}.{}.map.js", resolved_profile, provider)),
+        )
+    }
+
+    fn resolve_provider_url(&self, provider: &str) -> String {
+        Self::file_url(&self.assets_path.join(format!("{}.provider.json", provider)))
+    }
+
+    /// Loads and sets up core, does nothing if already initialized.
+    pub fn init(&mut self) -> Result<(), OneSdkError> {
+        if self.app.is_initialized() {
+            return Ok(());
+        }
+
+        let wasm = std::fs::read(&self.core_path).map_err(|err| {
+            OneSdkError::unexpected(
+                "CoreLoadError",
+                format!(
+                    "Failed to read core from {}: {}",
+                    self.core_path.display(),
+                    err
+                ),
+            )
+        })?;
+        self.app.load_core(&wasm)?;
+        self.app.init()
+    }
+
+    pub fn destroy(&mut self) {
+        self.app.destroy();
+    }
+
+    /// Performs `usecase` of `profile` using `provider`.
+    ///
+    /// Core is initialized on first use and reinitialized after it crashes.
+    pub fn perform<I: Serialize, O: DeserializeOwned>(
+        &mut self,
+        profile: &str,
+        provider: &str,
+        usecase: &str,
+        input: &I,
+        parameters: &HashMap<String, String>,
+        security: &SecurityValuesMap,
+    ) -> Result<O, OneSdkError> {
+        self.init()?;
+
+        let to_json = |value: serde_json::Result<serde_json::Value>| {
+            value.map_err(|err| OneSdkError::unexpected("SerializationError", err.to_string()))
+        };
+        let input = to_json(serde_json::to_value(input))?;
+        let parameters = to_json(serde_json::to_value(parameters))?;
+        let security = to_json(serde_json::to_value(security))?;
+
+        let result = self.app.perform(
+            &self.resolve_profile_url(profile),
+            &self.resolve_provider_url(provider),
+            &self.resolve_map_url(profile, provider),
+            usecase,
+            input,
+            parameters,
+            security,
+        )?;
+
+        serde_json::from_value(result)
+            .map_err(|err| OneSdkError::unexpected("DeserializationError", err.to_string()))
+    }
+
+    /// Takes metrics events collected by core since the last call, so that they can be forwarded.
+    pub fn take_metrics(&mut self) -> Result<Vec<String>, OneSdkError> {
+        self.app.take_metrics()
+    }
+}
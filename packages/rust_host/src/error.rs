@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// Error codes of host errors, as understood by core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+    #[serde(rename = "network:error")]
+    NetworkError,
+    #[serde(rename = "network:ECONNREFUSED")]
+    NetworkConnectionRefused,
+    #[serde(rename = "network:ENOTFOUND")]
+    NetworkHostNotFound,
+    #[serde(rename = "network:invalid_url")]
+    NetworkInvalidUrl,
+    #[serde(rename = "network:invalid_handle")]
+    NetworkInvalidHandle,
+}
+
+/// Error of a host operation requested by core.
+#[derive(Debug, thiserror::Error)]
+#[error("{code:?}: {message}")]
+pub struct HostError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+impl HostError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// WASI errno values, see <https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md#-errno-variant>.
+pub mod errno {
+    pub type WasiErrno = u32;
+
+    pub const SUCCESS: WasiErrno = 0;
+    pub const EACCES: WasiErrno = 2;
+    pub const EBADF: WasiErrno = 8;
+    pub const EEXIST: WasiErrno = 20;
+    pub const EINVAL: WasiErrno = 28;
+    pub const EIO: WasiErrno = 29;
+    pub const EISDIR: WasiErrno = 31;
+    pub const ENOENT: WasiErrno = 44;
+    pub const ENOTSUP: WasiErrno = 58;
+    pub const ERANGE: WasiErrno = 68;
+
+    pub fn from_io_error(err: &std::io::Error) -> WasiErrno {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => ENOENT,
+            std::io::ErrorKind::PermissionDenied => EACCES,
+            std::io::ErrorKind::AlreadyExists => EEXIST,
+            std::io::ErrorKind::InvalidInput => EINVAL,
+            std::io::ErrorKind::Unsupported => ENOTSUP,
+            _ => match err.raw_os_error() {
+                // EISDIR on posix platforms
+                Some(21) if cfg!(unix) => EISDIR,
+                _ => EIO,
+            },
+        }
+    }
+}
+
+/// Error returned from [OneClient::perform](crate::OneClient::perform).
+#[derive(Debug, thiserror::Error)]
+pub enum OneSdkError {
+    /// Error defined in the profile and returned by the map.
+    #[error("PerformError: {0}")]
+    PerformError(JsonValue),
+    /// Input or parameters did not pass validation.
+    #[error("ValidationError: {message}")]
+    ValidationError {
+        message: String,
+        perform_id: Option<String>,
+    },
+    /// Any other error, including a crashed core.
+    #[error("{name}: {message}")]
+    UnexpectedError {
+        name: String,
+        message: String,
+        perform_id: Option<String>,
+    },
+    #[error("Uninitialized: OneClient isn't initialized.")]
+    UninitializedError,
+}
+impl OneSdkError {
+    pub(crate) fn unexpected(name: &str, message: impl Into<String>) -> Self {
+        Self::UnexpectedError {
+            name: name.to_string(),
+            message: message.into(),
+            perform_id: None,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+pub type Handle = u32;
+
+/// Map of values indexed by handles passed to core.
+///
+/// Handles start at 1 so that 0 can be used as "no handle" and are never reused.
+pub struct HandleMap<T> {
+    next: Handle,
+    data: HashMap<Handle, T>,
+}
+impl<T> HandleMap<T> {
+    pub fn new() -> Self {
+        Self {
+            next: 1,
+            data: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> Handle {
+        let handle = self.next;
+        self.next += 1;
+        self.data.insert(handle, value);
+
+        handle
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.data.get_mut(&handle)
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        self.data.remove(&handle)
+    }
+}
+impl<T> Default for HandleMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, io::Read};
+
+use crate::error::HostError;
+
+/// Header names mapped to all their values.
+pub type HeadersMultiMap = HashMap<String, Vec<String>>;
+
+/// Request sent by the map.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    /// HTTP method - to be used as-is.
+    pub method: String,
+    /// Url including the query.
+    pub url: String,
+    pub headers: HeadersMultiMap,
+    pub body: Option<Vec<u8>>,
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HeadersMultiMap,
+    /// Body stream, read by core on demand.
+    pub body: Box<dyn Read + Send>,
+}
+impl std::fmt::Debug for HttpResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Client used to execute HTTP calls of maps.
+///
+/// Redirects must not be followed, core follows them after checking each location. Responses must be returned as-is
+/// regardless of their status code.
+pub trait HttpClient: Send + Sync {
+    fn fetch(&self, request: HttpRequest) -> Result<HttpResponse, HostError>;
+}
+
+#[cfg(feature = "ureq")]
+pub use self::ureq_client::UreqHttpClient;
+
+#[cfg(feature = "ureq")]
+mod ureq_client {
+    use std::time::Duration;
+
+    use super::{HeadersMultiMap, HttpClient, HttpRequest, HttpResponse};
+    use crate::error::{ErrorCode, HostError};
+
+    /// Default [HttpClient] implemented using [ureq].
+    pub struct UreqHttpClient {
+        agent: ureq::Agent,
+    }
+    impl UreqHttpClient {
+        pub fn new() -> Self {
+            Self {
+                agent: ureq::AgentBuilder::new()
+                    .redirects(0)
+                    .timeout_connect(Duration::from_secs(30))
+                    .build(),
+            }
+        }
+
+        fn into_response(response: ureq::Response) -> HttpResponse {
+            let mut headers = HeadersMultiMap::new();
+            for name in response.headers_names() {
+                let values = response.all(&name).into_iter().map(String::from).collect();
+                headers.insert(name, values);
+            }
+
+            HttpResponse {
+                status: response.status(),
+                headers,
+                body: response.into_reader(),
+            }
+        }
+    }
+    impl Default for UreqHttpClient {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+    impl HttpClient for UreqHttpClient {
+        fn fetch(&self, request: HttpRequest) -> Result<HttpResponse, HostError> {
+            let mut call = self.agent.request(&request.method, &request.url);
+            for (name, values) in request.headers.iter() {
+                // ureq replaces header values, so multiple values are joined into one
+                call = call.set(name, &values.join(", "));
+            }
+
+            let result = match request.body {
+                Some(body) => call.send_bytes(&body),
+                None => call.call(),
+            };
+            match result {
+                Ok(response) => Ok(Self::into_response(response)),
+                // error status codes are still valid responses for the map
+                Err(ureq::Error::Status(_, response)) => Ok(Self::into_response(response)),
+                Err(ureq::Error::Transport(err)) => {
+                    let code = match err.kind() {
+                        ureq::ErrorKind::InvalidUrl | ureq::ErrorKind::UnknownScheme => {
+                            ErrorCode::NetworkInvalidUrl
+                        }
+                        ureq::ErrorKind::Dns => ErrorCode::NetworkHostNotFound,
+                        ureq::ErrorKind::ConnectionFailed => ErrorCode::NetworkConnectionRefused,
+                        _ => ErrorCode::NetworkError,
+                    };
+
+                    Err(HostError::new(code, err.to_string()))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! OneSDK host for Rust.
+//!
+//! Runs the OneSDK core wasm module in an embedded wasmtime runtime and implements the `sf_host_unstable` imports
+//! it needs: message exchange, streams, file access and HTTP calls through a pluggable [HttpClient].
+
+mod app;
+mod client;
+mod error;
+mod handle_map;
+mod http;
+mod sf_host;
+
+pub use app::{CoreAbiInfo, WasiApp};
+pub use client::{OneClient, SecurityValuesMap};
+pub use error::{ErrorCode, HostError, OneSdkError};
+#[cfg(feature = "ureq")]
+pub use http::UreqHttpClient;
+pub use http::{HeadersMultiMap, HttpClient, HttpRequest, HttpResponse};
@@ -0,0 +1,161 @@
+//! Implementation of the `sf_host_unstable` module imported by core.
+//!
+//! All pointers and sizes are 32-bit as core is a wasm32 module. Functions returning [AbiResult] encode the value in the
+//! lower 31 bits and set the upper bit on error, in which case the value is a WASI errno.
+
+use wasmtime::{Caller, Extern, Linker, Memory};
+
+use crate::{
+    app::HostState,
+    error::errno::{self, WasiErrno},
+    handle_map::Handle,
+};
+
+const MODULE: &str = "sf_host_unstable";
+
+type Ptr = i32;
+type Size = i32;
+type AbiResult = i32;
+
+fn abi_ok(value: usize) -> AbiResult {
+    (value as u32 & 0x7FFF_FFFF) as AbiResult
+}
+fn abi_err(errno: WasiErrno) -> AbiResult {
+    ((errno & 0x7FFF_FFFF) | 0x8000_0000) as AbiResult
+}
+
+fn memory(caller: &mut Caller<'_, HostState>) -> anyhow::Result<Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => anyhow::bail!("Core does not export memory"),
+    }
+}
+
+fn message_exchange(
+    mut caller: Caller<'_, HostState>,
+    msg_ptr: Ptr,
+    msg_len: Size,
+    out_ptr: Ptr,
+    out_len: Size,
+    ret_handle: Ptr,
+) -> anyhow::Result<Size> {
+    let memory = memory(&mut caller)?;
+
+    let mut message = vec![0u8; msg_len as u32 as usize];
+    memory.read(&caller, msg_ptr as u32 as usize, &mut message)?;
+
+    let response = caller.data_mut().app.handle_message(&message)?;
+    let response_len = response.len();
+
+    // responses which don't fit are stored and retrieved by core in a second call
+    let message_handle: Handle = if response_len > out_len as u32 as usize {
+        caller.data_mut().message_store.insert(response)
+    } else {
+        memory.write(&mut caller, out_ptr as u32 as usize, &response)?;
+        0
+    };
+    memory.write(
+        &mut caller,
+        ret_handle as u32 as usize,
+        &message_handle.to_le_bytes(),
+    )?;
+
+    Ok(abi_ok(response_len))
+}
+
+fn message_exchange_retrieve(
+    mut caller: Caller<'_, HostState>,
+    handle: i32,
+    out_ptr: Ptr,
+    out_len: Size,
+) -> anyhow::Result<AbiResult> {
+    let memory = memory(&mut caller)?;
+
+    let response = match caller.data_mut().message_store.remove(handle as Handle) {
+        None => return Ok(abi_err(errno::EBADF)),
+        Some(response) => response,
+    };
+    if (out_len as u32 as usize) < response.len() {
+        return Ok(abi_err(errno::ERANGE));
+    }
+    memory.write(&mut caller, out_ptr as u32 as usize, &response)?;
+
+    Ok(abi_ok(response.len()))
+}
+
+fn stream_read(
+    mut caller: Caller<'_, HostState>,
+    handle: i32,
+    out_ptr: Ptr,
+    out_len: Size,
+) -> anyhow::Result<AbiResult> {
+    let memory = memory(&mut caller)?;
+
+    let mut buffer = vec![0u8; out_len as u32 as usize];
+    let count = match caller
+        .data_mut()
+        .app
+        .stream_read(handle as Handle, &mut buffer)
+    {
+        Err(errno) => return Ok(abi_err(errno)),
+        Ok(count) => count,
+    };
+    memory.write(&mut caller, out_ptr as u32 as usize, &buffer[..count])?;
+
+    Ok(abi_ok(count))
+}
+
+fn stream_write(
+    mut caller: Caller<'_, HostState>,
+    handle: i32,
+    in_ptr: Ptr,
+    in_len: Size,
+) -> anyhow::Result<AbiResult> {
+    let memory = memory(&mut caller)?;
+
+    let mut buffer = vec![0u8; in_len as u32 as usize];
+    memory.read(&caller, in_ptr as u32 as usize, &mut buffer)?;
+
+    Ok(
+        match caller
+            .data_mut()
+            .app
+            .stream_write(handle as Handle, &buffer)
+        {
+            Err(errno) => abi_err(errno),
+            Ok(count) => abi_ok(count),
+        },
+    )
+}
+
+fn stream_close(mut caller: Caller<'_, HostState>, handle: i32) -> AbiResult {
+    match caller.data_mut().app.stream_close(handle as Handle) {
+        Err(errno) => abi_err(errno),
+        Ok(()) => abi_ok(errno::SUCCESS as usize),
+    }
+}
+
+pub(crate) fn link(linker: &mut Linker<HostState>) -> anyhow::Result<()> {
+    linker.func_wrap(MODULE, "message_exchange", message_exchange)?;
+    linker.func_wrap(
+        MODULE,
+        "message_exchange_retrieve",
+        message_exchange_retrieve,
+    )?;
+    linker.func_wrap(MODULE, "stream_read", stream_read)?;
+    linker.func_wrap(MODULE, "stream_write", stream_write)?;
+    linker.func_wrap(MODULE, "stream_close", stream_close)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_abi_result() {
+        assert_eq!(abi_ok(12), 12);
+        assert_eq!(abi_err(errno::EBADF) as u32, 0x8000_0008);
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use one_sdk::{HostError, HttpClient, HttpRequest, HttpResponse, OneSdkError, WasiApp};
+use serde_json::json;
+
+struct NoHttpClient;
+impl HttpClient for NoHttpClient {
+    fn fetch(&self, _request: HttpRequest) -> Result<HttpResponse, HostError> {
+        unimplemented!()
+    }
+}
+
+/// Minimal core in text format.
+///
+/// The default perform requests perform input into a buffer which is too small, retrieves it and sends the whole input
+/// response back as the perform result. Both metrics and developer dump contain one `dump` event.
+fn fake_core(perform_body: Option<&str>, extra: &str) -> String {
+    let echo_body = r#"
+        (local $len i32)
+        (local.set $len (call $message_exchange (i32.const 0) (i32.const 24) (i32.const 1024) (i32.const 8) (i32.const 256)))
+        (if (i32.eqz (i32.load (i32.const 256))) (then unreachable))
+        (if (i32.ne (call $message_exchange_retrieve (i32.load (i32.const 256)) (i32.const 1024) (i32.const 4096)) (local.get $len)) (then unreachable))
+        (i32.store8 (i32.add (i32.const 1024) (local.get $len)) (i32.const 125))
+        (drop (call $message_exchange (i32.const 983) (i32.add (local.get $len) (i32.const 42)) (i32.const 8192) (i32.const 64) (i32.const 256)))
+    "#;
+
+    format!(
+        r#"(module
+            (import "sf_host_unstable" "message_exchange" (func $message_exchange (param i32 i32 i32 i32 i32) (result i32)))
+            (import "sf_host_unstable" "message_exchange_retrieve" (func $message_exchange_retrieve (param i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "{{\"kind\":\"perform-input\"}}")
+            (data (i32.const 983) "{{\"kind\":\"perform-output-result\",\"result\":")
+            (data (i32.const 512) "\58\02\00\00\05\00\00\00\00\00\00\00\00\00\00\00")
+            (data (i32.const 600) "dump\00")
+            (func (export "oneclient_core_setup"))
+            (func (export "oneclient_core_teardown"))
+            (func (export "oneclient_core_perform") {})
+            (func (export "oneclient_core_get_metrics") (result i32) (i32.const 512))
+            (func (export "oneclient_core_clear_metrics"))
+            (func (export "oneclient_core_get_developer_dump") (result i32) (i32.const 512))
+            {}
+        )"#,
+        perform_body.unwrap_or(echo_body),
+        extra
+    )
+}
+
+fn app(perform_body: Option<&str>) -> WasiApp {
+    app_with_extra(perform_body, "")
+}
+
+fn app_with_extra(perform_body: Option<&str>, extra: &str) -> WasiApp {
+    let mut app = WasiApp::new(Arc::new(NoHttpClient)).unwrap();
+    app.load_core(fake_core(perform_body, extra).as_bytes())
+        .unwrap();
+
+    app
+}
+
+/// Exports `oneclient_core_abi_info` of a core implementing `abi_version`.
+fn abi_info_export(abi_version: u32) -> String {
+    let abi_info = format!(
+        r#"{{\"abi_version\":{},\"core_version\":\"0.1.0\",\"message_kinds\":[],\"features\":[]}}"#,
+        abi_version
+    );
+    let abi_info_len = abi_info.len() - abi_info.matches('\\').count();
+
+    format!(
+        r#"(data (i32.const 16384) "\10\40\00\00\{:02x}\00\00\00")
+        (data (i32.const 16400) "{}")
+        (func (export "oneclient_core_abi_info") (result i32) (i32.const 16384))"#,
+        abi_info_len, abi_info
+    )
+}
+
+fn perform(app: &mut WasiApp) -> Result<serde_json::Value, OneSdkError> {
+    app.perform(
+        "file://profile",
+        "file://provider",
+        "file://map",
+        "Example",
+        json!({ "id": 1 }),
+        json!({ "PARAM": "value" }),
+        json!({}),
+    )
+}
+
+#[test]
+fn test_uninitialized() {
+    let mut app = app(None);
+
+    assert!(matches!(
+        perform(&mut app),
+        Err(OneSdkError::UninitializedError)
+    ));
+}
+
+#[test]
+fn test_perform() {
+    let mut app = app(None);
+    app.init().unwrap();
+
+    let result = perform(&mut app).unwrap();
+    assert_eq!(result["kind"], "ok");
+    assert_eq!(result["usecase"], "Example");
+    assert_eq!(result["map_input"], json!({ "id": 1 }));
+    assert_eq!(result["map_parameters"], json!({ "PARAM": "value" }));
+
+    assert_eq!(app.take_metrics().unwrap(), vec!["dump".to_string()]);
+    app.destroy();
+}
+
+#[test]
+fn test_trapped_core() {
+    let dump_dir = std::env::temp_dir().join(format!("one_sdk_test_dump_{}", std::process::id()));
+    std::fs::create_dir_all(&dump_dir).unwrap();
+
+    let mut app = app(Some("unreachable"));
+    app.set_developer_dump_dir(&dump_dir);
+    app.init().unwrap();
+
+    match perform(&mut app) {
+        Err(OneSdkError::UnexpectedError { name, .. }) => {
+            assert_eq!(name, "WebAssemblyRuntimeError")
+        }
+        other => panic!("Unexpected perform result: {:?}", other),
+    }
+    assert!(!app.is_initialized());
+
+    let dumps: Vec<_> = std::fs::read_dir(&dump_dir).unwrap().collect();
+    assert_eq!(dumps.len(), 1);
+    let dump = std::fs::read_to_string(dumps[0].as_ref().unwrap().path()).unwrap();
+    assert_eq!(dump, "dump");
+    std::fs::remove_dir_all(&dump_dir).unwrap();
+
+    // core can be initialized again after it crashed
+    app.init().unwrap();
+    assert!(app.is_initialized());
+}
+
+#[test]
+fn test_core_abi_info() {
+    let mut core_app = app_with_extra(None, &abi_info_export(1));
+    core_app.init().unwrap();
+    let abi_info = core_app.core_abi_info().unwrap();
+    assert_eq!(abi_info.abi_version, 1);
+    assert_eq!(abi_info.core_version, "0.1.0");
+
+    // cores which predate the handshake are accepted
+    let mut legacy_app = app(None);
+    legacy_app.init().unwrap();
+    assert!(legacy_app.core_abi_info().is_none());
+}
+
+#[test]
+fn test_incompatible_core() {
+    let mut app = app_with_extra(None, &abi_info_export(2));
+
+    assert!(matches!(
+        app.init(),
+        Err(OneSdkError::UnexpectedError { ref name, .. }) if name == "CoreIncompatible"
+    ));
+    assert!(!app.is_initialized());
+}
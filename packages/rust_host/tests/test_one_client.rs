@@ -0,0 +1,67 @@
+//! Tests against the mocked core, which is copied into assets by `make test_rust_host`.
+//!
+//! They are ignored by default because they need the asset, `make test_rust_host` and CI run them with `--include-ignored`.
+#![cfg(feature = "ureq")]
+
+use std::{collections::HashMap, path::PathBuf};
+
+use one_sdk::{OneClient, OneSdkError, SecurityValuesMap};
+use serde_json::json;
+
+fn client() -> OneClient {
+    let core_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets/test-core.wasm");
+    assert!(
+        core_path.exists(),
+        "{} does not exist, run `make test_rust_host`",
+        core_path.display()
+    );
+
+    let mut client = OneClient::new("superface").unwrap();
+    client.set_core_path(core_path);
+    client.set_developer_dump_dir(std::env::temp_dir());
+
+    client
+}
+
+fn perform(client: &mut OneClient, usecase: &str) -> Result<bool, OneSdkError> {
+    client.perform(
+        "wasm-sdk/example",
+        "localhost",
+        usecase,
+        &json!({}),
+        &HashMap::new(),
+        &SecurityValuesMap::new(),
+    )
+}
+
+#[test]
+#[ignore = "requires assets/test-core.wasm"]
+fn test_perform() {
+    let mut client = client();
+
+    assert!(perform(&mut client, "CORE_PERFORM_TRUE").unwrap());
+    client.destroy();
+}
+
+#[test]
+#[ignore = "requires assets/test-core.wasm"]
+fn test_invalid_user_input() {
+    let mut client = client();
+
+    assert!(matches!(
+        perform(&mut client, "CORE_PERFORM_INPUT_VALIDATION_ERROR"),
+        Err(OneSdkError::ValidationError { .. })
+    ));
+}
+
+#[test]
+#[ignore = "requires assets/test-core.wasm"]
+fn test_panicked_core() {
+    let mut client = client();
+
+    assert!(matches!(
+        perform(&mut client, "CORE_PERFORM_PANIC"),
+        Err(OneSdkError::UnexpectedError { ref name, .. }) if name == "WebAssemblyRuntimeError"
+    ));
+    assert!(perform(&mut client, "CORE_PERFORM_TRUE").unwrap());
+}